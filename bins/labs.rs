@@ -5,6 +5,7 @@ mod robot;
 fn main() {
     let mut builders: Vec<(_, fn(&mut World))> = vec![
         ("Ferbot", robot::init_world),
+        ("Ferbot arm", robot::init_arm),
     ];
 
     let test = WorldApp::from_builders(0, builders);
@@ -1,16 +1,39 @@
 use std::fs::File;
 use std::io::BufReader;
-use bevy::prelude::*;
 use bevy_obj::*;
-use nalgebra::{Point3, point, Vector3};
+use std::f32::consts::FRAC_PI_2;
+use nalgebra::{Isometry3, Point3, point, Vector3};
 use obj::raw::object::Polygon;
 use parry3d::shape::SharedShape;
 
 
 
 use world::{World, WorldRender};
+use world::harness::{Conveyor, Robot, Sink, Spawner};
+use bluster::dynamics::{Actuator, InverseDynamics, JointController, JointDrive, JointDrives, JointFriction, PidGains};
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::{GripperModel, KinematicChain, ParallelJaw, SelfCollisionSampler, Tool, VacuumCup, WorkObject};
+use bluster::pipeline::distance_monitor::SafetyMargin;
+use bluster::signals::{LightBarrier, SignalKind};
 use bluster::prelude::*;
 
+const ARM_PROGRAM: &str = "; Ferbot demo
+VAR home = JOINTS(0, 0, 0, 0, 0, 0)
+VAR p1 = POSE(0, 3, 2, 0, 0, 0)
+VAR p2 = POSE(0, 3, -2, 0, 0, 0)
+VAR via = POSE(0, 5, 0, 0, 0, 0)
+TOOL 1
+BASE 1
+LOOP
+    PTP home VEL 50
+    LIN p1 VEL 2
+    CIRC via, p2 VEL 2
+    SET OUT[1] = TRUE
+    WAIT TIME 0.5
+    SET OUT[1] = FALSE
+ENDLOOP
+";
+
 
 pub fn init_world(world: &mut World) {
     let mut objects = ObjectSet::new();
@@ -69,6 +92,161 @@ pub fn init_world(world: &mut World) {
     world.look_at(point![100.0, 100.0, 100.0], Point3::origin());
 }
 
+/// A six-axis arm standing on `ground` at `base`, with its drives and tools.
+fn arm(objects: &mut ObjectSet, ground: ObjectHandle, name: &str, base: Isometry3<f32>) -> Robot {
+    let link = |objects: &mut ObjectSet, center: Vector3<f32>, half_extents: Vector3<f32>| {
+        let shape = SharedShape::compound(vec![(
+            Isometry3::translation(center.x, center.y, center.z),
+            SharedShape::cuboid(half_extents.x, half_extents.y, half_extents.z),
+        )]);
+
+        Some(objects.insert(ObjectBuilder::new(shape)))
+    };
+
+    let mut chain = KinematicChain::new(base);
+    let limits = [-170f32.to_radians(), 170f32.to_radians()];
+
+    let link1 = link(objects, Vector3::new(0.0, 2.0, 0.0), Vector3::new(1.0, 2.0, 1.0));
+    // The base link stands on the ground
+    objects.exclude_pair(ground, link1.unwrap());
+    let link2 = link(objects, Vector3::new(0.0, 2.5, 0.0), Vector3::new(0.5, 2.5, 0.5));
+    let link3 = link(objects, Vector3::new(2.0, 0.0, 0.0), Vector3::new(2.0, 0.4, 0.4));
+    let link4 = link(objects, Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.5, 0.3, 0.3));
+    let link5 = link(objects, Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.5, 0.25, 0.25));
+    let link6 = link(objects, Vector3::new(0.1, 0.0, 0.0), Vector3::new(0.1, 0.4, 0.4));
+
+    chain.push_link(RevoluteJoint::new(Vector3::y_axis()).limits(limits), link1)
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![0.0, 4.0, 0.0]).limits(limits), link2)
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![0.0, 5.0, 0.0]).limits(limits), link3)
+        .push_link(RevoluteJoint::new(Vector3::x_axis()).local_anchor1(point![4.0, 0.0, 0.0]).limits(limits), link4)
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![1.0, 0.0, 0.0]).limits(limits), link5)
+        .push_link(RevoluteJoint::new(Vector3::x_axis()).local_anchor1(point![1.0, 0.0, 0.0]).limits(limits), link6)
+        .set_flange(Isometry3::translation(0.2, 0.0, 0.0));
+
+    for link in &mut chain.links {
+        link.friction = JointFriction::new(20.0, 2.0);
+    }
+
+    let tool = |length: f32, half_width: f32| SharedShape::compound(vec![(
+        Isometry3::translation(length / 2.0, 0.0, 0.0),
+        SharedShape::cuboid(length / 2.0, half_width, half_width),
+    )]);
+
+    let mut robot = Robot::with_chain(chain);
+    robot.name = name.to_string();
+
+    // PID gains for a 40 rad/s bandwidth, from the inertia every joint moves at the zero position
+    let zeros = robot.chain.zeros();
+    let drives = (0..robot.chain.ndofs()).map(|i| {
+        let mut acceleration = robot.chain.zeros();
+        acceleration[i] = 1.0;
+        let inertia = InverseDynamics { gravity: Vector3::zeros() }
+            .torques(&robot.chain, objects, &zeros, &zeros, &acceleration, None)
+            .map_or(1.0, |torques| torques[i]);
+
+        JointDrive::new(
+            JointController::pid(PidGains::new(1600.0 * inertia, 1600.0 * inertia, 80.0 * inertia)),
+            Actuator::new(100.0, 50.0, 300.0).with_encoder(4096),
+        )
+    });
+    robot.drives = Some(JointDrives::new(drives.collect()));
+    robot.collision_matrix = Some(SelfCollisionSampler { samples: 2000, ..Default::default() }
+        .generate(&robot.chain, objects));
+    robot.insert_tool(1, Tool::new("Gripper", Isometry3::translation(0.8, 0.0, 0.0))
+        .shape(tool(0.8, 0.3))
+        .mass(2.0, point![0.4, 0.0, 0.0]));
+    robot.insert_tool(2, Tool::new("Pointer", Isometry3::translation(1.5, 0.0, 0.0))
+        .shape(tool(1.5, 0.05))
+        .mass(0.5, point![0.75, 0.0, 0.0]));
+
+    // The jaws close across the flange y axis, the cup faces along its x axis
+    let jaws = ParallelJaw::new(
+        Isometry3::new(Vector3::new(0.5, 0.0, 0.0), Vector3::z() * FRAC_PI_2),
+        0.1,
+        1.2,
+        Vector3::new(0.05, 0.4, 0.2),
+    );
+    robot.insert_tool(3, Tool::new("Parallel jaws", Isometry3::translation(0.5, 0.0, 0.0))
+        .gripper(GripperModel::ParallelJaw(jaws))
+        .mass(1.0, point![0.5, 0.0, 0.0]));
+    let cup = VacuumCup::new(Isometry3::new(Vector3::new(0.6, 0.0, 0.0), Vector3::y() * FRAC_PI_2), 0.25);
+    robot.insert_tool(4, Tool::new("Vacuum", Isometry3::translation(0.6, 0.0, 0.0))
+        .gripper(GripperModel::Vacuum(cup))
+        .mass(0.5, point![0.3, 0.0, 0.0]));
+
+    robot
+}
+
+pub fn init_arm(world: &mut World) {
+    let mut objects = ObjectSet::new();
+    let ground = objects.insert(ObjectBuilder::cuboid(20.0, 0.5, 20.0)
+        .position(Isometry3::translation(0.0, -0.5, 0.0)));
+
+    // A fixture with a part on top of it, the targets of the program are expressed on its top face
+    let fixture = objects.insert(ObjectBuilder::cuboid(1.5, 1.5, 1.5)
+        .position(Isometry3::translation(5.0, 1.5, 0.0)));
+    objects.insert_with_parent(
+        ObjectBuilder::cuboid(0.5, 0.25, 0.5),
+        fixture,
+        Isometry3::translation(0.0, 1.75, 0.0),
+    );
+
+    // A belt running along x, behind the robot
+    let belt = objects.insert(ObjectBuilder::cuboid(4.0, 0.25, 0.75)
+        .position(Isometry3::translation(0.0, 0.75, -7.0)));
+
+    // Loose boxes dropped next to the arm, they fall, stack and get pushed around
+    for i in 0..3 {
+        objects.insert(ObjectBuilder::cuboid(0.5, 0.5, 0.5)
+            .position(Isometry3::translation(-4.0 + 0.1 * i as f32, 1.0 + 1.5 * i as f32, 3.0))
+            .dynamic(true));
+    }
+
+    // Two arms sharing the fixture, the second one facing it from the side
+    let mut robots = [
+        arm(&mut objects, ground, "left", Isometry3::identity()),
+        arm(&mut objects, ground, "right", Isometry3::new(Vector3::new(5.0, 0.0, 7.5), Vector3::y() * FRAC_PI_2)),
+    ];
+
+    for robot in &mut robots {
+        robot.work_objects.insert(1, WorkObject::attached("Fixture", fixture, Isometry3::translation(0.0, 1.5, 0.0)));
+    }
+
+    world.init_world(objects);
+
+    for robot in robots {
+        world.add_robot(robot);
+    }
+
+    world.add_obstacle(fixture, SafetyMargin::new(1.0, 0.25));
+    world.set_program_text(ARM_PROGRAM);
+
+    // A light barrier across the loose boxes reports whether any is left, on input 2
+    let _ = world.add_signal("part_present", SignalKind::DigitalInput, 2);
+    world.add_sensor(LightBarrier::new("part_present", point![-6.0, 0.5, 3.0], Vector3::x_axis(), 4.0))
+        .expect("the light barrier signal is declared");
+
+    // Parts dropped at the start of the belt, carried along and removed at its end, while output 2 is on
+    let _ = world.add_signal("conveyor_on", SignalKind::DigitalOutput, 2);
+    let _ = world.add_signal("parts_done", SignalKind::AnalogInput, 1);
+    let _ = world.set_signal("conveyor_on", true);
+    world.add_plugin(Conveyor::new(belt, Vector3::x(), 1.0).with_signal("conveyor_on"))
+        .expect("the conveyor signal is declared");
+    world.add_plugin(Spawner::new(
+        ObjectBuilder::cuboid(0.3, 0.3, 0.3).dynamic(true),
+        Isometry3::translation(-3.5, 1.6, -7.0),
+        2.0,
+        7,
+    )
+        .with_jitter(Vector3::new(0.0, 0.0, 0.3), 0.5)
+        .with_signal("conveyor_on"))
+        .expect("the spawner signal is declared");
+    world.add_plugin(Sink::new(SharedShape::cuboid(0.5, 1.0, 1.0), Isometry3::translation(4.0, 1.5, -7.0))
+        .with_signal("parts_done"))
+        .expect("the sink signal is declared");
+    world.look_at(point![20.0, 15.0, 20.0], point![0.0, 5.0, 0.0]);
+}
+
 fn models() -> Vec<String> {
     vec!["bins/assets/3d/link1.obj".to_string()]
 }
\ No newline at end of file
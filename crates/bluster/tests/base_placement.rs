@@ -0,0 +1,88 @@
+use nalgebra::{point, Isometry3, Vector3};
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::{KinematicChain, ReachabilityMap, ReachabilitySampler};
+use bluster::mesh::ObjectSet;
+use bluster::pipeline::query_pipeline::QueryPipeline;
+use bluster::planning::{BasePlacement, BasePlacementSearch};
+
+
+/// A planar arm in the xy plane with 1 m, 1 m and 0.5 m links, reaching any heading within its range.
+fn planar_arm() -> KinematicChain {
+    let joint = |anchor: f32| RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![anchor, 0.0, 0.0]);
+
+    let mut chain = KinematicChain::default();
+    chain.push_link(joint(0.0), None).push_link(joint(1.0), None).push_link(joint(1.0), None);
+    chain.flange = Isometry3::translation(0.5, 0.0, 0.0);
+
+    chain
+}
+
+fn map(chain: &KinematicChain) -> ReachabilityMap {
+    ReachabilitySampler { samples: 40_000, seed: 5, ..ReachabilitySampler::default() }.generate(chain)
+}
+
+/// Searches the xy plane around the targets, rotating the base around z.
+fn search(chain: &KinematicChain, targets: &[Isometry3<f32>]) -> Option<BasePlacement> {
+    let mut search = BasePlacementSearch::new(point![-2.0, -2.0, 0.0], point![4.0, 4.0, 0.0]);
+    search.steps = [7, 7, 1];
+    search.up = Vector3::z_axis();
+    search.seed = 3;
+
+    let objects = ObjectSet::new();
+    let mut query_pipeline = QueryPipeline::new();
+    query_pipeline.update(&objects);
+
+    search.search(chain, &map(chain), &objects, &query_pipeline, targets)
+}
+
+fn targets() -> Vec<Isometry3<f32>> {
+    vec![
+        Isometry3::new(Vector3::new(0.0, 0.0, 0.0), Vector3::zeros()),
+        Isometry3::new(Vector3::new(2.5, 0.0, 0.0), Vector3::z() * 1.0),
+        Isometry3::new(Vector3::new(1.2, 2.0, 0.0), Vector3::z() * -2.0),
+    ]
+}
+
+#[test]
+fn reaches_every_target() {
+    let mut chain = planar_arm();
+    let targets = targets();
+    let placement = search(&chain, &targets).unwrap();
+
+    assert_eq!(placement.reached(), 3);
+    assert!(placement.manipulability > 0.0);
+    assert_eq!(placement.base.translation.z, 0.0);
+
+    chain.base = placement.base;
+
+    for (q, target) in placement.solutions.iter().zip(&targets) {
+        let reached = chain.end_effector(q.as_ref().unwrap());
+        assert!((reached.translation.vector - target.translation.vector).norm() < 1e-3, "{:?} isn't at {:?}", reached, target);
+        assert!(reached.rotation.angle_to(&target.rotation) < 1e-3);
+    }
+
+    // The same seed gives the same placement
+    assert_eq!(search(&planar_arm(), &targets), Some(placement));
+}
+
+#[test]
+fn unreachable_targets_are_ranked_last() {
+    let mut targets = targets();
+    targets.push(Isometry3::translation(20.0, 0.0, 0.0));
+    let placement = search(&planar_arm(), &targets).unwrap();
+
+    assert_eq!(placement.reached(), 3);
+    assert_eq!(placement.solutions[3], None);
+}
+
+#[test]
+fn placements_rank_by_reach_then_manipulability() {
+    let placement = |solutions: usize, manipulability: f32| BasePlacement {
+        base: Isometry3::identity(),
+        solutions: vec![Some(planar_arm().zeros()); solutions],
+        manipulability,
+    };
+
+    assert!(placement(2, 0.1).compare(&placement(1, 0.9)).is_gt());
+    assert!(placement(2, 0.1).compare(&placement(2, 0.9)).is_lt());
+}
@@ -0,0 +1,84 @@
+use std::f32::consts::PI;
+use nalgebra::{point, Vector3};
+use bluster::data::random::Random;
+use bluster::joint::{GenericJoint, JointAxesMask, JointAxis, RevoluteJoint};
+use bluster::kinematics::{KinematicChain, LinkPairStatus, SelfCollisionSampler};
+use bluster::mesh::{ObjectBuilder, ObjectSet};
+
+
+/// A planar arm of 1 m links with boxes at the joints, then a link without object and one with a box
+/// enclosing the whole arm.
+fn arm(objects: &mut ObjectSet, elbow_limits: Option<[f32; 2]>) -> KinematicChain {
+    let mut chain = KinematicChain::default();
+    let mut box_link = |half: f32| Some(objects.insert(ObjectBuilder::cuboid(half, half, half)));
+    let joint = || RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![1.0, 0.0, 0.0]);
+    let elbow = match elbow_limits {
+        Some(limits) => joint().limits(limits),
+        None => joint(),
+    };
+
+    chain.push_link(RevoluteJoint::new(Vector3::z_axis()), box_link(0.3))
+        .push_link(elbow, box_link(0.3))
+        .push_link(joint(), box_link(0.3))
+        .push_link(joint(), None)
+        .push_link(joint(), box_link(10.0));
+
+    chain
+}
+
+fn sampler() -> SelfCollisionSampler {
+    SelfCollisionSampler { samples: 2000, seed: 7, ..SelfCollisionSampler::default() }
+}
+
+#[test]
+fn generated_matrix() {
+    let mut objects = ObjectSet::new();
+    let chain = arm(&mut objects, None);
+    let matrix = sampler().generate(&chain, &objects);
+
+    assert_eq!(matrix.get(0, 1), LinkPairStatus::Adjacent);
+    assert_eq!(matrix.get(1, 2), LinkPairStatus::Adjacent);
+    // The link without object is skipped, its neighbours touch each other
+    assert_eq!(matrix.get(2, 4), LinkPairStatus::Adjacent);
+    assert_eq!(matrix.get(1, 3), LinkPairStatus::NeverColliding);
+    assert_eq!(matrix.get(0, 4), LinkPairStatus::AlwaysColliding);
+    assert_eq!(matrix.get(1, 4), LinkPairStatus::AlwaysColliding);
+    // The wrist reaches back to the base only with the elbow folded
+    assert_eq!(matrix.get(0, 2), LinkPairStatus::Check);
+    assert_eq!(matrix.pairs_to_check().collect::<Vec<_>>(), [(0, 2)]);
+
+    // The same seed gives the same matrix
+    assert_eq!(sampler().generate(&chain, &objects), matrix);
+}
+
+#[test]
+fn limits_restrict_the_samples() {
+    let mut objects = ObjectSet::new();
+    let chain = arm(&mut objects, Some([-1.0, 1.0]));
+    let matrix = sampler().generate(&chain, &objects);
+
+    assert_eq!(matrix.get(0, 2), LinkPairStatus::NeverColliding);
+    assert_eq!(matrix.pairs_to_check().count(), 0);
+}
+
+#[test]
+fn sampling_bounds() {
+    let mut slider = GenericJoint::new(JointAxesMask::ANGLE_AXES | JointAxesMask::Y | JointAxesMask::Z);
+    slider.set_limits(JointAxis::X, [4.0, 5.0]);
+
+    let mut chain = KinematicChain::default();
+    chain.push_link(RevoluteJoint::new(Vector3::z_axis()), None)
+        .push_link(slider, None)
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).limits([2.0 * PI, 3.0 * PI]), None)
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).limits([0.5, f32::MAX]), None);
+
+    // Only the unbounded sides are replaced by one turn
+    assert_eq!(chain.sampling_bounds(), [(-PI, PI), (4.0, 5.0), (2.0 * PI, 3.0 * PI), (0.5, 0.5 + 2.0 * PI)]);
+
+    let mut random = Random::new(3);
+
+    for _ in 0..1000 {
+        let q = chain.random_joints(&mut random);
+        assert!(chain.within_limits(&q), "{:?} is out of the limits", q);
+    }
+}
@@ -0,0 +1,68 @@
+use std::f32::consts::FRAC_PI_2;
+use nalgebra::{dvector, point, Isometry3, Vector3};
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::KinematicChain;
+use bluster::planning::{ConstraintProjector, TaskConstraint};
+
+
+/// A planar arm in the xy plane with two 1 m links, the elbow limited to one side.
+fn planar_arm() -> KinematicChain {
+    let mut chain = KinematicChain::default();
+    chain.push_link(RevoluteJoint::new(Vector3::z_axis()), None)
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![1.0, 0.0, 0.0]).limits([0.0, 2.5]), None);
+    chain.flange = Isometry3::translation(1.0, 0.0, 0.0);
+
+    chain
+}
+
+#[test]
+fn project_onto_plane() {
+    let chain = planar_arm();
+    let projector = ConstraintProjector::default();
+    let plane = [TaskConstraint::on_plane(point![1.2, 0.0, 0.0], Vector3::x_axis(), 0.0)];
+    let start = dvector![0.3, 0.5];
+
+    assert!(ConstraintProjector::violation(&chain, &chain.tool, &plane, &start) > 0.1);
+
+    let q = projector.project(&chain, &chain.tool, &plane, &start).unwrap();
+    let tcp = chain.end_effector(&q);
+
+    assert!((tcp.translation.x - 1.2).abs() <= projector.tolerance, "{:?} isn't on the plane", tcp.translation);
+    assert!(chain.within_limits(&q));
+    // The projection moves to a nearby configuration, not across the workspace
+    assert!((q - start).amax() < 1.0);
+}
+
+#[test]
+fn satisfied_constraints_keep_the_configuration() {
+    let chain = planar_arm();
+    let q = dvector![0.3, 0.5];
+    let tcp = chain.end_effector(&q);
+    let plane = [TaskConstraint::on_plane(tcp.translation.vector.into(), Vector3::y_axis(), 0.01)];
+
+    assert_eq!(ConstraintProjector::default().project(&chain, &chain.tool, &plane, &q), Some(q));
+}
+
+#[test]
+fn project_onto_orientation_and_plane() {
+    let chain = planar_arm();
+    let projector = ConstraintProjector { max_iterations: 200, ..ConstraintProjector::default() };
+    // The last link pointing along y, its tip on the plane x = 0.5
+    let constraints = [
+        TaskConstraint::orientation(Vector3::x_axis(), Vector3::y_axis(), 0.01),
+        TaskConstraint::on_plane(point![0.5, 0.0, 0.0], Vector3::x_axis(), 0.01),
+    ];
+
+    let q = projector.project(&chain, &chain.tool, &constraints, &dvector![0.2, 0.8]).unwrap();
+
+    assert!(ConstraintProjector::violation(&chain, &chain.tool, &constraints, &q) <= projector.tolerance);
+    assert!((q[0] + q[1] - FRAC_PI_2).abs() < 0.01 + projector.tolerance);
+}
+
+#[test]
+fn unreachable_constraints() {
+    let chain = planar_arm();
+    let plane = [TaskConstraint::on_plane(point![3.0, 0.0, 0.0], Vector3::x_axis(), 0.01)];
+
+    assert_eq!(ConstraintProjector::default().project(&chain, &chain.tool, &plane, &dvector![0.3, 0.5]), None);
+}
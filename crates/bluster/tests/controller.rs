@@ -0,0 +1,130 @@
+use std::f32::consts::PI;
+use nalgebra::{dvector, point, Vector3};
+use bluster::dynamics::{Actuator, ForwardDynamics, JointController, JointDrive, JointDrives, JointSetpoint, Pid, PidGains};
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::KinematicChain;
+use bluster::mesh::{MassProperties, ObjectBuilder, ObjectSet};
+
+
+/// A single 1 kg link with its center of mass 0.5 m from the joint, moving in the vertical plane.
+fn link(objects: &mut ObjectSet) -> KinematicChain {
+    let properties = MassProperties::new(point![0.5, 0.0, 0.0], 1.0, Vector3::repeat(0.01));
+    let object = objects.insert(ObjectBuilder::cuboid(0.5, 0.05, 0.05).mass_properties(properties));
+
+    let mut chain = KinematicChain::default();
+    chain.push_link(RevoluteJoint::new(Vector3::z_axis()), Some(object));
+
+    chain
+}
+
+/// Drives the link to 1 rad for `duration` seconds, returning the final position and the largest torque applied.
+fn drive_to_target(drive: JointDrive, duration: f32) -> (f32, f32) {
+    let mut objects = ObjectSet::new();
+    let chain = link(&mut objects);
+    let dynamics = ForwardDynamics::default();
+    let mut drives = JointDrives::uniform(1, drive);
+    let (mut q, mut qd) = (chain.zeros(), chain.zeros());
+    let mut peak: f32 = 0.0;
+    let dt = 0.005;
+
+    for _ in 0..(duration / dt) as usize {
+        let setpoints = drives.setpoints(&dvector![1.0], dt);
+        let torques = drives.torques(&setpoints, &q, &qd, dt);
+        peak = peak.max(torques[0].abs());
+        dynamics.step(&chain, &objects, &mut q, &mut qd, &torques, None, dt).unwrap();
+    }
+
+    (q[0], peak)
+}
+
+#[test]
+fn pid_terms() {
+    let mut pid = Pid::new(PidGains::new(2.0, 10.0, 0.5).with_integral_limit(0.3));
+
+    assert_eq!(pid.update(1.0, 0.0, 0.01), 2.0 + 0.1);
+    assert_eq!(pid.update(0.0, -2.0, 0.01), 0.1 - 1.0);
+
+    // The integral stops growing at its limit, and unwinds as soon as the error reverses
+    for _ in 0..100 {
+        pid.update(1.0, 0.0, 0.01);
+    }
+
+    assert_eq!(pid.update(0.0, 0.0, 0.01), 0.3);
+    assert!((pid.update(-1.0, 0.0, 0.01) - (-2.0 + 0.2)).abs() < 1e-6);
+
+    pid.reset();
+    assert_eq!(pid.update(0.0, 0.0, 0.01), 0.0);
+}
+
+#[test]
+fn controllers() {
+    let setpoint = JointSetpoint { position: 1.0, velocity: 0.5, acceleration: 0.0, torque: 3.0 };
+
+    let mut pid = JointController::pid(PidGains::new(10.0, 0.0, 2.0));
+    assert_eq!(pid.torque(&setpoint, 0.8, 0.0, 0.01), 10.0 * 0.2 + 2.0 * 0.5 + 3.0);
+
+    // The position loop commands 0.5 + 4 * 0.2 rad/s, the velocity loop follows it
+    let mut cascade = JointController::cascade(4.0, PidGains::new(10.0, 0.0, 0.0));
+    assert!((cascade.torque(&setpoint, 0.8, 1.0, 0.01) - (10.0 * 0.3 + 3.0)).abs() < 1e-5);
+}
+
+#[test]
+fn actuator_limits() {
+    let actuator = Actuator::new(100.0, 2.0, 300.0);
+
+    assert_eq!(actuator.max_joint_torque(), 200.0);
+    assert_eq!(actuator.max_joint_velocity(), 3.0);
+    assert_eq!(actuator.output(500.0, 0.0), 200.0);
+    assert_eq!(actuator.output(-50.0, 1.0), -50.0);
+    // Past the velocity limit the motor brakes, but doesn't accelerate
+    assert_eq!(actuator.output(50.0, 3.5), 0.0);
+    assert_eq!(actuator.output(-50.0, 3.5), -50.0);
+}
+
+#[test]
+fn actuator_sensing() {
+    let mut actuator = Actuator::default().with_backlash(0.02);
+
+    assert_eq!(actuator.sense(0.0, 0.01), (0.0, 0.0));
+    assert_eq!(actuator.sense(0.1, 0.01).0, 0.09);
+    // Reversing, the measure stays put until the play is taken up on the other side
+    assert_eq!(actuator.sense(0.085, 0.01).0, 0.09);
+    assert!((actuator.sense(0.05, 0.01).0 - 0.06).abs() < 1e-6);
+
+    let mut encoder = Actuator::new(2.0, 1.0, 1.0).with_encoder(100);
+    let resolution = PI / 100.0;
+    let (position, _) = encoder.sense(0.05, 0.1);
+    let (_, velocity) = encoder.sense(0.05 + 3.0 * resolution, 0.1);
+
+    assert!((position - 2.0 * resolution).abs() < 1e-6, "{}", position);
+    assert!((velocity - 3.0 * resolution / 0.1).abs() < 1e-4, "{}", velocity);
+}
+
+#[test]
+fn drives_track_the_command() {
+    let mut drives = JointDrives::uniform(1, JointDrive::new(JointController::pid(PidGains::new(1.0, 0.0, 0.0)), Actuator::default()));
+    drives.setpoints(&dvector![0.0], 0.1);
+    let setpoints = drives.setpoints(&dvector![0.2], 0.1);
+    assert!((setpoints[0].velocity - 2.0).abs() < 1e-5 && (setpoints[0].acceleration - 20.0).abs() < 1e-3);
+
+    // Without an integral term, holding the link against gravity leaves an offset
+    let pd = JointDrive::new(JointController::pid(PidGains::new(200.0, 0.0, 30.0)), Actuator::default());
+    let (position, _) = drive_to_target(pd, 3.0);
+    assert!(position < 0.99, "{}", position);
+
+    let pid = JointDrive::new(JointController::pid(PidGains::new(200.0, 400.0, 30.0)), Actuator::default());
+    let (position, _) = drive_to_target(pid, 3.0);
+    assert!((position - 1.0).abs() < 1e-3, "{}", position);
+
+    let cascade = JointDrive::new(JointController::cascade(10.0, PidGains::new(20.0, 50.0, 0.0)), Actuator::default());
+    let (position, _) = drive_to_target(cascade, 3.0);
+    assert!((position - 1.0).abs() < 1e-3, "{}", position);
+
+    // A weak motor still gets there, more slowly, never exceeding its torque; the integral is limited to what it
+    // can deliver
+    let gains = PidGains::new(200.0, 400.0, 30.0).with_integral_limit(3.0);
+    let weak = JointDrive::new(JointController::pid(gains), Actuator::new(10.0, 0.5, f32::MAX));
+    let (position, peak) = drive_to_target(weak, 6.0);
+    assert!((position - 1.0).abs() < 1e-2, "{}", position);
+    assert!(peak <= 5.0 + 1e-5, "{}", peak);
+}
@@ -0,0 +1,202 @@
+use std::f32::consts::FRAC_PI_2;
+use nalgebra::{dvector, point, DMatrix, DVector, Isometry3, Vector3};
+use bluster::data::random::Random;
+use bluster::dynamics::{ForwardDynamics, Integrator, InverseDynamics, JointFriction};
+use bluster::errors::KinematicsError;
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::KinematicChain;
+use bluster::mesh::{MassProperties, ObjectBuilder, ObjectSet};
+
+
+const G: f32 = 9.81;
+const MASSES: [f32; 2] = [2.0, 1.0];
+/// The angular inertia of both links around their center of mass.
+const INERTIA: f32 = 0.01;
+
+/// A vertical planar arm in the xy plane, gravity along -y, with two 1 m links whose centers of mass are halfway.
+fn two_link_arm(objects: &mut ObjectSet) -> KinematicChain {
+    let mut link = |mass: f32| {
+        let properties = MassProperties::new(point![0.5, 0.0, 0.0], mass, Vector3::repeat(INERTIA));
+        Some(objects.insert(ObjectBuilder::cuboid(0.5, 0.05, 0.05).mass_properties(properties)))
+    };
+
+    let mut chain = KinematicChain::default();
+    chain.push_link(RevoluteJoint::new(Vector3::z_axis()), link(MASSES[0]))
+        .push_link(RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![1.0, 0.0, 0.0]), link(MASSES[1]));
+    chain.flange = Isometry3::translation(1.0, 0.0, 0.0);
+
+    chain
+}
+
+/// The textbook equations of motion of the two-link arm.
+fn expected_torques(q: &DVector<f32>, qd: &DVector<f32>, qdd: &DVector<f32>) -> DVector<f32> {
+    let [m1, m2] = MASSES;
+    let (l1, lc) = (1.0, 0.5);
+    let m11 = m1 * lc * lc + m2 * (l1 * l1 + lc * lc + 2.0 * l1 * lc * q[1].cos()) + 2.0 * INERTIA;
+    let m12 = m2 * (lc * lc + l1 * lc * q[1].cos()) + INERTIA;
+    let m22 = m2 * lc * lc + INERTIA;
+    let h = -m2 * l1 * lc * q[1].sin();
+    let g1 = (m1 * lc + m2 * l1) * G * q[0].cos() + m2 * lc * G * (q[0] + q[1]).cos();
+    let g2 = m2 * lc * G * (q[0] + q[1]).cos();
+
+    dvector![
+        m11 * qdd[0] + m12 * qdd[1] + h * qd[1] * qd[1] + 2.0 * h * qd[0] * qd[1] + g1,
+        m12 * qdd[0] + m22 * qdd[1] - h * qd[0] * qd[0] + g2
+    ]
+}
+
+fn assert_close(torques: &DVector<f32>, expected: &DVector<f32>) {
+    assert!((torques - expected).amax() < 1e-3 * expected.amax().max(1.0), "{} != {}", torques, expected);
+}
+
+#[test]
+fn gravity_torques_of_two_link_arm() {
+    let mut objects = ObjectSet::new();
+    let chain = two_link_arm(&mut objects);
+    let dynamics = InverseDynamics::default();
+    let zeros = chain.zeros();
+
+    // Stretched out horizontally, both links pull down with their whole lever arm
+    let torques = dynamics.gravity_torques(&chain, &objects, &zeros, None).unwrap();
+    assert_close(&torques, &dvector![(2.0 * 0.5 + 1.0 * 1.5) * G, 1.0 * 0.5 * G]);
+
+    // Hanging straight down, nothing has to be held
+    let hanging = dynamics.gravity_torques(&chain, &objects, &dvector![-FRAC_PI_2, 0.0], None).unwrap();
+    assert!(hanging.amax() < 1e-4, "{}", hanging);
+
+    for q in [dvector![0.3, -1.2], dvector![2.0, 0.7]] {
+        let torques = dynamics.gravity_torques(&chain, &objects, &q, None).unwrap();
+        assert_close(&torques, &expected_torques(&q, &zeros, &zeros));
+    }
+}
+
+#[test]
+fn torques_of_two_link_arm() {
+    let mut objects = ObjectSet::new();
+    let chain = two_link_arm(&mut objects);
+    let mut random = Random::new(1);
+    let mut state = || DVector::from_fn(2, |_, _| random.range(-3.0, 3.0));
+
+    for _ in 0..20 {
+        let (q, qd, qdd) = (state(), state(), state());
+        let torques = InverseDynamics::default().torques(&chain, &objects, &q, &qd, &qdd, None).unwrap();
+
+        assert_close(&torques, &expected_torques(&q, &qd, &qdd));
+    }
+}
+
+#[test]
+fn payloads() {
+    let mut objects = ObjectSet::new();
+    let chain = two_link_arm(&mut objects);
+    let dynamics = InverseDynamics::default();
+    let zeros = chain.zeros();
+
+    // A 1 kg point mass at the flange adds its weight with a 2 m and a 1 m lever arm
+    let payload = MassProperties::new(point![0.0, 0.0, 0.0], 1.0, Vector3::zeros());
+    let empty = dynamics.gravity_torques(&chain, &objects, &zeros, None).unwrap();
+    let loaded = dynamics.gravity_torques(&chain, &objects, &zeros, Some(&payload)).unwrap();
+    assert_close(&(loaded - &empty), &dvector![2.0 * G, G]);
+
+    // The first joint gives out first
+    let limits = dvector![empty[0] + 10.0 * G, empty[1] + 10.0 * G];
+    let max = dynamics.max_payload(&chain, &objects, &zeros, &point![0.0, 0.0, 0.0], &limits).unwrap();
+    assert!((max - 5.0).abs() < 1e-3, "{}", max);
+    assert_eq!(dynamics.max_payload(&chain, &objects, &zeros, &point![0.0, 0.0, 0.0], &(empty * 0.5)), Ok(0.0));
+
+    let peaks = dynamics.peak_torques(&chain, &objects, [(&zeros, &zeros, &zeros), (&dvector![-1.0, 0.0], &zeros, &zeros)], None).unwrap();
+    assert_close(&peaks, &dynamics.gravity_torques(&chain, &objects, &zeros, None).unwrap());
+}
+
+#[test]
+fn dof_mismatch() {
+    let mut objects = ObjectSet::new();
+    let chain = two_link_arm(&mut objects);
+
+    assert_eq!(
+        InverseDynamics::default().gravity_torques(&chain, &objects, &dvector![0.0], None),
+        Err(KinematicsError::DofMismatch { expected: 2, found: 1 }),
+    );
+}
+
+/// The kinetic and potential energy of the two-link arm, the mass matrix coming from inverse dynamics.
+fn energy(chain: &KinematicChain, objects: &ObjectSet, q: &DVector<f32>, qd: &DVector<f32>) -> f32 {
+    let weightless = InverseDynamics { gravity: Vector3::zeros() };
+    let zeros = chain.zeros();
+    let mass_matrix = DMatrix::from_columns(&[0, 1].map(|j| {
+        weightless.torques(chain, objects, q, &zeros, &DVector::from_fn(2, |i, _| (i == j) as u8 as f32), None).unwrap()
+    }));
+    let [m1, m2] = MASSES;
+    let potential = G * (m1 * 0.5 * q[0].sin() + m2 * (q[0].sin() + 0.5 * (q[0] + q[1]).sin()));
+
+    0.5 * qd.dot(&(mass_matrix * qd)) + potential
+}
+
+/// Lets the arm swing freely from horizontal for a second.
+fn swing(integrator: Integrator, substeps: usize) -> (DVector<f32>, DVector<f32>, f32) {
+    let mut objects = ObjectSet::new();
+    let chain = two_link_arm(&mut objects);
+    let dynamics = ForwardDynamics { integrator, substeps, ..ForwardDynamics::default() };
+    let (mut q, mut qd) = (dvector![0.0, 0.3], chain.zeros());
+    let start = energy(&chain, &objects, &q, &qd);
+
+    for _ in 0..100 {
+        dynamics.step(&chain, &objects, &mut q, &mut qd, &chain.zeros(), None, 0.01).unwrap();
+    }
+
+    let drift = (energy(&chain, &objects, &q, &qd) - start).abs() / start.abs().max(1.0);
+    (q, qd, drift)
+}
+
+#[test]
+fn forward_dynamics_inverts_inverse_dynamics() {
+    let mut objects = ObjectSet::new();
+    let chain = two_link_arm(&mut objects);
+    let mut random = Random::new(2);
+    let mut state = || DVector::from_fn(2, |_, _| random.range(-3.0, 3.0));
+
+    for _ in 0..20 {
+        let (q, qd, qdd) = (state(), state(), state());
+        let torques = InverseDynamics::default().torques(&chain, &objects, &q, &qd, &qdd, None).unwrap();
+        let accelerations = ForwardDynamics::default().accelerations(&chain, &objects, &q, &qd, &torques, None).unwrap();
+
+        assert!((&accelerations - &qdd).amax() < 1e-2, "{} != {}", accelerations, qdd);
+    }
+
+    // Gravity torques hold the arm still
+    let q = dvector![0.4, -0.9];
+    let holding = InverseDynamics::default().gravity_torques(&chain, &objects, &q, None).unwrap();
+    let still = ForwardDynamics::default().accelerations(&chain, &objects, &q, &chain.zeros(), &holding, None).unwrap();
+    assert!(still.amax() < 1e-3, "{}", still);
+}
+
+#[test]
+fn integrators_conserve_energy() {
+    let (q_rk4, qd_rk4, drift_rk4) = swing(Integrator::RungeKutta4, 10);
+    let (q_euler, qd_euler, drift_euler) = swing(Integrator::SemiImplicitEuler, 100);
+
+    assert!(drift_rk4 < 1e-3, "RK4 energy drift {}", drift_rk4);
+    // The Euler error is first order, it shrinks with the step
+    assert!(drift_euler < swing(Integrator::SemiImplicitEuler, 10).2 / 5.0, "Euler energy drift {}", drift_euler);
+    // Both follow the same motion
+    assert!((&q_rk4 - &q_euler).amax() < 1e-2 && (&qd_rk4 - &qd_euler).amax() < 5e-2, "{} {} != {} {}", q_rk4, qd_rk4, q_euler, qd_euler);
+}
+
+#[test]
+fn friction_and_limits() {
+    let mut objects = ObjectSet::new();
+    let mut chain = two_link_arm(&mut objects);
+    chain.links[0].joint = RevoluteJoint::new(Vector3::z_axis()).limits([-0.5, 0.5]).into();
+    chain.links[1].friction = JointFriction::new(1.0, 0.0);
+
+    let dynamics = ForwardDynamics::default();
+    let (mut q, mut qd) = (chain.zeros(), chain.zeros());
+
+    for _ in 0..500 {
+        dynamics.step(&chain, &objects, &mut q, &mut qd, &chain.zeros(), None, 0.01).unwrap();
+    }
+
+    // The shoulder falls onto its lower limit and stays there, the damped elbow settles hanging down
+    assert_eq!((q[0], qd[0]), (-0.5, 0.0));
+    assert!((q[0] + q[1] + FRAC_PI_2).abs() < 1e-2 && qd[1].abs() < 1e-2, "{} {}", q, qd);
+}
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+use nalgebra::{point, Isometry3, Vector3};
+use bluster::program;
+use bluster::program::export::{CellData, Krl, PostProcessor, Rapid, ToolLoad, UrScript};
+
+
+/// A gripper 0.2 m along the flange z axis, over a base 0.8 m in front of the robot.
+fn cell() -> CellData {
+    CellData {
+        tools: BTreeMap::from([(1, Isometry3::new(Vector3::new(0.0, 0.0, 0.2), Vector3::zeros()))]),
+        bases: BTreeMap::from([(1, Isometry3::new(Vector3::new(0.8, 0.0, 0.0), Vector3::z() * 0.5))]),
+        gripper_output: Some(4),
+        tool_loads: BTreeMap::from([(1, ToolLoad { mass: 2.0, center_of_mass: point![0.0, 0.0, 0.1] })]),
+        ..CellData::default()
+    }
+}
+
+/// Exports the fixture program, compared line by line to the expected output for a readable failure.
+fn check(post_processor: &dyn PostProcessor, expected: &str) {
+    let program = program::parse("cell", include_str!("export/cell.bluster")).unwrap();
+    let output = post_processor.export(&program, &cell()).unwrap();
+
+    for (i, (line, expected)) in output.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(line, expected, "{} output differs at line {}", post_processor.name(), i + 1);
+    }
+
+    assert_eq!(output.lines().count(), expected.lines().count(), "{} output length differs", post_processor.name());
+}
+
+#[test]
+fn krl() {
+    check(&Krl, include_str!("export/cell.src"));
+}
+
+#[test]
+fn rapid() {
+    check(&Rapid, include_str!("export/cell.mod"));
+}
+
+#[test]
+fn urscript() {
+    check(&UrScript, include_str!("export/cell.script"));
+}
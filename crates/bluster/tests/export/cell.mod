@@ -0,0 +1,30 @@
+MODULE cell
+    PERS tooldata tool1 := [TRUE, [[0.000, 0.000, 200.000], [1.000000, 0.000000, 0.000000, 0.000000]], [2.000, [0.000, 0.000, 100.000], [1, 0, 0, 0], 0, 0, 0]];
+    PERS wobjdata wobj1 := [FALSE, TRUE, "", [[800.000, 0.000, 0.000], [0.968912, 0.000000, 0.000000, 0.247404]], [[0, 0, 0], [1, 0, 0, 0]]];
+    VAR robtarget approach;
+    VAR jointtarget home;
+    VAR robtarget pick;
+    VAR robtarget via;
+
+    PROC main()
+        ConfJ \Off;
+        ConfL \Off;
+        home := [[0.000, -30.000, 60.000, 0.000, 45.000, 0.000], [9E9, 9E9, 9E9, 9E9, 9E9, 9E9]];
+        approach := [[500.000, 200.000, 300.000], [0.707107, 0.000000, 0.707107, 0.000000], [0, 0, 0, 0], [9E9, 9E9, 9E9, 9E9, 9E9, 9E9]];
+        pick := [[500.000, 200.000, 100.000], [0.707107, 0.000000, 0.707107, 0.000000], [0, 0, 0, 0], [9E9, 9E9, 9E9, 9E9, 9E9, 9E9]];
+        via := [[600.000, 300.000, 200.000], [0.707107, 0.000000, 0.707107, 0.000000], [0, 0, 0, 0], [9E9, 9E9, 9E9, 9E9, 9E9, 9E9]];
+        MoveAbsJ home, [5000, 28.6, 5000, 28.6], fine, tool1;
+        MoveL approach, [500.0, 57.3, 5000, 57.3], fine, tool1 \WObj:=wobj1;
+        MoveL pick, [250.0, 57.3, 5000, 57.3], fine, tool1 \WObj:=wobj1;
+        WaitDI di3, 1;
+        SetDO do4, 1;
+        WaitTime 0.500;
+        MoveC via, approach, [250.0, 57.3, 5000, 57.3], fine, tool1 \WObj:=wobj1;
+        FOR i1 FROM 1 TO 2 DO
+            MoveAbsJ home, [5000, 57.3, 5000, 57.3], fine, tool1;
+            SetDO do2, 1;
+        ENDFOR
+        SetDO do4, 0;
+        MoveAbsJ [[0.000, 0.000, 0.000, 0.000, 0.000, 0.000], [9E9, 9E9, 9E9, 9E9, 9E9, 9E9]], [5000, 11.5, 5000, 11.5], fine, tool1;
+    ENDPROC
+ENDMODULE
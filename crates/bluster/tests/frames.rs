@@ -0,0 +1,107 @@
+use nalgebra::{Isometry3, Vector3};
+use bluster::errors::FrameError;
+use bluster::frames::{FrameKind, FrameParent, FrameTree, WORLD_FRAME};
+use bluster::mesh::{ObjectBuilder, ObjectSet};
+
+
+fn translation(x: f32) -> Isometry3<f32> {
+    Isometry3::translation(x, 0.0, 0.0)
+}
+
+fn assert_x(pose: Result<Isometry3<f32>, FrameError>, x: f32) {
+    let pose = pose.unwrap();
+    assert!((pose.translation.vector - Vector3::new(x, 0.0, 0.0)).norm() < 1e-5, "{:?} isn't at x = {}", pose, x);
+}
+
+/// Moves the dynamic frame `name` to `x` at each timestep in turn, starting at 0.
+fn move_over_time(tree: &mut FrameTree, name: &str, xs: &[f32]) {
+    let objects = ObjectSet::new();
+
+    for (timestep, x) in xs.iter().enumerate() {
+        tree.update(&objects, timestep);
+        tree.set_frame(name, FrameParent::World, translation(*x)).unwrap();
+    }
+}
+
+#[test]
+fn lookup_at_past_timesteps() {
+    let mut tree = FrameTree::new();
+    tree.add("table", FrameParent::World, translation(1.0), FrameKind::Static).unwrap();
+    move_over_time(&mut tree, "tcp", &[0.0, 0.5, 2.0]);
+
+    assert_eq!(tree.timestep(), 2);
+    assert_x(tree.lookup("table", "tcp"), 1.0);
+    assert_x(tree.lookup_at("table", "tcp", 1), -0.5);
+    assert_x(tree.lookup_at(WORLD_FRAME, "tcp", 0), 0.0);
+    // Static frames are valid at every timestep, dynamic ones hold their last value
+    assert_x(tree.lookup_at("tcp", "table", 7), -1.0);
+}
+
+#[test]
+fn frames_follow_objects() {
+    let mut objects = ObjectSet::new();
+    let handle = objects.insert(ObjectBuilder::cuboid(0.1, 0.1, 0.1).position(translation(3.0)));
+
+    let mut tree = FrameTree::new();
+    tree.add("part", FrameParent::Object(handle), translation(0.5), FrameKind::Static).unwrap();
+
+    // The object position is only known once the tree was updated
+    assert_eq!(tree.world_pose("part"), Err(FrameError::NoHistory { frame: "part".to_string(), timestep: 0 }));
+    tree.update(&objects, 1);
+    assert_x(tree.world_pose("part"), 3.5);
+}
+
+#[test]
+fn history_is_trimmed() {
+    let mut tree = FrameTree::with_history(2);
+    move_over_time(&mut tree, "tcp", &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+    assert_x(tree.world_pose_at("tcp", 3), 3.0);
+    assert_x(tree.world_pose_at("tcp", 4), 4.0);
+    assert_eq!(tree.world_pose_at("tcp", 2), Err(FrameError::NoHistory { frame: "tcp".to_string(), timestep: 2 }));
+}
+
+#[test]
+fn trimming_keeps_the_last_sample_before_the_window() {
+    let mut tree = FrameTree::with_history(2);
+    let objects = ObjectSet::new();
+
+    tree.set_frame("tcp", FrameParent::World, translation(1.0)).unwrap();
+    tree.update(&objects, 10);
+    tree.set_frame("tcp", FrameParent::World, translation(2.0)).unwrap();
+
+    // Nothing was recorded between 0 and 10, the first sample still holds at the start of the window
+    assert_x(tree.world_pose_at("tcp", 8), 1.0);
+    assert_x(tree.world_pose_at("tcp", 0), 1.0);
+}
+
+#[test]
+fn reparenting_drops_the_history() {
+    let mut tree = FrameTree::new();
+    tree.add("base", FrameParent::World, translation(10.0), FrameKind::Static).unwrap();
+    move_over_time(&mut tree, "tcp", &[0.0, 1.0]);
+
+    tree.update(&ObjectSet::new(), 2);
+    tree.set_frame("tcp", FrameParent::Frame("base".to_string()), translation(1.0)).unwrap();
+
+    assert_eq!(tree.parent("tcp"), Some(&FrameParent::Frame("base".to_string())));
+    assert_x(tree.world_pose("tcp"), 11.0);
+    // The old transforms were relative to the world, they aren't replayed under the new parent
+    assert_eq!(tree.world_pose_at("tcp", 1), Err(FrameError::NoHistory { frame: "tcp".to_string(), timestep: 1 }));
+}
+
+#[test]
+fn invalid_changes() {
+    let mut tree = FrameTree::new();
+    tree.add("base", FrameParent::World, translation(1.0), FrameKind::Static).unwrap();
+    tree.set_frame("tcp", FrameParent::Frame("base".to_string()), translation(1.0)).unwrap();
+
+    assert_eq!(tree.set_frame("base", FrameParent::Frame("tcp".to_string()), translation(1.0)), Err(FrameError::StaticFrame("base".to_string())));
+    assert_eq!(tree.set_transform("base", translation(2.0)), Err(FrameError::StaticFrame("base".to_string())));
+    assert_eq!(tree.add("tcp", FrameParent::World, translation(0.0), FrameKind::Dynamic), Err(FrameError::DuplicateFrame("tcp".to_string())));
+    assert_eq!(tree.set_frame("tool", FrameParent::Frame("flange".to_string()), translation(0.0)), Err(FrameError::UnknownFrame("flange".to_string())));
+    assert_eq!(tree.remove("base"), Err(FrameError::HasChildren("base".to_string())));
+
+    tree.set_frame("tool", FrameParent::Frame("tcp".to_string()), translation(0.0)).unwrap();
+    assert_eq!(tree.set_frame("tcp", FrameParent::Frame("tool".to_string()), translation(0.0)), Err(FrameError::Cycle("tcp".to_string())));
+}
@@ -0,0 +1,38 @@
+use std::f32::consts::PI;
+use nalgebra::{Isometry3, Vector3};
+use bluster::kinematics::VacuumCup;
+use bluster::mesh::{ObjectBuilder, ObjectSet};
+use bluster::pipeline::query_pipeline::QueryPipeline;
+
+
+/// A cube with its top face at z = 0.5.
+fn cube() -> (ObjectSet, QueryPipeline) {
+    let mut objects = ObjectSet::new();
+    objects.insert(ObjectBuilder::cuboid(0.5, 0.5, 0.5));
+    let mut query_pipeline = QueryPipeline::new();
+    query_pipeline.update(&objects);
+
+    (objects, query_pipeline)
+}
+
+/// The flange with the cup pointing down, its lip at `height` and tilted by `tilt` around the x axis.
+fn flange(height: f32, tilt: f32) -> Isometry3<f32> {
+    Isometry3::new(Vector3::new(0.0, 0.0, height), Vector3::x() * (PI + tilt))
+}
+
+#[test]
+fn vacuum_pick() {
+    let (objects, query_pipeline) = cube();
+    let cup = VacuumCup::new(Isometry3::identity(), 0.1);
+    let handle = objects.iter().next().unwrap().0;
+
+    assert_eq!(cup.pick(&objects, &query_pipeline, &flange(0.505, 0.0), &[]), Some(handle));
+    assert_eq!(cup.pick(&objects, &query_pipeline, &flange(0.5, 0.05), &[]), Some(handle));
+    assert_eq!(cup.pick(&objects, &query_pipeline, &flange(0.505, 0.0), &[handle]), None);
+    // Too far above the face, or too tilted to seal
+    assert_eq!(cup.pick(&objects, &query_pipeline, &flange(0.6, 0.0), &[]), None);
+    assert_eq!(cup.with_tolerances(0.05, 0.1).pick(&objects, &query_pipeline, &flange(0.5, 0.2), &[]), None);
+    assert_eq!(cup.with_tolerances(0.05, 0.3).pick(&objects, &query_pipeline, &flange(0.5, 0.2), &[]), Some(handle));
+    // Buried in the cube, where the rays start inside it
+    assert_eq!(cup.pick(&objects, &query_pipeline, &flange(0.3, 0.0), &[]), None);
+}
@@ -0,0 +1,95 @@
+use bluster::errors::ParseError;
+use bluster::program::export::{Krl, Rapid, UrScript};
+use bluster::program::import::Importer;
+use bluster::program::Command;
+
+
+/// Imports a malformed program, which has to fail with a located error rather than panic.
+fn error(importer: &dyn Importer, source: &str) -> ParseError {
+    match importer.import("p", source, 1.0) {
+        Ok(imported) => panic!("imported malformed program {:?}", imported.program.instructions),
+        Err(error) => error,
+    }
+}
+
+fn assert_error(error: ParseError, line: usize, column: usize, message: &str) {
+    assert_eq!((error.line, error.column), (line, column), "{}", error);
+    assert!(error.message.contains(message), "`{}` doesn't mention `{}`", error.message, message);
+}
+
+fn krl(body: &str) -> String {
+    format!("DEF p()\n{}\nEND\n", body)
+}
+
+fn rapid(body: &str) -> String {
+    format!("MODULE m\n  PROC main()\n{}\n  ENDPROC\nENDMODULE\n", body)
+}
+
+fn urscript(body: &str) -> String {
+    format!("def p():\n{}\nend\n", body)
+}
+
+#[test]
+fn krl_frame_data_without_index() {
+    assert_error(error(&Krl, &krl("  TOOL_DATA[ = {X 0}")), 2, 3, "TOOL_DATA[<index>]");
+    assert_error(error(&Krl, &krl("  BASE_DATA[=1")), 2, 3, "BASE_DATA[<index>]");
+    assert_error(error(&Krl, &krl("  $OUT[1 = TRUE")), 2, 3, "$OUT[<port>]");
+}
+
+#[test]
+fn krl_malformed_statements() {
+    assert_error(error(&Krl, &krl("  TOOL_DATA[1] = {X 0")), 2, 18, "aggregate");
+    assert_error(error(&Krl, &krl("  FOR i = 1 TO 2 STEP 0\n  ENDFOR")), 2, 23, "`STEP`");
+    assert_error(error(&Krl, &krl("  LOOP\n    PTP {A1 0}")), 2, 1, "loop without matching end");
+    assert_error(error(&Krl, &krl("  ENDFOR")), 2, 3, "without matching start");
+    assert_error(error(&Krl, &krl("  GOTO nowhere")), 2, 1, "unknown label `nowhere`");
+}
+
+#[test]
+fn krl_for_step() {
+    let imported = Krl.import("p", &krl("  FOR i = 10 TO 1 STEP -3\n    PTP {A1 0}\n  ENDFOR"), 1.0).unwrap();
+
+    assert!(matches!(imported.program.instructions[0].command, Command::Loop { count: Some(4), .. }));
+}
+
+#[test]
+fn rapid_malformed_statements() {
+    assert_error(error(&Rapid, &rapid("    MoveJ")), 3, 1, "expects more arguments");
+    assert_error(error(&Rapid, &rapid("    MoveJ [[1, 2, 3], [1, 0, 0]], v100, fine, tool0;")), 3, 11, "robtarget");
+    assert_error(error(&Rapid, &rapid("    MoveAbsJ [[0, 0, x, 0, 0, 0], [9E9]], v100, fine, tool0;")), 3, 22, "found `x`");
+    assert_error(error(&Rapid, &rapid("    MoveL p1, v100, fine, nosuch;")), 3, 27, "unknown tool `nosuch`");
+    assert_error(error(&Rapid, &rapid("    FOR i FROM 1 TO 3 DO")), 3, 1, "loop without matching end");
+    assert_error(error(&Rapid, &rapid("    GOTO nowhere;")), 3, 1, "unknown label `nowhere`");
+    assert_error(error(&Rapid, "MODULE m\n  PERS tooldata t := [TRUE, [[0, 0"), 2, 8, "tooldata");
+}
+
+#[test]
+fn rapid_local_declaration() {
+    let source = "MODULE m\n  LOCAL PERS tooldata grip := [TRUE, [[0, 0, 100], [1, 0, 0, 0]], [1, [0, 0, 0], [1, 0, 0, 0], 0, 0, 0]];\n";
+    let imported = Rapid.import("m", source, 1.0).unwrap();
+
+    assert_eq!(imported.cell.tools.keys().collect::<Vec<_>>(), [&1]);
+}
+
+#[test]
+fn urscript_malformed_statements() {
+    assert_error(error(&UrScript, &urscript("  movej([0, 0, x, 0, 0, 0])")), 2, 16, "found `x`");
+    assert_error(error(&UrScript, &urscript("  movel(p[0, 0, 0])")), 2, 9, "6 components");
+    assert_error(error(&UrScript, &urscript("  set_tcp(p[0, 0])")), 2, 11, "6 components");
+    assert_error(error(&UrScript, &urscript("  movej()")), 2, 3, "expects a target");
+    assert_error(error(&UrScript, &urscript("  end")), 3, 1, "without matching block");
+    assert_error(error(&UrScript, "def p():\n  while True:\n    movej([0, 0, 0, 0, 0, 0])\n"), 2, 1, "loop without matching end");
+}
+
+#[test]
+fn chained_variables_are_kept() {
+    let imported = Krl.import("p", &krl("  DECL E6AXIS a\n  DECL E6AXIS b\n  a = {A1 10}\n  b = a\n  PTP b"), 1.0).unwrap();
+    let assigned: Vec<_> = imported.program.instructions.iter()
+        .filter_map(|instruction| match &instruction.command {
+            Command::Assign { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(assigned, ["a", "b"]);
+}
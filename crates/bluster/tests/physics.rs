@@ -0,0 +1,134 @@
+use nalgebra::{Isometry3, Vector3};
+use bluster::mesh::{ObjectBuilder, ObjectHandle, ObjectSet, ObjectVelocity};
+use bluster::pipeline::physics_pipeline::PhysicsPipeline;
+use bluster::pipeline::query_pipeline::QueryPipeline;
+
+
+const DT: f32 = 0.01;
+
+/// A 10 cm cube of 1 kg, dynamic, its center at `height`.
+fn cube(objects: &mut ObjectSet, x: f32, height: f32) -> ObjectHandle {
+    objects.insert(ObjectBuilder::cuboid(0.05, 0.05, 0.05).density(1000.0).dynamic(true).position(Isometry3::translation(x, height, 0.0)))
+}
+
+/// A static slab whose top face is at y = 0.
+fn ground(objects: &mut ObjectSet) -> ObjectHandle {
+    objects.insert(ObjectBuilder::cuboid(5.0, 0.5, 5.0).position(Isometry3::translation(0.0, -0.5, 0.0)))
+}
+
+/// Steps the scene for `duration` seconds.
+fn simulate(physics: &mut PhysicsPipeline, objects: &mut ObjectSet, duration: f32) {
+    let mut query_pipeline = QueryPipeline::new();
+
+    for _ in 0..(duration / DT).round() as usize {
+        physics.step(objects, &mut query_pipeline, DT);
+    }
+}
+
+fn height(objects: &ObjectSet, handle: ObjectHandle) -> f32 {
+    objects[handle].position().translation.y
+}
+
+#[test]
+fn free_fall() {
+    let mut objects = ObjectSet::new();
+    let handle = cube(&mut objects, 0.0, 10.0);
+    let mut physics = PhysicsPipeline::new();
+    physics.linear_damping = 0.0;
+
+    simulate(&mut physics, &mut objects, 1.0);
+
+    // Semi-implicit Euler falls slightly ahead of the exact 4.905 m
+    let velocity = objects[handle].velocity().linvel;
+    assert!((velocity - Vector3::new(0.0, -9.81, 0.0)).norm() < 1e-3, "{:?}", velocity);
+    assert!((10.0 - height(&objects, handle) - 4.905).abs() < 0.02, "{}", height(&objects, handle));
+}
+
+#[test]
+fn resting_contact() {
+    let mut objects = ObjectSet::new();
+    ground(&mut objects);
+    let handle = cube(&mut objects, 0.0, 0.3);
+    let mut physics = PhysicsPipeline::new();
+
+    simulate(&mut physics, &mut objects, 2.0);
+
+    // The cube lands and stays on the ground, sinking at most by the tolerated penetration
+    let resting = height(&objects, handle);
+    assert!((0.05 - physics.allowed_penetration - 1e-3..=0.05 + 1e-3).contains(&resting), "{}", resting);
+    assert!(objects[handle].velocity().linvel.norm() < 1e-2);
+
+    simulate(&mut physics, &mut objects, 1.0);
+    assert!((height(&objects, handle) - resting).abs() < 1e-3);
+}
+
+#[test]
+fn friction_stops_sliding() {
+    let slide = |friction: f32| {
+        let mut objects = ObjectSet::new();
+        ground(&mut objects);
+        let handle = cube(&mut objects, 0.0, 0.05);
+        objects[handle].set_velocity(ObjectVelocity { linvel: Vector3::new(2.0, 0.0, 0.0), angvel: Vector3::zeros() });
+
+        let mut physics = PhysicsPipeline::new();
+        physics.friction = friction;
+        physics.linear_damping = 0.0;
+        simulate(&mut physics, &mut objects, 1.0);
+
+        (objects[handle].position().translation.x, objects[handle].velocity().linvel.x)
+    };
+
+    // Sliding at 2 m/s with a deceleration of μg stops after 2² / (2 μ g) = 0.41 m
+    let (distance, velocity) = slide(0.5);
+    assert!((distance - 0.41).abs() < 0.05, "{}", distance);
+    assert!(velocity.abs() < 1e-2);
+
+    let (distance, velocity) = slide(0.0);
+    assert!((distance - 2.0).abs() < 0.05 && (velocity - 2.0).abs() < 1e-2, "{} {}", distance, velocity);
+}
+
+#[test]
+fn kinematic_objects_push() {
+    let mut objects = ObjectSet::new();
+    ground(&mut objects);
+    let handle = cube(&mut objects, 0.0, 0.05);
+    let pusher = objects.insert(ObjectBuilder::cuboid(0.05, 0.2, 0.2).position(Isometry3::translation(-0.2, 0.2, 0.0)));
+    let mut physics = PhysicsPipeline::new();
+    let mut query_pipeline = QueryPipeline::new();
+
+    // The pusher moves at 0.5 m/s, it isn't affected by the cube nor by gravity
+    for i in 1..=100 {
+        objects[pusher].set_position(Isometry3::translation(-0.2 + 0.005 * i as f32, 0.2, 0.0));
+        physics.step(&mut objects, &mut query_pipeline, DT);
+    }
+
+    assert_eq!(objects[pusher].position().translation.vector, Vector3::new(0.3, 0.2, 0.0));
+    // The cube stays ahead of the pusher face, which ended at x = 0.35
+    let x = objects[handle].position().translation.x;
+    assert!((0.38..0.45).contains(&x), "{}", x);
+}
+
+#[test]
+fn bounces() {
+    let drop = |restitution: f32| {
+        let mut objects = ObjectSet::new();
+        ground(&mut objects);
+        let handle = cube(&mut objects, 0.0, 1.05);
+        let mut physics = PhysicsPipeline::new();
+        physics.restitution = restitution;
+        let mut query_pipeline = QueryPipeline::new();
+        let mut rebound: f32 = 0.0;
+
+        for _ in 0..100 {
+            physics.step(&mut objects, &mut query_pipeline, DT);
+            rebound = rebound.max(objects[handle].velocity().linvel.y);
+        }
+
+        rebound
+    };
+
+    // Landing at about 4.4 m/s
+    assert!(drop(0.0) < 0.1);
+    let rebound = drop(0.5);
+    assert!((rebound - 2.2).abs() < 0.3, "{}", rebound);
+}
@@ -0,0 +1,135 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use nalgebra::{Isometry3, Translation3, Unit, UnitQuaternion, Vector3};
+use bluster::pose::{self, AngleUnit, Axis, EulerSet};
+
+
+/// The largest angle between two rotations accepted as equal.
+const TOLERANCE: f32 = 1e-5;
+
+/// Rotations spread over the whole sphere, including the ones around the singular Tait-Bryan pitches.
+fn rotations() -> Vec<UnitQuaternion<f32>> {
+    let angles = [-170.0, -120.0, -90.0, -45.0, 0.0, 30.0, 89.9, 90.0, 135.0, 180.0f32];
+    let mut rotations = Vec::new();
+
+    for roll in angles {
+        for pitch in angles {
+            for yaw in angles {
+                rotations.push(UnitQuaternion::from_euler_angles(roll.to_radians(), pitch.to_radians(), yaw.to_radians()));
+            }
+        }
+    }
+
+    rotations
+}
+
+fn assert_same_rotation(rotation: &UnitQuaternion<f32>, expected: &UnitQuaternion<f32>) {
+    let angle = rotation.angle_to(expected);
+    assert!(angle < TOLERANCE, "{:?} is {} rad away from {:?}", rotation, angle, expected);
+}
+
+fn sets() -> Vec<EulerSet> {
+    vec![
+        EulerSet::ZYX,
+        EulerSet::ZYZ,
+        EulerSet::XYZ,
+        EulerSet::RPY,
+        EulerSet::extrinsic(Axis::Z, Axis::X, Axis::Z),
+        EulerSet::intrinsic(Axis::Y, Axis::X, Axis::Y),
+        EulerSet::extrinsic(Axis::Y, Axis::Z, Axis::X),
+    ]
+}
+
+#[test]
+fn euler_round_trip() {
+    for set in sets() {
+        for rotation in rotations() {
+            let angles = set.from_rotation(&rotation, AngleUnit::Radians);
+            assert_same_rotation(&set.to_rotation(angles, AngleUnit::Radians), &rotation);
+
+            let middle = if set.is_proper() { 0.0..=PI } else { -FRAC_PI_2..=FRAC_PI_2 };
+            assert!(middle.contains(&angles[1]), "{:?}: middle angle of {:?}", set, angles);
+            assert!(angles.iter().all(|angle| (-PI..=PI).contains(angle)), "{:?}: {:?}", set, angles);
+        }
+    }
+}
+
+#[test]
+fn euler_singularities() {
+    for set in sets() {
+        let singular = if set.is_proper() { [0.0, 180.0] } else { [-90.0, 90.0] };
+
+        for middle in singular {
+            let rotation = set.to_rotation([40.0, middle, -25.0], AngleUnit::Degrees);
+            let angles = set.from_rotation(&rotation, AngleUnit::Degrees);
+
+            assert_same_rotation(&set.to_rotation(angles, AngleUnit::Degrees), &rotation);
+            // Only the first and third angles together are defined, the third is zero
+            assert!(angles[2].abs() < 1e-3, "{:?}: {:?}", set, angles);
+        }
+    }
+}
+
+#[test]
+fn euler_known_angles() {
+    let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2)
+        * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.3);
+    let angles = EulerSet::ZYX.from_rotation(&rotation, AngleUnit::Radians);
+
+    assert!((angles[0] - FRAC_PI_2).abs() < TOLERANCE && (angles[1] - 0.3).abs() < TOLERANCE && angles[2].abs() < TOLERANCE);
+
+    // RPY is ZYX with the angles reversed
+    let [a, b, c] = EulerSet::ZYX.from_rotation(&rotation, AngleUnit::Degrees);
+    assert_same_rotation(&EulerSet::RPY.to_rotation([c, b, a], AngleUnit::Degrees), &rotation);
+}
+
+#[test]
+fn kuka_frames() {
+    for rotation in rotations() {
+        let [a, b, c] = pose::kuka_abc(&rotation);
+        let frame = pose::kuka_frame(1.0, -2.0, 3.0, a, b, c);
+
+        assert_same_rotation(&frame.rotation, &rotation);
+        assert_eq!(frame.translation, Translation3::new(1.0, -2.0, 3.0));
+    }
+
+    assert_eq!(pose::kuka_abc(&pose::from_kuka_abc([30.0, -20.0, 10.0])).map(|angle| (angle * 1e3).round() / 1e3), [30.0, -20.0, 10.0]);
+}
+
+#[test]
+fn abb_quaternions() {
+    for rotation in rotations() {
+        let q = pose::abb_quaternion(&rotation);
+
+        assert!(q[0] >= 0.0);
+        assert_same_rotation(&pose::from_abb_quaternion(q), &rotation);
+    }
+
+    // The controller normalizes the quaternions it reads
+    assert_same_rotation(&pose::from_abb_quaternion([2.0, 0.0, 0.0, 2.0]), &UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2));
+}
+
+#[test]
+fn ur_rotation_vectors() {
+    for rotation in rotations() {
+        let rotvec = pose::ur_rotvec(&rotation);
+
+        assert!(rotvec.norm() <= PI + TOLERANCE);
+        assert_same_rotation(&pose::from_ur_rotvec(rotvec), &rotation);
+    }
+
+    // Half turns, where the rotation vector and its opposite describe the same rotation
+    for axis in [Vector3::x_axis(), Vector3::y_axis(), Vector3::z_axis(), Unit::new_normalize(Vector3::new(1.0, 1.0, 0.0))] {
+        let rotation = UnitQuaternion::from_axis_angle(&axis, PI);
+        let rotvec = pose::ur_rotvec(&rotation);
+
+        assert!((rotvec.norm() - PI).abs() < TOLERANCE);
+        assert!(rotvec.normalize().dot(&axis).abs() > 1.0 - TOLERANCE);
+        assert_same_rotation(&pose::from_ur_rotvec(rotvec), &rotation);
+    }
+
+    let pose = Isometry3::from_parts(Translation3::new(0.4, -0.1, 0.25), UnitQuaternion::from_euler_angles(0.1, -0.2, 2.5));
+    let round_trip = pose::from_ur_pose(pose::ur_pose(&pose));
+
+    assert!((round_trip.translation.vector - pose.translation.vector).norm() < TOLERANCE);
+    assert_same_rotation(&round_trip.rotation, &pose.rotation);
+}
@@ -0,0 +1,209 @@
+use std::f32::consts::FRAC_PI_2;
+use nalgebra::{DVector, Isometry3, Vector3};
+use bluster::errors::ProgramError;
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::KinematicChain;
+use bluster::program::{self, Command, Condition, Interpreter, ProgramContext, ProgramState, Target};
+
+
+/// A single joint robot recording what the program does to its outputs and gripper.
+struct Cell {
+    chain: KinematicChain,
+    joints: DVector<f32>,
+    inputs: Vec<bool>,
+    outputs: Vec<(usize, bool)>,
+    gripper: Option<bool>,
+}
+
+impl Cell {
+    fn new() -> Self {
+        let mut chain = KinematicChain::default();
+        chain.push_link(RevoluteJoint::new(Vector3::z_axis()), None);
+
+        Self {
+            joints: chain.zeros(),
+            chain,
+            inputs: vec![false; 8],
+            outputs: Vec::new(),
+            gripper: None,
+        }
+    }
+}
+
+impl ProgramContext for Cell {
+    fn chain(&self) -> &KinematicChain {
+        &self.chain
+    }
+
+    fn joints(&self) -> &DVector<f32> {
+        &self.joints
+    }
+
+    fn set_joints(&mut self, joints: DVector<f32>) {
+        self.joints = joints;
+    }
+
+    fn tool_frame(&self, tool: usize) -> Option<Isometry3<f32>> {
+        (tool == 0).then(Isometry3::identity)
+    }
+
+    fn base_frame(&self, base: usize) -> Option<Isometry3<f32>> {
+        (base == 0).then(Isometry3::identity)
+    }
+
+    fn digital_input(&self, port: usize) -> bool {
+        self.inputs[port]
+    }
+
+    fn set_digital_output(&mut self, port: usize, value: bool) {
+        self.outputs.push((port, value));
+    }
+
+    fn set_gripper(&mut self, closed: bool) {
+        self.gripper = Some(closed);
+    }
+}
+
+/// Parses `source`, which has to fail at `line` and `column` with a message mentioning `message`.
+fn assert_parse_error(source: &str, line: usize, column: usize, message: &str) {
+    let error = program::parse("test", source).expect_err(source);
+
+    assert_eq!((error.line, error.column), (line, column), "{}", error);
+    assert!(error.message.contains(message), "`{}` doesn't mention `{}`", error.message, message);
+}
+
+/// Runs `source` for a single step.
+fn run(source: &str, cell: &mut Cell) -> (Interpreter, Result<ProgramState, ProgramError>) {
+    let mut interpreter = Interpreter::new(program::parse("test", source).unwrap());
+    let result = interpreter.step(cell, 0.01);
+
+    (interpreter, result)
+}
+
+#[test]
+fn parse_statements() {
+    let program = program::parse("test", "VAR home = JOINTS(0, 90)\n\nTOOL 1 ; the gripper\nlin home vel 0.5\nWAIT TIME 1e-3\nWAIT IN[2] == FALSE").unwrap();
+    let commands: Vec<_> = program.instructions.iter().map(|i| (i.line, i.command.clone())).collect();
+
+    assert_eq!(commands.len(), 5);
+    assert_eq!(commands[0], (1, Command::Assign {
+        name: "home".to_string(),
+        target: Target::Joints(DVector::from_vec(vec![0.0, FRAC_PI_2])),
+    }));
+    assert_eq!(commands[1], (3, Command::Tool(1)));
+    assert!(matches!(&commands[2], (4, Command::Move(motion)) if motion.velocity == Some(0.5)));
+    assert_eq!(commands[3], (5, Command::WaitTime(1e-3)));
+    assert_eq!(commands[4], (6, Command::Wait(Condition::Input { port: 2, value: false })));
+}
+
+#[test]
+fn parse_loops() {
+    let program = program::parse("test", "LOOP 2\n  LOOP\n    GRIP\n  ENDLOOP\nENDLOOP").unwrap();
+    let commands: Vec<_> = program.instructions.iter().map(|i| i.command.clone()).collect();
+
+    assert_eq!(commands, [
+        Command::Loop { count: Some(2), end: 4 },
+        Command::Loop { count: None, end: 3 },
+        Command::Gripper(true),
+        Command::EndLoop { start: 1 },
+        Command::EndLoop { start: 0 },
+    ]);
+}
+
+#[test]
+fn parse_errors() {
+    assert_parse_error("SET OUT[1] = #", 1, 14, "unexpected character `#`");
+    assert_parse_error("GRIP\nJUMP top", 2, 1, "unknown statement `JUMP`");
+    assert_parse_error("GRIP now", 1, 6, "expected end of line");
+    assert_parse_error("PTP home VEL 0", 1, 14, "velocity must be positive");
+    assert_parse_error("VAR p = POSE(1, 2, 3, 4, 5)", 1, 9, "expects 6 values");
+    assert_parse_error("SET OUT[-1] = TRUE", 1, 9, "non-negative integer");
+    assert_parse_error("LOOP 1.5\nENDLOOP", 1, 6, "non-negative integer");
+    assert_parse_error("LOOP 4294967297\nENDLOOP", 1, 6, "loop count");
+    assert_parse_error("GRIP\nENDLOOP", 2, 1, "without matching `LOOP`");
+    assert_parse_error("GRIP\nLOOP 2\nRELEASE", 2, 1, "without matching `ENDLOOP`");
+    assert_parse_error("GOTO nowhere", 1, 6, "unknown label `nowhere`");
+    assert_parse_error("LABEL top\nLABEL TOP", 2, 7, "already defined");
+}
+
+#[test]
+fn counted_loops() {
+    let mut cell = Cell::new();
+    let (_, result) = run("LOOP 3\n  LOOP 2\n    SET OUT[1] = TRUE\n  ENDLOOP\nENDLOOP\nLOOP 0\n  SET OUT[2] = TRUE\nENDLOOP", &mut cell);
+
+    assert_eq!(result, Ok(ProgramState::Finished));
+    assert_eq!(cell.outputs, vec![(1, true); 6]);
+}
+
+#[test]
+fn conditional_goto() {
+    let mut cell = Cell::new();
+    cell.inputs[1] = true;
+    let (_, result) = run("GOTO skip\nSET OUT[1] = TRUE\nLABEL skip\nIF IN[1] == FALSE GOTO end\nSET OUT[2] = TRUE\nLABEL end", &mut cell);
+
+    assert_eq!(result, Ok(ProgramState::Finished));
+    assert_eq!(cell.outputs, [(2, true)]);
+}
+
+#[test]
+fn waits() {
+    let mut cell = Cell::new();
+    let mut interpreter = Interpreter::new(program::parse("test", "WAIT IN[3]\nWAIT TIME 0.5\nGRIP").unwrap());
+
+    assert_eq!(interpreter.step(&mut cell, 1.0), Ok(ProgramState::Waiting));
+    assert_eq!(interpreter.current_line(), Some(1));
+
+    cell.inputs[3] = true;
+    assert_eq!(interpreter.step(&mut cell, 0.3), Ok(ProgramState::Waiting));
+    assert_eq!(interpreter.step(&mut cell, 0.3), Ok(ProgramState::Waiting));
+    assert_eq!(cell.gripper, None);
+    assert_eq!(interpreter.step(&mut cell, 0.3), Ok(ProgramState::Finished));
+    assert_eq!(cell.gripper, Some(true));
+}
+
+#[test]
+fn joint_motion() {
+    let mut cell = Cell::new();
+    let mut interpreter = Interpreter::new(program::parse("test", "PTP JOINTS(90) VEL 100").unwrap());
+
+    // The motion is planned by the first step and starts with the next one
+    assert_eq!(interpreter.step(&mut cell, 0.5), Ok(ProgramState::Moving));
+    assert_eq!(cell.joints[0], 0.0);
+    assert_eq!(interpreter.step(&mut cell, 0.5), Ok(ProgramState::Moving));
+    assert!(cell.joints[0] > 0.0 && cell.joints[0] < FRAC_PI_2);
+
+    for _ in 0..100 {
+        if interpreter.step(&mut cell, 0.1) == Ok(ProgramState::Finished) {
+            break;
+        }
+    }
+
+    assert_eq!(interpreter.state(), ProgramState::Finished);
+    assert!((cell.joints[0] - FRAC_PI_2).abs() < 1e-5);
+}
+
+#[test]
+fn runtime_errors() {
+    let mut cell = Cell::new();
+    let (interpreter, result) = run("GRIP\nPTP home", &mut cell);
+
+    assert_eq!(result, Err(ProgramError::UnknownVariable { line: 2, name: "home".to_string() }));
+    assert_eq!(interpreter.state(), ProgramState::Failed);
+
+    let (_, result) = run("TOOL 3", &mut cell);
+    assert_eq!(result, Err(ProgramError::UnknownTool { line: 1, tool: 3 }));
+}
+
+#[test]
+fn busy_loops_yield() {
+    let mut cell = Cell::new();
+    let (mut interpreter, result) = run("LABEL spin\nGOTO spin", &mut cell);
+
+    assert_eq!(result, Ok(ProgramState::Ready));
+    assert_eq!(interpreter.step(&mut cell, 0.01), Ok(ProgramState::Ready));
+
+    // Each step executes at most 1000 instructions: the loop start, then the output and the loop end in turn
+    let (_, result) = run("LOOP\n  SET OUT[1] = TRUE\nENDLOOP", &mut cell);
+    assert_eq!(result, Ok(ProgramState::Ready));
+    assert_eq!(cell.outputs.len(), 500);
+}
@@ -0,0 +1,69 @@
+use std::f32::consts::PI;
+use nalgebra::{point, Isometry3, Vector3};
+use bluster::joint::RevoluteJoint;
+use bluster::kinematics::{KinematicChain, ReachabilitySampler};
+
+
+/// A planar arm in the xy plane with two 1 m links, its tool z axis always pointing up.
+fn planar_arm(limits: Option<[f32; 2]>) -> KinematicChain {
+    let joint = |anchor: f32| {
+        let joint = RevoluteJoint::new(Vector3::z_axis()).local_anchor1(point![anchor, 0.0, 0.0]);
+        limits.map_or(joint, |limits| joint.limits(limits))
+    };
+
+    let mut chain = KinematicChain::default();
+    chain.push_link(joint(0.0), None).push_link(joint(1.0), None);
+    chain.flange = Isometry3::translation(1.0, 0.0, 0.0);
+
+    chain
+}
+
+fn sampler() -> ReachabilitySampler {
+    ReachabilitySampler { samples: 40_000, seed: 11, ..ReachabilitySampler::default() }
+}
+
+#[test]
+fn reachable_positions() {
+    let map = sampler().generate(&planar_arm(None));
+
+    assert!(map.is_position_reachable(&point![1.45, 0.05, 0.05]));
+    assert!(map.is_position_reachable(&point![-0.35, 1.25, 0.05]));
+    // Out of the arm reach, or out of its plane
+    assert!(!map.is_position_reachable(&point![2.15, 0.05, 0.05]));
+    assert!(!map.is_position_reachable(&point![1.45, 0.05, 0.35]));
+
+    // The same seed gives the same map
+    assert_eq!(sampler().generate(&planar_arm(None)), map);
+}
+
+#[test]
+fn approach_directions() {
+    let map = sampler().generate(&planar_arm(None));
+    let target = Isometry3::translation(1.45, 0.05, 0.05);
+    let flipped = target * Isometry3::rotation(Vector3::x() * PI);
+
+    // A planar arm only ever points its tool up
+    assert_eq!(map.orientations(&point![1.45, 0.05, 0.05]), 1);
+    assert_eq!(map.check_targets(&[target, flipped]), [true, false]);
+    assert!(map.voxels().all(|(_, orientations)| orientations == 1));
+}
+
+#[test]
+fn moved_base() {
+    let mut map = sampler().generate(&planar_arm(None));
+    map.set_base(Isometry3::new(Vector3::new(10.0, 0.0, 0.0), Vector3::z() * PI));
+
+    assert!(map.is_position_reachable(&point![8.55, -0.05, 0.05]));
+    assert!(!map.is_position_reachable(&point![1.45, 0.05, 0.05]));
+}
+
+#[test]
+fn limits_and_filters() {
+    let limited = sampler().generate(&planar_arm(Some([-0.5, 0.5])));
+
+    assert!(limited.is_position_reachable(&point![1.95, 0.05, 0.05]));
+    assert!(!limited.is_position_reachable(&point![-1.45, 0.05, 0.05]));
+    assert!(limited.len() < sampler().generate(&planar_arm(None)).len());
+
+    assert!(sampler().generate_filtered(&planar_arm(None), |_| false).is_empty());
+}
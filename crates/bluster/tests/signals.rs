@@ -0,0 +1,100 @@
+use nalgebra::{point, Isometry3, Vector3};
+use bluster::errors::SignalError;
+use bluster::mesh::{ObjectBuilder, ObjectSet};
+use bluster::pipeline::query_pipeline::QueryPipeline;
+use bluster::signals::{Edge, LightBarrier, RangeSensor, Sensor, SignalKind, SignalTable, SignalValue};
+
+
+fn table() -> SignalTable {
+    let mut signals = SignalTable::new();
+    signals.insert("part_present", SignalKind::DigitalInput, 1).unwrap();
+    signals.insert("distance", SignalKind::AnalogInput, 1).unwrap();
+    signals.insert("conveyor_on", SignalKind::DigitalOutput, 1).unwrap();
+
+    signals
+}
+
+#[test]
+fn declarations() {
+    let mut signals = table();
+
+    assert_eq!(signals.insert("part_present", SignalKind::DigitalOutput, 5), Err(SignalError::DuplicateSignal("part_present".to_string())));
+    assert_eq!(signals.insert("other", SignalKind::DigitalInput, 1), Err(SignalError::DuplicatePort { name: "part_present".to_string(), port: 1 }));
+    assert_eq!(signals.port_name(SignalKind::AnalogInput, 1), Some("distance"));
+    assert_eq!(signals.digital("part_present"), Some(false));
+    assert_eq!(signals.analog("part_present"), None);
+    assert_eq!(signals.len(), 3);
+}
+
+#[test]
+fn set_and_check() {
+    let mut signals = table();
+
+    assert_eq!(signals.set("part_present", 1.0), Err(SignalError::TypeMismatch("part_present".to_string())));
+    assert_eq!(signals.set("part_presnt", true), Err(SignalError::UnknownSignal("part_presnt".to_string())));
+    assert_eq!(signals.set("distance", 0.5), Ok(()));
+    assert_eq!(signals.analog("distance"), Some(0.5));
+
+    assert_eq!(signals.check("part_present", true), Ok(()));
+    assert_eq!(signals.check("distance", true), Err(SignalError::TypeMismatch("distance".to_string())));
+    assert_eq!(signals.check("missing", false), Err(SignalError::UnknownSignal("missing".to_string())));
+}
+
+#[test]
+fn edge_events() {
+    let mut signals = table();
+
+    signals.set("part_present", true).unwrap();
+    // Setting the same value again is no change
+    signals.set("part_present", true).unwrap();
+    signals.set("part_present", false).unwrap();
+    signals.set("distance", 2.0).unwrap();
+
+    let events: Vec<_> = signals.events().iter().map(|event| (event.name.as_str(), event.edge())).collect();
+    assert_eq!(events, [("part_present", Some(Edge::Rising)), ("part_present", Some(Edge::Falling)), ("distance", None)]);
+    assert_eq!(signals.events()[2].previous, SignalValue::Analog(0.0));
+
+    signals.clear_events();
+    assert!(signals.events().is_empty());
+}
+
+#[test]
+fn ports() {
+    let mut signals = table();
+
+    // Ports used without a declared signal get one, named like the program signals
+    signals.set_port(SignalKind::DigitalOutput, 3, true).unwrap();
+    signals.set_port(SignalKind::DigitalOutput, 1, true).unwrap();
+
+    assert_eq!(signals.digital("OUT[3]"), Some(true));
+    assert_eq!(signals.digital("conveyor_on"), Some(true));
+    assert_eq!(signals.digital_ports(SignalKind::DigitalOutput), [false, true, false, true]);
+    assert_eq!(signals.port_value(SignalKind::AnalogOutput, 7), SignalValue::Analog(0.0));
+    assert_eq!(signals.set_port(SignalKind::DigitalOutput, 3, 1.0), Err(SignalError::TypeMismatch("OUT[3]".to_string())));
+}
+
+#[test]
+fn ray_sensors() {
+    let mut objects = ObjectSet::new();
+    objects.insert(ObjectBuilder::cuboid(0.5, 0.5, 0.5).position(Isometry3::translation(2.0, 0.0, 0.0)));
+    let mut query_pipeline = QueryPipeline::new();
+    query_pipeline.update(&objects);
+
+    let mut signals = table();
+    let mut barrier = LightBarrier::new("part_present", point![0.0, 0.0, 0.0], Vector3::x_axis(), 4.0);
+    let mut range = RangeSensor::new("distance", point![0.0, 0.0, 0.0], Vector3::x_axis(), 10.0);
+    let mut missed = LightBarrier::new("part_present", point![0.0, 2.0, 0.0], Vector3::x_axis(), 4.0);
+
+    assert_eq!(barrier.check(&signals), Ok(()));
+    assert_eq!(range.check(&signals), Ok(()));
+    assert!(RangeSensor::new("part_present", point![0.0, 0.0, 0.0], Vector3::x_axis(), 1.0).check(&signals).is_err());
+
+    barrier.sense(&objects, &query_pipeline, &mut signals);
+    range.sense(&objects, &query_pipeline, &mut signals);
+    assert_eq!(signals.digital("part_present"), Some(true));
+    assert!((signals.analog("distance").unwrap() - 1.5).abs() < 1e-5);
+
+    missed.sense(&objects, &query_pipeline, &mut signals);
+    assert_eq!(signals.digital("part_present"), Some(false));
+    assert_eq!(missed.blocked_by, None);
+}
@@ -1 +1,2 @@
-pub mod space;
\ No newline at end of file
+pub mod space;
+pub mod random;
\ No newline at end of file
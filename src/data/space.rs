@@ -39,6 +39,12 @@ pub struct Iter<'a, T: 'a> {
     inner: iter::Enumerate<slice::Iter<'a, Entry<T>>>,
 }
 
+#[derive(Debug)]
+pub struct IterMut<'a, T: 'a> {
+    len: usize,
+    inner: iter::Enumerate<slice::IterMut<'a, Entry<T>>>,
+}
+
 impl<T> Space<T> {
     pub fn new() -> Space<T> {
         Space::with_capacity(CAPACITY)
@@ -131,6 +137,27 @@ impl<T> Space<T> {
             .expect("error")
     }
 
+    /// Removes the value at `i`. `i` is never valid again, even once its slot is reused, the other indices stay valid.
+    pub fn remove(&mut self, i: Index) -> Option<T> {
+        match self.items.get(i.index as usize) {
+            Some(Entry::Used { generation, .. }) if *generation == i.generation => {}
+            _ => return None,
+        }
+
+        let entry = std::mem::replace(
+            &mut self.items[i.index as usize],
+            Entry::Free { next_free: self.list_head },
+        );
+        self.list_head = Some(i.index);
+        self.generation += 1;
+        self.len -= 1;
+
+        match entry {
+            Entry::Used { val, .. } => Some(val),
+            Entry::Free { .. } => None,
+        }
+    }
+
     pub fn get(&self, i: Index) -> Option<&T> {
         match self.items.get(i.index as usize) {
             Some(Entry::Used { generation, val }) if *generation == i.generation => {
@@ -155,6 +182,13 @@ impl<T> Space<T> {
             inner: self.items.iter().enumerate(),
         }
     }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            len: self.len,
+            inner: self.items.iter_mut().enumerate(),
+        }
+    }
 }
 
 impl Index {
@@ -275,6 +309,55 @@ impl<'a, T> ExactSizeIterator for Iter<'a, T> {
 
 impl<'a, T> FusedIterator for Iter<'a, T> {}
 
+impl<'a, T> IntoIterator for &'a mut Space<T> {
+    type Item = (Index, &'a mut T);
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (Index, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next() {
+                Some((_, &mut Entry::Free { .. })) => continue,
+                Some((
+                    index,
+                    &mut Entry::Used {
+                        generation,
+                        ref mut val,
+                    },
+                )) => {
+                    self.len -= 1;
+                    let idx = Index {
+                        index: index as u32,
+                        generation,
+                    };
+
+                    return Some((idx, val));
+                }
+                None => {
+                    debug_assert_eq!(self.len, 0);
+
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, T> FusedIterator for IterMut<'a, T> {}
+
 impl<T> ops::Index<Index> for Space<T> {
     type Output = T;
 
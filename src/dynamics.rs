@@ -0,0 +1,10 @@
+mod actuator;
+mod controller;
+mod forward_dynamics;
+mod inverse_dynamics;
+
+
+pub use self::actuator::Actuator;
+pub use self::controller::{JointController, JointDrive, JointDrives, JointSetpoint, Pid, PidGains};
+pub use self::forward_dynamics::{ForwardDynamics, Integrator, JointFriction};
+pub use self::inverse_dynamics::InverseDynamics;
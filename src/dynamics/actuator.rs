@@ -0,0 +1,110 @@
+use std::f32::consts::PI;
+
+
+/// A motor driving a joint through a gearbox, with its encoder on the motor side.
+///
+/// The default actuator is ideal: direct drive, unlimited and exactly measured. The backlash is only seen by the
+/// encoder: the torque reaches the joint without any dead band.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Actuator {
+    /// Motor turns per joint turn.
+    pub gear_ratio: f32,
+    /// The largest motor torque.
+    pub max_torque: f32,
+    /// The largest motor velocity, the motor stops accelerating beyond it.
+    pub max_velocity: f32,
+    /// The play of the gearbox, in joint radians, between the joint and the encoder. It offsets the measured
+    /// position when the motion reverses, the transmitted torque isn't affected.
+    pub backlash: f32,
+    /// Encoder counts per motor turn, zero for an exact measure.
+    pub encoder_counts: u32,
+    /// The joint position seen through the gearbox, with its play.
+    motor_position: Option<f32>,
+    last_measure: Option<f32>,
+}
+
+impl Default for Actuator {
+    fn default() -> Self {
+        Self {
+            gear_ratio: 1.0,
+            max_torque: f32::MAX,
+            max_velocity: f32::MAX,
+            backlash: 0.0,
+            encoder_counts: 0,
+            motor_position: None,
+            last_measure: None,
+        }
+    }
+}
+
+impl Actuator {
+    pub fn new(gear_ratio: f32, max_torque: f32, max_velocity: f32) -> Self {
+        Self { gear_ratio, max_torque, max_velocity, ..Self::default() }
+    }
+
+    pub fn with_backlash(mut self, backlash: f32) -> Self {
+        self.backlash = backlash;
+        self
+    }
+
+    pub fn with_encoder(mut self, counts: u32) -> Self {
+        self.encoder_counts = counts;
+        self
+    }
+
+    /// The largest torque on the joint side.
+    pub fn max_joint_torque(&self) -> f32 {
+        self.max_torque * self.gear_ratio
+    }
+
+    /// The largest velocity on the joint side.
+    pub fn max_joint_velocity(&self) -> f32 {
+        self.max_velocity / self.gear_ratio
+    }
+
+    pub fn reset(&mut self) {
+        self.motor_position = None;
+        self.last_measure = None;
+    }
+
+    /// The joint position and velocity seen by the controller, measured every `dt` on the motor side.
+    ///
+    /// The play of the gearbox lets the measured motor position lag behind the joint when the motion reverses,
+    /// and the encoder quantizes it. The velocity is the difference of the measures.
+    pub fn sense(&mut self, joint_position: f32, dt: f32) -> (f32, f32) {
+        let half_play = self.backlash / 2.0;
+        let motor = match self.motor_position {
+            Some(motor) => motor.clamp(joint_position - half_play, joint_position + half_play),
+            None => joint_position,
+        };
+        self.motor_position = Some(motor);
+
+        let measure = if self.encoder_counts > 0 {
+            let resolution = 2.0 * PI / (self.encoder_counts as f32 * self.gear_ratio);
+            (motor / resolution).round() * resolution
+        } else {
+            motor
+        };
+
+        let velocity = match self.last_measure {
+            Some(last) if dt > 0.0 => (measure - last) / dt,
+            _ => 0.0,
+        };
+        self.last_measure = Some(measure);
+
+        (measure, velocity)
+    }
+
+    /// The joint torque produced for the commanded joint torque, at the joint velocity `joint_velocity`.
+    pub fn output(&self, command: f32, joint_velocity: f32) -> f32 {
+        let max = self.max_joint_torque();
+        let torque = command.clamp(-max, max);
+
+        // Past the velocity limit the motor can only brake
+        if joint_velocity.abs() >= self.max_joint_velocity() && torque * joint_velocity > 0.0 {
+            0.0
+        } else {
+            torque
+        }
+    }
+}
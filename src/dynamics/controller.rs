@@ -0,0 +1,180 @@
+use nalgebra::DVector;
+use crate::dynamics::Actuator;
+
+
+/// The motion a joint has to follow at one instant, with the torque expected to produce it.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct JointSetpoint {
+    pub position: f32,
+    pub velocity: f32,
+    pub acceleration: f32,
+    /// The feedforward torque, typically from inverse dynamics.
+    pub torque: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct PidGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    /// The largest absolute value of the integral term, to limit windup.
+    pub integral_limit: f32,
+}
+
+impl PidGains {
+    pub fn new(kp: f32, ki: f32, kd: f32) -> Self {
+        Self { kp, ki, kd, integral_limit: f32::MAX }
+    }
+
+    pub fn with_integral_limit(mut self, limit: f32) -> Self {
+        self.integral_limit = limit;
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Pid {
+    pub gains: PidGains,
+    integral: f32,
+}
+
+impl Pid {
+    pub fn new(gains: PidGains) -> Self {
+        Self { gains, integral: 0.0 }
+    }
+
+    /// The output for `error`, changing at the rate `error_rate`.
+    pub fn update(&mut self, error: f32, error_rate: f32, dt: f32) -> f32 {
+        let limit = self.gains.integral_limit;
+        self.integral = (self.integral + self.gains.ki * error * dt).clamp(-limit, limit);
+
+        self.gains.kp * error + self.integral + self.gains.kd * error_rate
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+    }
+}
+
+/// Computes the torque of a joint from its setpoint and its measured state.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JointController {
+    /// PID on the position error, its derivative being the velocity error, plus the feedforward torque.
+    Pid(Pid),
+    /// A proportional position loop commanding the velocity of a PID velocity loop, plus the feedforward torque.
+    Cascade {
+        position_gain: f32,
+        velocity: Pid,
+    },
+}
+
+impl JointController {
+    pub fn pid(gains: PidGains) -> Self {
+        JointController::Pid(Pid::new(gains))
+    }
+
+    pub fn cascade(position_gain: f32, velocity_gains: PidGains) -> Self {
+        JointController::Cascade { position_gain, velocity: Pid::new(velocity_gains) }
+    }
+
+    pub fn torque(&mut self, setpoint: &JointSetpoint, position: f32, velocity: f32, dt: f32) -> f32 {
+        let feedback = match self {
+            JointController::Pid(pid) => {
+                pid.update(setpoint.position - position, setpoint.velocity - velocity, dt)
+            }
+            JointController::Cascade { position_gain, velocity: pid } => {
+                let command = setpoint.velocity + *position_gain * (setpoint.position - position);
+                pid.update(command - velocity, 0.0, dt)
+            }
+        };
+
+        feedback + setpoint.torque
+    }
+
+    pub fn reset(&mut self) {
+        match self {
+            JointController::Pid(pid) | JointController::Cascade { velocity: pid, .. } => pid.reset(),
+        }
+    }
+}
+
+/// The controller and the actuator of a joint.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JointDrive {
+    pub controller: JointController,
+    pub actuator: Actuator,
+}
+
+impl JointDrive {
+    pub fn new(controller: JointController, actuator: Actuator) -> Self {
+        Self { controller, actuator }
+    }
+}
+
+/// The drives of every joint of a chain, between the commanded joint positions and the simulated joints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointDrives {
+    pub drives: Vec<JointDrive>,
+    /// The last commanded positions and velocities, to differentiate the command.
+    command: Option<(DVector<f32>, DVector<f32>)>,
+}
+
+impl JointDrives {
+    pub fn new(drives: Vec<JointDrive>) -> Self {
+        Self { drives, command: None }
+    }
+
+    /// The same drive for `ndofs` joints.
+    pub fn uniform(ndofs: usize, drive: JointDrive) -> Self {
+        Self::new(vec![drive; ndofs])
+    }
+
+    /// Forgets the command and the controller and actuator states.
+    pub fn reset(&mut self) {
+        self.command = None;
+
+        for drive in &mut self.drives {
+            drive.controller.reset();
+            drive.actuator.reset();
+        }
+    }
+
+    /// The setpoints following the commanded joint positions `command`, sent every `dt`, without feedforward torque.
+    pub fn setpoints(&mut self, command: &DVector<f32>, dt: f32) -> Vec<JointSetpoint> {
+        let (velocity, acceleration) = match &self.command {
+            Some((position, velocity)) if position.len() == command.len() && dt > 0.0 => {
+                let new_velocity = (command - position) / dt;
+                let acceleration = (&new_velocity - velocity) / dt;
+                (new_velocity, acceleration)
+            }
+            _ => (DVector::zeros(command.len()), DVector::zeros(command.len())),
+        };
+
+        let setpoints = command.iter()
+            .zip(velocity.iter().zip(acceleration.iter()))
+            .map(|(position, (velocity, acceleration))| JointSetpoint {
+                position: *position,
+                velocity: *velocity,
+                acceleration: *acceleration,
+                torque: 0.0,
+            })
+            .collect();
+
+        self.command = Some((command.clone(), velocity));
+        setpoints
+    }
+
+    /// The joint torques applied by the actuators to follow `setpoints`, with the joints at the positions `q`
+    /// and velocities `qd`. Joints without a drive get no torque.
+    pub fn torques(&mut self, setpoints: &[JointSetpoint], q: &DVector<f32>, qd: &DVector<f32>, dt: f32) -> DVector<f32> {
+        let mut torques = DVector::zeros(q.len());
+
+        for (i, (drive, setpoint)) in self.drives.iter_mut().zip(setpoints).enumerate().take(q.len()) {
+            let (position, velocity) = drive.actuator.sense(q[i], dt);
+            let command = drive.controller.torque(setpoint, position, velocity, dt);
+            torques[i] = drive.actuator.output(command, qd[i]);
+        }
+
+        torques
+    }
+}
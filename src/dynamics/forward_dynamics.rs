@@ -0,0 +1,295 @@
+use nalgebra::{DVector, Matrix3, Matrix6, Point3, Vector3, Vector6};
+use crate::errors::KinematicsError;
+use crate::kinematics::KinematicChain;
+use crate::mesh::{MassProperties, ObjectSet};
+use super::inverse_dynamics::{check_dofs, link_mass_properties, link_states};
+
+
+/// The joint velocity below which Coulomb friction ramps down linearly, so that the joints can come to rest.
+const STICTION_VELOCITY: f32 = 1.0e-3;
+
+/// The friction opposing the motion of a joint.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct JointFriction {
+    /// The torque per unit of joint velocity.
+    pub viscous: f32,
+    /// The constant torque opposing any motion.
+    pub coulomb: f32,
+}
+
+impl JointFriction {
+    pub fn new(viscous: f32, coulomb: f32) -> Self {
+        Self { viscous, coulomb }
+    }
+
+    /// The friction torque at the joint velocity `qd`, opposed to it.
+    pub fn torque(&self, qd: f32) -> f32 {
+        -self.viscous * qd - self.coulomb * (qd / STICTION_VELOCITY).clamp(-1.0, 1.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    SemiImplicitEuler,
+    RungeKutta4,
+}
+
+/// Articulated-body forward dynamics: the joint accelerations produced by joint torques, and their integration.
+///
+/// The mass properties of every link are the ones of its object, and the friction is the one of its chain link.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ForwardDynamics {
+    pub gravity: Vector3<f32>,
+    pub integrator: Integrator,
+    /// The number of integration steps in every call to `step`.
+    pub substeps: usize,
+}
+
+impl Default for ForwardDynamics {
+    fn default() -> Self {
+        Self {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            integrator: Integrator::SemiImplicitEuler,
+            substeps: 10,
+        }
+    }
+}
+
+/// The spatial cross product of motion vectors, with angular components first.
+fn cross_motion(v: &Vector6<f32>) -> Matrix6<f32> {
+    let (w, v) = (v.fixed_rows::<3>(0).cross_matrix(), v.fixed_rows::<3>(3).cross_matrix());
+    let mut result = Matrix6::zeros();
+    result.fixed_slice_mut::<3, 3>(0, 0).copy_from(&w);
+    result.fixed_slice_mut::<3, 3>(3, 0).copy_from(&v);
+    result.fixed_slice_mut::<3, 3>(3, 3).copy_from(&w);
+    result
+}
+
+/// The spatial cross product of a motion vector with force vectors.
+fn cross_force(v: &Vector6<f32>) -> Matrix6<f32> {
+    -cross_motion(v).transpose()
+}
+
+/// The spatial inertia of a body around the world origin.
+fn spatial_inertia(mass: f32, com: &Point3<f32>, inertia: &Matrix3<f32>) -> Matrix6<f32> {
+    let c = com.coords.cross_matrix();
+    let mut result = Matrix6::zeros();
+    result.fixed_slice_mut::<3, 3>(0, 0).copy_from(&(inertia - c * c * mass));
+    result.fixed_slice_mut::<3, 3>(0, 3).copy_from(&(c * mass));
+    result.fixed_slice_mut::<3, 3>(3, 0).copy_from(&(-c * mass));
+    result.fixed_slice_mut::<3, 3>(3, 3).copy_from(&(Matrix3::identity() * mass));
+    result
+}
+
+impl ForwardDynamics {
+    /// The joint accelerations produced by the joint torques `tau` at the joint positions `q` and velocities `qd`,
+    /// with the friction of the joints.
+    ///
+    /// `payload` is carried at the flange, its mass properties are expressed in the flange frame.
+    pub fn accelerations(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &DVector<f32>,
+        qd: &DVector<f32>,
+        tau: &DVector<f32>,
+        payload: Option<&MassProperties>,
+    ) -> Result<DVector<f32>, KinematicsError> {
+        self.locked_accelerations(chain, objects, q, qd, tau, payload, &vec![false; chain.ndofs()])
+    }
+
+    /// Like `accelerations`, with the `locked` joints held rigid: they don't accelerate and pass every load on to
+    /// the links before them.
+    #[allow(clippy::too_many_arguments)]
+    fn locked_accelerations(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &DVector<f32>,
+        qd: &DVector<f32>,
+        tau: &DVector<f32>,
+        payload: Option<&MassProperties>,
+        locked: &[bool],
+    ) -> Result<DVector<f32>, KinematicsError> {
+        check_dofs(chain, &[q, qd, tau])?;
+
+        // Spatial vectors are expressed in world space, around the world origin
+        let links = link_states(chain, &link_mass_properties(chain, objects, payload), q);
+        let n = links.len();
+        let mut subspaces = Vec::with_capacity(n);
+        let mut biases = Vec::with_capacity(n);
+        let mut inertias = Vec::with_capacity(n);
+        let mut forces = Vec::with_capacity(n);
+        let mut velocity = Vector6::zeros();
+
+        for (i, link) in links.iter().enumerate() {
+            let subspace = if link.angular {
+                let moment = link.origin.coords.cross(&link.axis);
+                Vector6::new(link.axis.x, link.axis.y, link.axis.z, moment.x, moment.y, moment.z)
+            } else {
+                Vector6::new(0.0, 0.0, 0.0, link.axis.x, link.axis.y, link.axis.z)
+            };
+            let joint_velocity = subspace * qd[i];
+
+            velocity += joint_velocity;
+
+            let inertia = spatial_inertia(link.mass, &link.com, &link.inertia);
+            subspaces.push(subspace);
+            biases.push(cross_motion(&velocity) * joint_velocity);
+            forces.push(cross_force(&velocity) * (inertia * velocity));
+            inertias.push(inertia);
+        }
+
+        // Inward: the articulated inertias, each link supporting the ones after it
+        let mut projected = Vec::with_capacity(n);
+
+        for i in (0..n).rev() {
+            if locked[i] {
+                if i > 0 {
+                    let (inertia, force) = (inertias[i], forces[i] + inertias[i] * biases[i]);
+                    inertias[i - 1] += inertia;
+                    forces[i - 1] += force;
+                }
+
+                projected.push((Vector6::zeros(), 0.0, 0.0));
+                continue;
+            }
+
+            let u = inertias[i] * subspaces[i];
+            let d = subspaces[i].dot(&u);
+            let friction = chain.links[i].friction.torque(qd[i]);
+            let torque = tau[i] + friction - subspaces[i].dot(&forces[i]);
+
+            if i > 0 && d > f32::EPSILON {
+                let inertia = inertias[i] - u * u.transpose() / d;
+                let force = forces[i] + inertia * biases[i] + u * (torque / d);
+                inertias[i - 1] += inertia;
+                forces[i - 1] += force;
+            }
+
+            projected.push((u, d, torque));
+        }
+
+        projected.reverse();
+
+        // Outward: the accelerations, gravity as an upward acceleration of the base
+        let mut accelerations = DVector::zeros(n);
+        let mut acceleration = Vector6::new(0.0, 0.0, 0.0, -self.gravity.x, -self.gravity.y, -self.gravity.z);
+
+        for (i, (u, d, torque)) in projected.into_iter().enumerate() {
+            acceleration += biases[i];
+
+            if d > f32::EPSILON {
+                accelerations[i] = (torque - u.dot(&acceleration)) / d;
+            }
+
+            acceleration += subspaces[i] * accelerations[i];
+        }
+
+        Ok(accelerations)
+    }
+
+    /// Advances the joint positions `q` and velocities `qd` by `dt` under the constant joint torques `tau`,
+    /// in `substeps` integration steps.
+    #[allow(clippy::too_many_arguments)]
+    pub fn step(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &mut DVector<f32>,
+        qd: &mut DVector<f32>,
+        tau: &DVector<f32>,
+        payload: Option<&MassProperties>,
+        dt: f32,
+    ) -> Result<(), KinematicsError> {
+        let h = dt / self.substeps.max(1) as f32;
+
+        for _ in 0..self.substeps.max(1) {
+            self.integrate(chain, objects, q, qd, tau, payload, h)?;
+        }
+
+        Ok(())
+    }
+
+    /// The joint accelerations, with the joints resting on a limit and pushed past it held still.
+    ///
+    /// Locking a joint changes the loads on the other ones, so the joints are locked until none is pushed past its
+    /// limit anymore.
+    fn limited_accelerations(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &DVector<f32>,
+        qd: &DVector<f32>,
+        tau: &DVector<f32>,
+        payload: Option<&MassProperties>,
+    ) -> Result<DVector<f32>, KinematicsError> {
+        let limits = chain.limits();
+        let mut locked = vec![false; chain.ndofs()];
+
+        loop {
+            let accelerations = self.locked_accelerations(chain, objects, q, qd, tau, payload, &locked)?;
+            let mut changed = false;
+
+            for (i, limits) in limits.iter().enumerate() {
+                let pushed = (q[i] <= limits.min && qd[i] <= 0.0 && accelerations[i] < 0.0)
+                    || (q[i] >= limits.max && qd[i] >= 0.0 && accelerations[i] > 0.0);
+
+                if pushed && !locked[i] {
+                    locked[i] = true;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return Ok(accelerations);
+            }
+        }
+    }
+
+    /// A single integration step of `h`. The joints stop at their limits.
+    #[allow(clippy::too_many_arguments)]
+    pub fn integrate(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &mut DVector<f32>,
+        qd: &mut DVector<f32>,
+        tau: &DVector<f32>,
+        payload: Option<&MassProperties>,
+        h: f32,
+    ) -> Result<(), KinematicsError> {
+        let accelerations = |q: &DVector<f32>, qd: &DVector<f32>| {
+            self.limited_accelerations(chain, objects, q, qd, tau, payload)
+        };
+
+        match self.integrator {
+            Integrator::SemiImplicitEuler => {
+                *qd += accelerations(q, qd)? * h;
+                *q += &*qd * h;
+            }
+            Integrator::RungeKutta4 => {
+                let k1 = (qd.clone(), accelerations(q, qd)?);
+                let (q2, qd2) = (&*q + &k1.0 * (h / 2.0), &*qd + &k1.1 * (h / 2.0));
+                let k2 = (qd2.clone(), accelerations(&q2, &qd2)?);
+                let (q3, qd3) = (&*q + &k2.0 * (h / 2.0), &*qd + &k2.1 * (h / 2.0));
+                let k3 = (qd3.clone(), accelerations(&q3, &qd3)?);
+                let (q4, qd4) = (&*q + &k3.0 * h, &*qd + &k3.1 * h);
+                let k4 = (qd4.clone(), accelerations(&q4, &qd4)?);
+
+                *q += (k1.0 + k2.0 * 2.0 + k3.0 * 2.0 + k4.0) * (h / 6.0);
+                *qd += (k1.1 + k2.1 * 2.0 + k3.1 * 2.0 + k4.1) * (h / 6.0);
+            }
+        }
+
+        for (i, limits) in chain.limits().iter().enumerate() {
+            if (q[i] <= limits.min && qd[i] < 0.0) || (q[i] >= limits.max && qd[i] > 0.0) {
+                qd[i] = 0.0;
+            }
+
+            q[i] = q[i].clamp(limits.min, limits.max);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use nalgebra::{DVector, Matrix3, Point3, Vector3};
+use crate::errors::KinematicsError;
+use crate::kinematics::KinematicChain;
+use crate::mesh::{MassProperties, ObjectSet};
+
+
+/// Recursive Newton–Euler inverse dynamics: the joint torques (or forces, for prismatic joints) producing a motion.
+///
+/// The mass properties of every link are the ones of its object; links without objects are massless.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InverseDynamics {
+    pub gravity: Vector3<f32>,
+}
+
+impl Default for InverseDynamics {
+    fn default() -> Self {
+        Self {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+        }
+    }
+}
+
+/// The world-space state of a link needed by the recursions.
+pub(crate) struct LinkState {
+    /// The joint origin and axis, the origin being attached to the link.
+    pub origin: Point3<f32>,
+    pub axis: Vector3<f32>,
+    pub angular: bool,
+    pub com: Point3<f32>,
+    pub mass: f32,
+    /// The angular inertia around the center of mass, in world space.
+    pub inertia: Matrix3<f32>,
+}
+
+/// The mass properties of every link in its own frame, the payload being added to the last link.
+///
+/// `payload` is expressed in the flange frame.
+pub(crate) fn link_mass_properties(
+    chain: &KinematicChain,
+    objects: &ObjectSet,
+    payload: Option<&MassProperties>,
+) -> Vec<MassProperties> {
+    let mut properties: Vec<_> = chain.links.iter()
+        .map(|link| {
+            link.object
+                .and_then(|handle| objects.get(handle))
+                .map(|obj| *obj.mass_properties())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    if let (Some(payload), Some(last)) = (payload, properties.last_mut()) {
+        *last += payload.transform_by(&chain.flange);
+    }
+
+    properties
+}
+
+pub(crate) fn link_states(chain: &KinematicChain, properties: &[MassProperties], q: &DVector<f32>) -> Vec<LinkState> {
+    chain.joint_frames(q).iter()
+        .zip(chain.link_poses(q))
+        .zip(chain.links.iter().zip(properties))
+        .map(|((frame, pose), (link, properties))| {
+            let (axis, angular) = match link.joint.free_axis() {
+                Some(axis) => (frame.rotation * axis.direction(), axis.is_angular()),
+                None => (Vector3::zeros(), true),
+            };
+            let rotation = pose.rotation.to_rotation_matrix();
+
+            LinkState {
+                origin: Point3::from(frame.translation.vector),
+                axis,
+                angular,
+                com: pose * properties.local_com,
+                mass: properties.mass(),
+                inertia: rotation.matrix() * properties.reconstruct_inertia_matrix() * rotation.matrix().transpose(),
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn check_dofs(chain: &KinematicChain, vectors: &[&DVector<f32>]) -> Result<(), KinematicsError> {
+    match vectors.iter().find(|v| v.len() != chain.ndofs()) {
+        Some(v) => Err(KinematicsError::DofMismatch { expected: chain.ndofs(), found: v.len() }),
+        None => Ok(()),
+    }
+}
+
+impl InverseDynamics {
+    /// The joint torques giving the joint accelerations `qdd` at the joint positions `q` and velocities `qd`.
+    ///
+    /// `payload` is carried at the flange, its mass properties are expressed in the flange frame.
+    pub fn torques(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &DVector<f32>,
+        qd: &DVector<f32>,
+        qdd: &DVector<f32>,
+        payload: Option<&MassProperties>,
+    ) -> Result<DVector<f32>, KinematicsError> {
+        check_dofs(chain, &[q, qd, qdd])?;
+
+        let links = link_states(chain, &link_mass_properties(chain, objects, payload), q);
+        let n = links.len();
+
+        // Outward: the velocities and accelerations of every link, gravity as an upward acceleration of the base
+        let mut angvel = Vector3::zeros();
+        let mut angacc = Vector3::zeros();
+        let mut origin = Point3::from(chain.base.translation.vector);
+        let mut linacc = -self.gravity;
+        let mut com_accs = Vec::with_capacity(n);
+        let mut angaccs = Vec::with_capacity(n);
+        let mut angvels = Vec::with_capacity(n);
+
+        for (i, link) in links.iter().enumerate() {
+            let r = link.origin - origin;
+            linacc += angacc.cross(&r) + angvel.cross(&angvel.cross(&r));
+
+            if link.angular {
+                angacc += qdd[i] * link.axis + angvel.cross(&(qd[i] * link.axis));
+                angvel += qd[i] * link.axis;
+            } else {
+                linacc += qdd[i] * link.axis + 2.0 * angvel.cross(&(qd[i] * link.axis));
+            }
+
+            let c = link.com - link.origin;
+            com_accs.push(linacc + angacc.cross(&c) + angvel.cross(&angvel.cross(&c)));
+            angaccs.push(angacc);
+            angvels.push(angvel);
+            origin = link.origin;
+        }
+
+        // Inward: the force and the moment around the joint origin transmitted by every joint
+        let mut torques = DVector::zeros(n);
+        let mut force = Vector3::zeros();
+        let mut moment = Vector3::zeros();
+        let mut next_origin = origin;
+
+        for i in (0..n).rev() {
+            let link = &links[i];
+            let link_force = link.mass * com_accs[i];
+            let link_moment = link.inertia * angaccs[i] + angvels[i].cross(&(link.inertia * angvels[i]));
+
+            moment += (next_origin - link.origin).cross(&force)
+                + link_moment
+                + (link.com - link.origin).cross(&link_force);
+            force += link_force;
+            next_origin = link.origin;
+
+            torques[i] = if link.angular { moment.dot(&link.axis) } else { force.dot(&link.axis) };
+        }
+
+        Ok(torques)
+    }
+
+    /// The joint torques holding the chain still at `q`.
+    pub fn gravity_torques(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &DVector<f32>,
+        payload: Option<&MassProperties>,
+    ) -> Result<DVector<f32>, KinematicsError> {
+        let zeros = chain.zeros();
+        self.torques(chain, objects, q, &zeros, &zeros, payload)
+    }
+
+    /// The largest absolute torque of every joint along a sampled motion, given as joint positions, velocities
+    /// and accelerations.
+    pub fn peak_torques<'a>(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        states: impl IntoIterator<Item = (&'a DVector<f32>, &'a DVector<f32>, &'a DVector<f32>)>,
+        payload: Option<&MassProperties>,
+    ) -> Result<DVector<f32>, KinematicsError> {
+        let mut peaks: DVector<f32> = DVector::zeros(chain.ndofs());
+
+        for (q, qd, qdd) in states {
+            let torques = self.torques(chain, objects, q, qd, qdd, payload)?;
+            peaks.zip_apply(&torques, |peak, torque| *peak = peak.max(torque.abs()));
+        }
+
+        Ok(peaks)
+    }
+
+    /// The largest point mass that can be held at `com`, in the flange frame, with the joints at `q`, without
+    /// any joint torque exceeding `torque_limits`.
+    ///
+    /// Returns zero if the chain alone already exceeds the limits.
+    pub fn max_payload(
+        &self,
+        chain: &KinematicChain,
+        objects: &ObjectSet,
+        q: &DVector<f32>,
+        com: &Point3<f32>,
+        torque_limits: &DVector<f32>,
+    ) -> Result<f32, KinematicsError> {
+        check_dofs(chain, &[torque_limits])?;
+
+        // The torques are affine in the payload mass
+        let unit = MassProperties::new(*com, 1.0, Vector3::zeros());
+        let empty = self.gravity_torques(chain, objects, q, None)?;
+        let per_mass = self.gravity_torques(chain, objects, q, Some(&unit))? - &empty;
+        let mut max: f32 = f32::INFINITY;
+
+        for ((torque, slope), limit) in empty.iter().zip(per_mass.iter()).zip(torque_limits.iter()) {
+            if torque.abs() > *limit {
+                return Ok(0.0);
+            }
+
+            if slope.abs() > f32::EPSILON {
+                max = max.min((limit - torque * slope.signum()) / slope.abs());
+            }
+        }
+
+        Ok(max)
+    }
+}
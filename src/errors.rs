@@ -7,4 +7,130 @@ pub enum ObjError {
 
     #[error("Unknown vertex format")]
     UnknownVertexFormat
-}
\ No newline at end of file
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum KinematicsError {
+    #[error("Expected {expected} joint positions, found {found}")]
+    DofMismatch { expected: usize, found: usize },
+
+    #[error("Target is unreachable (residual {residual})")]
+    Unreachable { residual: f32 },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("line {line}, column {column}: {message}")]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ProgramError {
+    #[error("line {line}: unknown variable `{name}`")]
+    UnknownVariable { line: usize, name: String },
+
+    #[error("line {line}: unknown label `{label}`")]
+    UnknownLabel { line: usize, label: String },
+
+    #[error("line {line}: unknown tool {tool}")]
+    UnknownTool { line: usize, tool: usize },
+
+    #[error("line {line}: unknown base {base}")]
+    UnknownBase { line: usize, base: usize },
+
+    #[error("line {line}: expected {expected} joint values, found {found}")]
+    JointCount { line: usize, expected: usize, found: usize },
+
+    #[error("line {line}: the points of the circular motion are aligned")]
+    DegenerateCircle { line: usize },
+
+    #[error("line {line}: {source}")]
+    Kinematics { line: usize, source: KinematicsError },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ExportError {
+    #[error("line {line}: unknown variable `{name}`")]
+    UnknownVariable { line: usize, name: String },
+
+    #[error("line {line}: variable `{name}` holds both joint and cartesian targets")]
+    TypeConflict { line: usize, name: String },
+
+    #[error("line {line}: tool {tool} isn't defined in the cell")]
+    UnknownTool { line: usize, tool: usize },
+
+    #[error("line {line}: base {base} isn't defined in the cell")]
+    UnknownBase { line: usize, base: usize },
+
+    #[error("line {line}: circular motion without auxiliary point")]
+    MissingAuxiliaryPoint { line: usize },
+
+    #[error("line {line}: {feature} isn't supported by {language}")]
+    Unsupported { line: usize, language: &'static str, feature: &'static str },
+
+    #[error("{frame} 0 is the bare {frame} of the robot, it can't be defined in the cell")]
+    ReservedFrame { frame: &'static str },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FrameError {
+    #[error("Unknown frame `{0}`")]
+    UnknownFrame(String),
+
+    #[error("Frame `{0}` already exists")]
+    DuplicateFrame(String),
+
+    #[error("Frame `{0}` is static")]
+    StaticFrame(String),
+
+    #[error("Attaching frame `{0}` would create a cycle")]
+    Cycle(String),
+
+    #[error("Frame `{0}` still has child frames")]
+    HasChildren(String),
+
+    #[error("No transform of `{frame}` is known at timestep {timestep}")]
+    NoHistory { frame: String, timestep: usize },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SignalError {
+    #[error("Unknown signal `{0}`")]
+    UnknownSignal(String),
+
+    #[error("Signal `{0}` already exists")]
+    DuplicateSignal(String),
+
+    #[error("Port {port} is already used by signal `{name}`")]
+    DuplicatePort { name: String, port: usize },
+
+    #[error("Signal `{0}` doesn't hold this kind of value")]
+    TypeMismatch(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PlanningError {
+    #[error("Expected {expected} joint positions, found {found}")]
+    DofMismatch { expected: usize, found: usize },
+
+    #[error("The start configuration is out of the joint limits or in collision")]
+    InvalidStart,
+
+    #[error("The goal configuration is out of the joint limits or in collision")]
+    InvalidGoal,
+
+    #[error("No collision-free path found")]
+    NoPath,
+}
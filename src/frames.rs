@@ -0,0 +1,4 @@
+mod tree;
+
+
+pub use self::tree::{FrameKind, FrameParent, FrameTree, WORLD_FRAME};
\ No newline at end of file
@@ -0,0 +1,278 @@
+use std::collections::{HashMap, VecDeque};
+use nalgebra::Isometry3;
+use crate::errors::FrameError;
+use crate::mesh::{ObjectHandle, ObjectSet};
+
+
+/// The name of the root frame of every tree.
+pub const WORLD_FRAME: &str = "world";
+
+/// What a frame is attached to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FrameParent {
+    World,
+    /// A scene object, the frame follows it as it moves.
+    Object(ObjectHandle),
+    Frame(String),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    /// A transform that never changes and is valid at every timestep.
+    Static,
+    /// A transform updated over time, with a history of its past values.
+    Dynamic,
+}
+
+#[derive(Clone, Debug)]
+struct Frame {
+    parent: FrameParent,
+    kind: FrameKind,
+    /// Transforms wrt the parent, by increasing timestep.
+    history: VecDeque<(usize, Isometry3<f32>)>,
+}
+
+/// A registry of named coordinate frames, attached to the world, to scene objects or to each other.
+///
+/// The tree keeps the transforms of the last `history_len` timesteps, so that frames can be looked up in the past.
+#[derive(Clone, Debug)]
+pub struct FrameTree {
+    frames: HashMap<String, Frame>,
+    /// Recorded positions of the objects that frames are attached to.
+    objects: HashMap<ObjectHandle, VecDeque<(usize, Isometry3<f32>)>>,
+    timestep: usize,
+    pub history_len: usize,
+}
+
+impl Default for FrameTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameTree {
+    pub fn new() -> Self {
+        Self::with_history(600)
+    }
+
+    pub fn with_history(history_len: usize) -> Self {
+        Self {
+            frames: HashMap::new(),
+            objects: HashMap::new(),
+            timestep: 0,
+            history_len,
+        }
+    }
+
+    /// The timestep of the last update.
+    pub fn timestep(&self) -> usize {
+        self.timestep
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        name == WORLD_FRAME || self.frames.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.frames.keys().map(|name| name.as_str())
+    }
+
+    pub fn parent(&self, name: &str) -> Option<&FrameParent> {
+        self.frames.get(name).map(|frame| &frame.parent)
+    }
+
+    /// Adds the frame `name`, placed at `transform` in its parent frame.
+    ///
+    /// Frames attached to an object can be looked up once the tree was updated with the object positions.
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        parent: FrameParent,
+        transform: Isometry3<f32>,
+        kind: FrameKind,
+    ) -> Result<(), FrameError> {
+        let name = name.into();
+
+        if self.contains(&name) {
+            return Err(FrameError::DuplicateFrame(name));
+        }
+
+        if let FrameParent::Frame(parent) = &parent {
+            if !self.contains(parent) {
+                return Err(FrameError::UnknownFrame(parent.clone()));
+            }
+        }
+
+        if let FrameParent::Object(handle) = parent {
+            self.objects.entry(handle).or_default();
+        }
+
+        let history = VecDeque::from([(self.timestep, transform)]);
+        self.frames.insert(name, Frame { parent, kind, history });
+
+        Ok(())
+    }
+
+    /// Records the transform of the dynamic frame `name` at the current timestep.
+    pub fn set_transform(&mut self, name: &str, transform: Isometry3<f32>) -> Result<(), FrameError> {
+        let timestep = self.timestep;
+        let history_len = self.history_len;
+        let frame = self.frames.get_mut(name)
+            .ok_or_else(|| FrameError::UnknownFrame(name.to_string()))?;
+
+        if frame.kind == FrameKind::Static {
+            return Err(FrameError::StaticFrame(name.to_string()));
+        }
+
+        record(&mut frame.history, timestep, transform, history_len);
+
+        Ok(())
+    }
+
+    /// Adds the dynamic frame `name` if needed, and records its transform at the current timestep.
+    ///
+    /// Moving the frame to another parent drops its history, the past transforms being relative to the old parent.
+    pub fn set_frame(&mut self, name: &str, parent: FrameParent, transform: Isometry3<f32>) -> Result<(), FrameError> {
+        match self.frames.get(name) {
+            Some(frame) if frame.parent != parent => {
+                if frame.kind == FrameKind::Static {
+                    return Err(FrameError::StaticFrame(name.to_string()));
+                }
+
+                match &parent {
+                    FrameParent::Frame(parent) if !self.contains(parent) => {
+                        return Err(FrameError::UnknownFrame(parent.clone()));
+                    }
+                    FrameParent::Frame(parent) if self.is_ancestor(name, parent) => {
+                        return Err(FrameError::Cycle(name.to_string()));
+                    }
+                    FrameParent::Object(handle) => {
+                        self.objects.entry(*handle).or_default();
+                    }
+                    _ => {}
+                }
+
+                let timestep = self.timestep;
+                let frame = self.frames.get_mut(name).unwrap();
+                frame.parent = parent;
+                frame.history = VecDeque::from([(timestep, transform)]);
+
+                Ok(())
+            }
+            Some(_) => self.set_transform(name, transform),
+            None => self.add(name, parent, transform, FrameKind::Dynamic),
+        }
+    }
+
+    /// Removes a frame that has no children.
+    pub fn remove(&mut self, name: &str) -> Result<(), FrameError> {
+        if !self.frames.contains_key(name) {
+            return Err(FrameError::UnknownFrame(name.to_string()));
+        }
+
+        if self.frames.values().any(|frame| frame.parent == FrameParent::Frame(name.to_string())) {
+            return Err(FrameError::HasChildren(name.to_string()));
+        }
+
+        self.frames.remove(name);
+        self.objects.retain(|handle, _| {
+            self.frames.values().any(|frame| frame.parent == FrameParent::Object(*handle))
+        });
+
+        Ok(())
+    }
+
+    /// Moves to `timestep`, recording the positions of the objects frames are attached to.
+    pub fn update(&mut self, objects: &ObjectSet, timestep: usize) {
+        self.timestep = timestep;
+
+        for (handle, history) in &mut self.objects {
+            if let Some(obj) = objects.get(*handle) {
+                record(history, timestep, *obj.position(), self.history_len);
+            }
+        }
+    }
+
+    /// The current pose of the frame `to`, expressed in the frame `from`.
+    pub fn lookup(&self, from: &str, to: &str) -> Result<Isometry3<f32>, FrameError> {
+        self.lookup_at(from, to, self.timestep)
+    }
+
+    /// The pose of the frame `to` expressed in the frame `from`, as it was at `timestep`.
+    pub fn lookup_at(&self, from: &str, to: &str, timestep: usize) -> Result<Isometry3<f32>, FrameError> {
+        Ok(self.world_pose_at(from, timestep)?.inv_mul(&self.world_pose_at(to, timestep)?))
+    }
+
+    /// The current world-space pose of the frame `name`.
+    pub fn world_pose(&self, name: &str) -> Result<Isometry3<f32>, FrameError> {
+        self.world_pose_at(name, self.timestep)
+    }
+
+    pub fn world_pose_at(&self, name: &str, timestep: usize) -> Result<Isometry3<f32>, FrameError> {
+        let mut pose = Isometry3::identity();
+        let mut current = name;
+
+        // Parents are checked when frames are added, so this walk ends at the world or an object
+        while current != WORLD_FRAME {
+            let frame = self.frames.get(current)
+                .ok_or_else(|| FrameError::UnknownFrame(current.to_string()))?;
+            let transform = match frame.kind {
+                FrameKind::Static => Some(frame.history[0].1),
+                FrameKind::Dynamic => sample(&frame.history, timestep),
+            };
+            let no_history = || FrameError::NoHistory { frame: current.to_string(), timestep };
+
+            pose = transform.ok_or_else(no_history)? * pose;
+
+            match &frame.parent {
+                FrameParent::World => break,
+                FrameParent::Object(handle) => {
+                    let object = self.objects.get(handle).and_then(|history| sample(history, timestep));
+                    return Ok(object.ok_or_else(no_history)? * pose);
+                }
+                FrameParent::Frame(parent) => current = parent,
+            }
+        }
+
+        Ok(pose)
+    }
+
+    /// Whether `ancestor` is `name` or one of the frames it is attached to, directly or not.
+    fn is_ancestor(&self, ancestor: &str, name: &str) -> bool {
+        let mut current = Some(name);
+
+        while let Some(name) = current {
+            if name == ancestor {
+                return true;
+            }
+
+            current = match self.frames.get(name).map(|frame| &frame.parent) {
+                Some(FrameParent::Frame(parent)) => Some(parent),
+                _ => None,
+            };
+        }
+
+        false
+    }
+}
+
+/// Appends a transform to a history, dropping what is older than `history_len` timesteps.
+fn record(history: &mut VecDeque<(usize, Isometry3<f32>)>, timestep: usize, transform: Isometry3<f32>, history_len: usize) {
+    while matches!(history.back(), Some((step, _)) if *step >= timestep) {
+        history.pop_back();
+    }
+
+    history.push_back((timestep, transform));
+
+    // The newest entry before the window is kept, it is still valid at the start of it
+    let oldest = timestep.saturating_sub(history_len);
+
+    while history.len() > 1 && history[1].0 <= oldest {
+        history.pop_front();
+    }
+}
+
+/// The last transform recorded at or before `timestep`.
+fn sample(history: &VecDeque<(usize, Isometry3<f32>)>, timestep: usize) -> Option<Isometry3<f32>> {
+    history.iter().rev().find(|(step, _)| *step <= timestep).map(|(_, transform)| *transform)
+}
@@ -1,4 +1,4 @@
-use nalgebra::Isometry3;
+use nalgebra::{Isometry3, Point3, Translation3, Unit, UnitQuaternion, UnitVector3, Vector3};
 use crate::DOF;
 
 
@@ -92,4 +92,106 @@ impl GenericJoint {
 
         self
     }
+
+    pub fn set_local_anchor1(&mut self, anchor: Point3<f32>) -> &mut Self {
+        self.local_frame1.translation.vector = anchor.coords;
+
+        self
+    }
+
+    pub fn set_local_anchor2(&mut self, anchor: Point3<f32>) -> &mut Self {
+        self.local_frame2.translation.vector = anchor.coords;
+
+        self
+    }
+
+    /// Orients the first joint frame so that its X axis matches `axis`.
+    pub fn set_local_axis1(&mut self, axis: UnitVector3<f32>) -> &mut Self {
+        self.local_frame1.rotation = axis_rotation(axis);
+
+        self
+    }
+
+    /// Orients the second joint frame so that its X axis matches `axis`.
+    pub fn set_local_axis2(&mut self, axis: UnitVector3<f32>) -> &mut Self {
+        self.local_frame2.rotation = axis_rotation(axis);
+
+        self
+    }
+
+    pub fn set_limits(&mut self, axis: JointAxis, limits: [f32; 2]) -> &mut Self {
+        let i = axis as usize;
+        self.limits[i].min = limits[0];
+        self.limits[i].max = limits[1];
+        self.limit_axes |= axis.into();
+
+        self
+    }
+
+    pub fn free_axes(&self) -> JointAxesMask {
+        !self.locked_axes
+    }
+
+    /// The single free axis of this joint, if it has exactly one.
+    pub fn free_axis(&self) -> Option<JointAxis> {
+        let free = self.free_axes();
+
+        if free.bits().count_ones() != 1 {
+            return None;
+        }
+
+        Some(JointAxis::from_index(free.bits().trailing_zeros() as usize))
+    }
+
+    /// The limits of the free axis, or unbounded limits if the axis isn't limited.
+    pub fn free_limits(&self) -> JointLimits {
+        match self.free_axis() {
+            Some(axis) if self.limit_axes.contains(axis.into()) => self.limits[axis as usize],
+            _ => JointLimits::default(),
+        }
+    }
+
+    /// The relative motion of the second joint frame wrt. the first one
+    /// when the free axis is at position `q`.
+    pub fn motion(&self, q: f32) -> Isometry3<f32> {
+        match self.free_axis() {
+            Some(axis) if axis.is_angular() => {
+                let rotation = UnitQuaternion::from_axis_angle(&Unit::new_unchecked(axis.direction()), q);
+                Isometry3::from_parts(Translation3::identity(), rotation)
+            }
+            Some(axis) => Translation3::from(axis.direction() * q).into(),
+            None => Isometry3::identity(),
+        }
+    }
+}
+
+impl JointAxis {
+    pub fn from_index(i: usize) -> Self {
+        match i {
+            0 => JointAxis::X,
+            1 => JointAxis::Y,
+            2 => JointAxis::Z,
+            3 => JointAxis::XAngle,
+            4 => JointAxis::YAngle,
+            _ => JointAxis::ZAngle,
+        }
+    }
+
+    pub fn is_angular(self) -> bool {
+        self as usize >= 3
+    }
+
+    /// The direction of this axis in the joint frame.
+    pub fn direction(self) -> Vector3<f32> {
+        match self {
+            JointAxis::X | JointAxis::XAngle => Vector3::x(),
+            JointAxis::Y | JointAxis::YAngle => Vector3::y(),
+            JointAxis::Z | JointAxis::ZAngle => Vector3::z(),
+        }
+    }
+}
+
+fn axis_rotation(axis: UnitVector3<f32>) -> UnitQuaternion<f32> {
+    UnitQuaternion::rotation_between_axis(&Vector3::x_axis(), &axis)
+        .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::PI))
 }
\ No newline at end of file
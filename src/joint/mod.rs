@@ -1,2 +1,5 @@
 mod revolute_joint;
-mod generic_joint;
\ No newline at end of file
+mod generic_joint;
+
+pub use self::generic_joint::{GenericJoint, JointAxesMask, JointAxis, JointLimits};
+pub use self::revolute_joint::RevoluteJoint;
\ No newline at end of file
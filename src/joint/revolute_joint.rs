@@ -0,0 +1,42 @@
+use nalgebra::{Point3, UnitVector3};
+use crate::joint::{GenericJoint, JointAxesMask, JointAxis};
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RevoluteJoint {
+    pub data: GenericJoint,
+}
+
+impl RevoluteJoint {
+    pub fn new(axis: UnitVector3<f32>) -> Self {
+        let mut data = GenericJoint::new(JointAxesMask::LOCKED_REVOLUTE_AXES);
+        data.set_local_axis1(axis)
+            .set_local_axis2(axis);
+
+        Self { data }
+    }
+
+    pub fn local_anchor1(mut self, anchor: Point3<f32>) -> Self {
+        self.data.set_local_anchor1(anchor);
+
+        self
+    }
+
+    pub fn local_anchor2(mut self, anchor: Point3<f32>) -> Self {
+        self.data.set_local_anchor2(anchor);
+
+        self
+    }
+
+    pub fn limits(mut self, limits: [f32; 2]) -> Self {
+        self.data.set_limits(JointAxis::XAngle, limits);
+
+        self
+    }
+}
+
+impl From<RevoluteJoint> for GenericJoint {
+    fn from(joint: RevoluteJoint) -> GenericJoint {
+        joint.data
+    }
+}
@@ -0,0 +1,16 @@
+mod chain;
+mod collision_matrix;
+mod gripper;
+mod inverse;
+mod reachability;
+mod tool;
+mod work_object;
+
+
+pub use self::chain::{ChainLink, KinematicChain};
+pub use self::collision_matrix::{AllowedCollisionMatrix, LinkPairStatus, SelfCollisionSampler};
+pub use self::gripper::{Gripper, GripperModel, JawGrasp, ParallelJaw, VacuumCup};
+pub use self::inverse::InverseKinematics;
+pub use self::reachability::{ReachabilityMap, ReachabilitySampler, ORIENTATION_BINS};
+pub use self::tool::{Tool, ToolTable};
+pub use self::work_object::WorkObject;
\ No newline at end of file
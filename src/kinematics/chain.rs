@@ -0,0 +1,213 @@
+use std::f32::consts::PI;
+use nalgebra::{DVector, Isometry3, Matrix6xX};
+use crate::data::random::Random;
+use crate::dynamics::JointFriction;
+use crate::joint::{GenericJoint, JointLimits};
+use crate::mesh::{ObjectHandle, ObjectSet};
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChainLink {
+    /// The joint attaching this link to the previous one (or to the chain base).
+    /// `local_frame1` is expressed in the previous link frame, `local_frame2` in this link frame.
+    pub joint: GenericJoint,
+    pub object: Option<ObjectHandle>,
+    pub friction: JointFriction,
+}
+
+/// A serial chain of links, each one driven by a single-axis joint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KinematicChain {
+    pub base: Isometry3<f32>,
+    /// The flange frame expressed in the frame of the last link.
+    pub flange: Isometry3<f32>,
+    /// The active tool center point, expressed in the flange frame.
+    pub tool: Isometry3<f32>,
+    pub links: Vec<ChainLink>,
+}
+
+impl Default for KinematicChain {
+    fn default() -> Self {
+        Self::new(Isometry3::identity())
+    }
+}
+
+impl KinematicChain {
+    pub fn new(base: Isometry3<f32>) -> Self {
+        Self {
+            base,
+            flange: Isometry3::identity(),
+            tool: Isometry3::identity(),
+            links: Vec::new(),
+        }
+    }
+
+    pub fn push_link(&mut self, joint: impl Into<GenericJoint>, object: Option<ObjectHandle>) -> &mut Self {
+        self.links.push(ChainLink {
+            joint: joint.into(),
+            object,
+            friction: JointFriction::default(),
+        });
+
+        self
+    }
+
+    /// Sets the friction of the joint of the last link pushed.
+    pub fn set_friction(&mut self, friction: JointFriction) -> &mut Self {
+        if let Some(link) = self.links.last_mut() {
+            link.friction = friction;
+        }
+
+        self
+    }
+
+    pub fn set_flange(&mut self, flange: Isometry3<f32>) -> &mut Self {
+        self.flange = flange;
+
+        self
+    }
+
+    pub fn set_tool(&mut self, tool: Isometry3<f32>) -> &mut Self {
+        self.tool = tool;
+
+        self
+    }
+
+    pub fn ndofs(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn zeros(&self) -> DVector<f32> {
+        DVector::zeros(self.ndofs())
+    }
+
+    pub fn limits(&self) -> Vec<JointLimits> {
+        self.links.iter().map(|l| l.joint.free_limits()).collect()
+    }
+
+    /// The range each joint is sampled from: its limits, with the unbounded sides over one turn.
+    pub fn sampling_bounds(&self) -> Vec<(f32, f32)> {
+        self.limits().iter().map(|limits| {
+            match (limits.min > -f32::MAX, limits.max < f32::MAX) {
+                (true, true) => (limits.min, limits.max),
+                (true, false) => (limits.min, limits.min + 2.0 * PI),
+                (false, true) => (limits.max - 2.0 * PI, limits.max),
+                (false, false) => (-PI, PI),
+            }
+        }).collect()
+    }
+
+    /// Joint positions drawn uniformly within `sampling_bounds`.
+    pub fn random_joints(&self, random: &mut Random) -> DVector<f32> {
+        DVector::from_iterator(self.ndofs(), self.sampling_bounds().into_iter().map(|(min, max)| random.range(min, max)))
+    }
+
+    pub fn clamp(&self, q: &mut DVector<f32>) {
+        for (qi, link) in q.iter_mut().zip(self.links.iter()) {
+            let limits = link.joint.free_limits();
+            *qi = qi.max(limits.min).min(limits.max);
+        }
+    }
+
+    pub fn within_limits(&self, q: &DVector<f32>) -> bool {
+        q.iter().zip(self.links.iter()).all(|(qi, link)| {
+            let limits = link.joint.free_limits();
+            *qi >= limits.min && *qi <= limits.max
+        })
+    }
+
+    /// The world-space frames of every joint, after applying the joint motion.
+    pub fn joint_frames(&self, q: &DVector<f32>) -> Vec<Isometry3<f32>> {
+        let mut frames = Vec::with_capacity(self.ndofs());
+        let mut link_pos = self.base;
+
+        for (link, qi) in self.links.iter().zip(q.iter()) {
+            let frame = link_pos * link.joint.local_frame1 * link.joint.motion(*qi);
+            link_pos = frame * link.joint.local_frame2.inverse();
+            frames.push(frame);
+        }
+
+        frames
+    }
+
+    /// The world-space positions of every link.
+    pub fn link_poses(&self, q: &DVector<f32>) -> Vec<Isometry3<f32>> {
+        self.joint_frames(q)
+            .iter()
+            .zip(self.links.iter())
+            .map(|(frame, link)| frame * link.joint.local_frame2.inverse())
+            .collect()
+    }
+
+    pub fn flange_pose(&self, q: &DVector<f32>) -> Isometry3<f32> {
+        self.link_poses(q).last().copied().unwrap_or(self.base) * self.flange
+    }
+
+    /// The world-space pose of the tool center point `tcp`, expressed wrt. the flange.
+    pub fn forward(&self, q: &DVector<f32>, tcp: &Isometry3<f32>) -> Isometry3<f32> {
+        self.flange_pose(q) * tcp
+    }
+
+    /// The world-space pose of the active tool center point.
+    pub fn end_effector(&self, q: &DVector<f32>) -> Isometry3<f32> {
+        self.forward(q, &self.tool)
+    }
+
+    /// The geometric jacobian of the tool center point: linear rows first, angular rows last.
+    pub fn jacobian(&self, q: &DVector<f32>, tcp: &Isometry3<f32>) -> Matrix6xX<f32> {
+        let frames = self.joint_frames(q);
+        let tip = self.forward(q, tcp).translation.vector;
+        let mut jacobian = Matrix6xX::zeros(self.ndofs());
+
+        for (i, (frame, link)) in frames.iter().zip(self.links.iter()).enumerate() {
+            let axis = match link.joint.free_axis() {
+                Some(axis) => axis,
+                None => continue,
+            };
+            let dir = frame.rotation * axis.direction();
+
+            if axis.is_angular() {
+                let lin = dir.cross(&(tip - frame.translation.vector));
+                jacobian.fixed_slice_mut::<3, 1>(0, i).copy_from(&lin);
+                jacobian.fixed_slice_mut::<3, 1>(3, i).copy_from(&dir);
+            } else {
+                jacobian.fixed_slice_mut::<3, 1>(0, i).copy_from(&dir);
+            }
+        }
+
+        jacobian
+    }
+
+    /// Yoshikawa's manipulability of the active tool center point, `sqrt(det(J Jᵀ))`: zero at singularities,
+    /// larger when the tool moves easily in every direction.
+    ///
+    /// Chains with less than six joints use `sqrt(det(Jᵀ J))`, `J Jᵀ` is always singular for them.
+    pub fn manipulability(&self, q: &DVector<f32>) -> f32 {
+        let jacobian = self.jacobian(q, &self.tool);
+        let determinant = if self.ndofs() < 6 {
+            (jacobian.transpose() * &jacobian).determinant()
+        } else {
+            (&jacobian * jacobian.transpose()).determinant()
+        };
+
+        determinant.max(0.0).sqrt()
+    }
+
+    /// Stops the objects of consecutive links from colliding, they always touch at the joint between them.
+    pub fn exclude_adjacent_links(&self, objects: &mut ObjectSet) {
+        let handles: Vec<_> = self.links.iter().filter_map(|link| link.object).collect();
+
+        for pair in handles.windows(2) {
+            objects.exclude_pair(pair[0], pair[1]);
+        }
+    }
+
+    /// Moves the objects attached to the links to the configuration `q`.
+    pub fn apply(&self, q: &DVector<f32>, objects: &mut ObjectSet) {
+        for (pos, link) in self.link_poses(q).into_iter().zip(self.links.iter()) {
+            if let Some(obj) = link.object.and_then(|h| objects.get_mut(h)) {
+                obj.set_position(pos);
+            }
+        }
+    }
+}
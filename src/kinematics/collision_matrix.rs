@@ -0,0 +1,185 @@
+use nalgebra::{DVector, Isometry3};
+use parry3d::query;
+use crate::data::random::Random;
+use crate::kinematics::KinematicChain;
+use crate::mesh::ObjectSet;
+
+
+/// Why a pair of links is or isn't checked for self-collisions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkPairStatus {
+    /// The links may collide, the pair has to be checked.
+    Check,
+    /// Consecutive links, touching at the joint between them.
+    Adjacent,
+    /// The links collided in nearly every sampled configuration.
+    AlwaysColliding,
+    /// The links never collided in any sampled configuration.
+    NeverColliding,
+}
+
+/// Which pairs of links of a chain are checked for self-collisions, by link index.
+///
+/// The matrix only depends on the chain geometry, so it can be generated once and saved with the model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AllowedCollisionMatrix {
+    links: usize,
+    /// The upper triangle of the matrix, row by row.
+    entries: Vec<LinkPairStatus>,
+}
+
+impl AllowedCollisionMatrix {
+    /// A matrix checking every pair of links.
+    pub fn new(links: usize) -> Self {
+        Self {
+            links,
+            entries: vec![LinkPairStatus::Check; links * links.saturating_sub(1) / 2],
+        }
+    }
+
+    pub fn links(&self) -> usize {
+        self.links
+    }
+
+    fn entry(&self, link1: usize, link2: usize) -> usize {
+        let (i, j) = if link1 < link2 { (link1, link2) } else { (link2, link1) };
+        assert!(i != j && j < self.links, "invalid link pair ({}, {})", link1, link2);
+
+        i * (2 * self.links - i - 1) / 2 + (j - i - 1)
+    }
+
+    pub fn get(&self, link1: usize, link2: usize) -> LinkPairStatus {
+        self.entries[self.entry(link1, link2)]
+    }
+
+    pub fn set(&mut self, link1: usize, link2: usize, status: LinkPairStatus) {
+        let entry = self.entry(link1, link2);
+        self.entries[entry] = status;
+    }
+
+    pub fn is_allowed(&self, link1: usize, link2: usize) -> bool {
+        self.get(link1, link2) != LinkPairStatus::Check
+    }
+
+    /// Every pair of links with its status, smaller index first.
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, usize, LinkPairStatus)> + '_ {
+        (0..self.links)
+            .flat_map(move |i| (i + 1..self.links).map(move |j| (i, j)))
+            .map(move |(i, j)| (i, j, self.get(i, j)))
+    }
+
+    /// The pairs of links that need to be checked.
+    pub fn pairs_to_check(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.pairs()
+            .filter(|(_, _, status)| *status == LinkPairStatus::Check)
+            .map(|(i, j, _)| (i, j))
+    }
+
+    /// Excludes the objects of the allowed pairs from collision detection, and includes the other ones.
+    pub fn apply(&self, chain: &KinematicChain, objects: &mut ObjectSet) {
+        for (i, j, status) in self.pairs() {
+            if let (Some(object1), Some(object2)) = (chain.links[i].object, chain.links[j].object) {
+                if status == LinkPairStatus::Check {
+                    objects.include_pair(object1, object2);
+                } else {
+                    objects.exclude_pair(object1, object2);
+                }
+            }
+        }
+    }
+
+    /// The pairs of links colliding at the joint positions `q`, among the ones that need to be checked.
+    pub fn self_collisions(&self, chain: &KinematicChain, objects: &ObjectSet, q: &DVector<f32>) -> Vec<(usize, usize)> {
+        let poses = chain.link_poses(q);
+
+        self.pairs_to_check()
+            .filter(|(i, j)| links_intersect(chain, objects, &poses, *i, *j))
+            .collect()
+    }
+}
+
+/// Generates the allowed collision matrix of a chain from random joint configurations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SelfCollisionSampler {
+    pub samples: usize,
+    /// Pairs colliding in at least this fraction of the samples are considered always colliding.
+    pub always_threshold: f32,
+    pub seed: u64,
+}
+
+impl Default for SelfCollisionSampler {
+    fn default() -> Self {
+        Self {
+            samples: 10_000,
+            always_threshold: 0.95,
+            seed: 0,
+        }
+    }
+}
+
+impl SelfCollisionSampler {
+    /// Samples joint positions within the chain limits, unbounded joints over one turn.
+    pub fn generate(&self, chain: &KinematicChain, objects: &ObjectSet) -> AllowedCollisionMatrix {
+        let links = chain.links.len();
+        let mut matrix = AllowedCollisionMatrix::new(links);
+        let mut counts = vec![0usize; matrix.entries.len()];
+        let mut random = Random::new(self.seed);
+
+        // Links without objects can't collide, the others are adjacent to the previous link with an object
+        let with_objects: Vec<_> = (0..links).filter(|i| chain.links[*i].object.is_some()).collect();
+
+        for i in 0..links {
+            for j in i + 1..links {
+                if !with_objects.contains(&i) || !with_objects.contains(&j) {
+                    matrix.set(i, j, LinkPairStatus::NeverColliding);
+                }
+            }
+        }
+
+        for pair in with_objects.windows(2) {
+            matrix.set(pair[0], pair[1], LinkPairStatus::Adjacent);
+        }
+
+        let pairs: Vec<_> = matrix.pairs_to_check().collect();
+
+        if self.samples == 0 {
+            return matrix;
+        }
+
+        for _ in 0..self.samples {
+            let q = chain.random_joints(&mut random);
+            let poses = chain.link_poses(&q);
+
+            for (i, j) in &pairs {
+                if links_intersect(chain, objects, &poses, *i, *j) {
+                    counts[matrix.entry(*i, *j)] += 1;
+                }
+            }
+        }
+
+        for (i, j) in pairs {
+            let count = counts[matrix.entry(i, j)];
+
+            if count == 0 {
+                matrix.set(i, j, LinkPairStatus::NeverColliding);
+            } else if count as f32 >= self.always_threshold * self.samples as f32 {
+                matrix.set(i, j, LinkPairStatus::AlwaysColliding);
+            }
+        }
+
+        matrix
+    }
+}
+
+fn links_intersect(chain: &KinematicChain, objects: &ObjectSet, poses: &[Isometry3<f32>], i: usize, j: usize) -> bool {
+    let object = |link: usize| chain.links[link].object.and_then(|handle| objects.get(handle));
+
+    match (object(i), object(j)) {
+        (Some(obj1), Some(obj2)) => {
+            query::intersection_test(&poses[i], obj1.shape(), &poses[j], obj2.shape()).unwrap_or(false)
+        }
+        _ => false,
+    }
+}
\ No newline at end of file
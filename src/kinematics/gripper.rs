@@ -0,0 +1,320 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use nalgebra::{Isometry3, Point3, UnitQuaternion, Vector3};
+use parry3d::query::{self, Ray, TOIStatus, TOI};
+use parry3d::shape::{Cuboid, Shape, SharedShape};
+use crate::mesh::{CollisionGroups, ObjectHandle, ObjectSet, ObjectVelocity};
+use crate::pipeline::query_pipeline::QueryPipeline;
+
+
+/// Grasps objects by attaching them to the tool holding them, keeping their world position.
+///
+/// Grasped objects move with their parent through `ObjectSet::propagate_positions`, and never collide with it.
+#[derive(Clone, Debug, Default)]
+pub struct Gripper {
+    grasped: Vec<ObjectHandle>,
+    /// The distance between the jaws of a parallel-jaw gripper closed by `close`.
+    width: Option<f32>,
+}
+
+impl Gripper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The grasped objects, in the order they were grasped.
+    pub fn grasped(&self) -> &[ObjectHandle] {
+        &self.grasped
+    }
+
+    pub fn is_grasping(&self, handle: ObjectHandle) -> bool {
+        self.grasped.contains(&handle)
+    }
+
+    /// The distance between the jaws of a closed parallel-jaw gripper, `None` while open.
+    pub fn jaw_width(&self) -> Option<f32> {
+        self.width
+    }
+
+    /// Attaches `handle` to `parent` at their current relative position.
+    ///
+    /// Returns `false` if either object doesn't exist, if the object is already attached to something, or if
+    /// `parent` is attached to it.
+    pub fn attach(&mut self, objects: &mut ObjectSet, handle: ObjectHandle, parent: ObjectHandle) -> bool {
+        let pos_wrt_parent = match (objects.get(handle), objects.get(parent)) {
+            (Some(obj), Some(parent_obj)) if obj.parent().is_none() => parent_obj.position().inv_mul(obj.position()),
+            _ => return false,
+        };
+
+        if !objects.set_parent(handle, Some((parent, pos_wrt_parent))) {
+            return false;
+        }
+
+        objects[handle].set_velocity(ObjectVelocity::default());
+        self.grasped.push(handle);
+        true
+    }
+
+    /// Detaches a grasped object where it is, dynamic objects fall from there.
+    pub fn detach(&mut self, objects: &mut ObjectSet, handle: ObjectHandle) -> bool {
+        match self.grasped.iter().position(|grasped| *grasped == handle) {
+            Some(index) => {
+                self.grasped.remove(index);
+
+                if let Some(obj) = objects.get_mut(handle) {
+                    obj.set_velocity(ObjectVelocity::default());
+                }
+
+                objects.set_parent(handle, None)
+            }
+            None => false,
+        }
+    }
+
+    /// Detaches every grasped object, returning them.
+    pub fn release(&mut self, objects: &mut ObjectSet) -> Vec<ObjectHandle> {
+        self.width = None;
+        let released = self.grasped.clone();
+
+        for handle in &released {
+            self.detach(objects, *handle);
+        }
+
+        released
+    }
+
+    /// The free objects intersecting `region` at `position`, except the ones of `ignored`.
+    ///
+    /// Objects attached to something can't be grasped. `query_pipeline` has to be up to date with the scene.
+    pub fn objects_in_region(
+        objects: &ObjectSet,
+        query_pipeline: &QueryPipeline,
+        region: &dyn Shape,
+        position: &Isometry3<f32>,
+        ignored: &[ObjectHandle],
+    ) -> Vec<ObjectHandle> {
+        query_pipeline.intersect_shape(objects, position, region, CollisionGroups::all())
+            .into_iter()
+            .filter(|handle| !ignored.contains(handle) && objects[*handle].parent().is_none())
+            .collect()
+    }
+
+    /// Attaches to `parent` every free dynamic object intersecting `region` at `position`, except the ones of
+    /// `ignored`, returning them.
+    ///
+    /// Static objects, like the ground, fixtures or conveyors, are never grasped.
+    pub fn grasp(
+        &mut self,
+        objects: &mut ObjectSet,
+        query_pipeline: &QueryPipeline,
+        parent: ObjectHandle,
+        region: (&dyn Shape, &Isometry3<f32>),
+        ignored: &[ObjectHandle],
+    ) -> Vec<ObjectHandle> {
+        Self::objects_in_region(objects, query_pipeline, region.0, region.1, ignored)
+            .into_iter()
+            .filter(|handle| *handle != parent && objects[*handle].is_dynamic() && self.attach(objects, *handle, parent))
+            .collect()
+    }
+
+    /// Actuates `model` with the flange at `flange`, attaching what it holds to `parent` and returning it.
+    pub fn close(
+        &mut self,
+        model: &GripperModel,
+        objects: &mut ObjectSet,
+        query_pipeline: &QueryPipeline,
+        parent: ObjectHandle,
+        flange: &Isometry3<f32>,
+        ignored: &[ObjectHandle],
+    ) -> Vec<ObjectHandle> {
+        let held = match model {
+            GripperModel::ParallelJaw(jaw) => {
+                let grasp = jaw.close(objects, query_pipeline, flange, ignored);
+                self.width = Some(grasp.width);
+                grasp.object
+            }
+            GripperModel::Vacuum(cup) => cup.pick(objects, query_pipeline, flange, ignored),
+        };
+
+        held.into_iter()
+            .filter(|handle| *handle != parent && self.attach(objects, *handle, parent))
+            .collect()
+    }
+}
+
+/// A gripper with two jaws closing on each other along a line, like a prismatic joint pair.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParallelJaw {
+    /// The frame centered between the jaws, expressed in the flange frame; the jaws close along its x axis.
+    pub frame: Isometry3<f32>,
+    /// The distance between the jaw pads when fully closed.
+    pub min_width: f32,
+    /// The distance between the jaw pads when fully open.
+    pub max_width: f32,
+    /// The half extents of a jaw pad.
+    pub pad: Vector3<f32>,
+}
+
+/// The result of closing a parallel-jaw gripper.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JawGrasp {
+    /// The object held between both jaws, if any.
+    pub object: Option<ObjectHandle>,
+    /// The distance between the jaw pads once closed.
+    pub width: f32,
+}
+
+impl ParallelJaw {
+    pub fn new(frame: Isometry3<f32>, min_width: f32, max_width: f32, pad: Vector3<f32>) -> Self {
+        Self { frame, min_width, max_width, pad }
+    }
+
+    /// The jaw pads `width` apart, in the flange frame.
+    pub fn shape(&self, width: f32) -> SharedShape {
+        let offset = width.clamp(self.min_width, self.max_width) / 2.0 + self.pad.x;
+        let pad = SharedShape::cuboid(self.pad.x, self.pad.y, self.pad.z);
+
+        SharedShape::compound(vec![
+            (self.frame * Isometry3::translation(-offset, 0.0, 0.0), pad.clone()),
+            (self.frame * Isometry3::translation(offset, 0.0, 0.0), pad),
+        ])
+    }
+
+    /// Closes the jaws from fully open with the flange at `flange`, each jaw stopping on the first object it touches.
+    ///
+    /// An object is held when both jaws stop on it, unless it already overlaps a jaw when open. Objects of `ignored`
+    /// are passed through.
+    pub fn close(
+        &self,
+        objects: &ObjectSet,
+        query_pipeline: &QueryPipeline,
+        flange: &Isometry3<f32>,
+        ignored: &[ObjectHandle],
+    ) -> JawGrasp {
+        let frame = flange * self.frame;
+        let stroke = (self.max_width - self.min_width).max(0.0) / 2.0;
+        let pad = Cuboid::new(self.pad);
+        let region = Cuboid::new(Vector3::new(self.max_width / 2.0 + 2.0 * self.pad.x, self.pad.y, self.pad.z));
+        let candidates = Gripper::objects_in_region(objects, query_pipeline, &region, &frame, ignored);
+
+        // Each jaw moves at unit speed, the time of impact is the distance it travels
+        let jaw = |side: f32| {
+            let position = frame * Isometry3::translation(side * (self.max_width / 2.0 + self.pad.x), 0.0, 0.0);
+            let velocity = frame.rotation * Vector3::x() * -side;
+
+            candidates.iter()
+                .filter_map(|handle| {
+                    let obj = &objects[*handle];
+                    let toi = query::time_of_impact(&position, &velocity, &pad, obj.position(), &Vector3::zeros(), obj.shape(), stroke);
+                    toi.ok()?.map(|toi| (*handle, toi))
+                })
+                .min_by(|(_, toi1), (_, toi2)| toi1.toi.total_cmp(&toi2.toi))
+        };
+        let (left, right) = (jaw(-1.0), jaw(1.0));
+        let travel = |jaw: &Option<(ObjectHandle, TOI)>| jaw.as_ref().map_or(stroke, |(_, toi)| toi.toi);
+        let width = self.max_width - travel(&left) - travel(&right);
+
+        match (left, right) {
+            (Some((object1, toi1)), Some((object2, toi2)))
+                if object1 == object2
+                    && toi1.status != TOIStatus::Penetrating
+                    && toi2.status != TOIStatus::Penetrating => JawGrasp { object: Some(object1), width },
+            _ => JawGrasp { object: None, width },
+        }
+    }
+}
+
+/// The number of points checked on the lip of a vacuum cup, besides its center.
+const LIP_SAMPLES: usize = 8;
+
+/// A suction cup, picking an object only when one of its faces covers the whole lip.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VacuumCup {
+    /// The frame centered on the lip, expressed in the flange frame; its z axis points out of the cup.
+    pub frame: Isometry3<f32>,
+    pub radius: f32,
+    /// The largest distance between the lip and the face, on either side of the lip.
+    pub tolerance: f32,
+    /// The largest angle between the cup axis and the face normal, in radians.
+    pub angle_tolerance: f32,
+}
+
+impl VacuumCup {
+    pub fn new(frame: Isometry3<f32>, radius: f32) -> Self {
+        Self { frame, radius, tolerance: 0.01, angle_tolerance: 0.1 }
+    }
+
+    pub fn with_tolerances(mut self, distance: f32, angle: f32) -> Self {
+        self.tolerance = distance;
+        self.angle_tolerance = angle;
+        self
+    }
+
+    /// The cup, a disc of `height` behind the lip, in the flange frame.
+    pub fn shape(&self, height: f32) -> SharedShape {
+        // Cylinders are aligned with the y axis
+        let position = self.frame
+            * Isometry3::translation(0.0, 0.0, -height / 2.0)
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), FRAC_PI_2);
+
+        SharedShape::compound(vec![(position, SharedShape::cylinder(height / 2.0, self.radius))])
+    }
+
+    /// The object whose face covers the lip with the flange at `flange`, except the ones of `ignored`.
+    pub fn pick(
+        &self,
+        objects: &ObjectSet,
+        query_pipeline: &QueryPipeline,
+        flange: &Isometry3<f32>,
+        ignored: &[ObjectHandle],
+    ) -> Option<ObjectHandle> {
+        let frame = flange * self.frame;
+        let region = Cuboid::new(Vector3::new(self.radius, self.radius, self.tolerance));
+        let candidates = Gripper::objects_in_region(objects, query_pipeline, &region, &frame, ignored);
+        let axis = frame.rotation * Vector3::z();
+
+        // Rays along the axis from behind the lip, at the center and around the lip
+        let origins = std::iter::once(Point3::origin()).chain((0..LIP_SAMPLES).map(|i| {
+            let angle = 2.0 * PI * i as f32 / LIP_SAMPLES as f32;
+            Point3::new(self.radius * angle.cos(), self.radius * angle.sin(), 0.0)
+        }));
+        let mut picked = None;
+
+        for origin in origins {
+            let ray = Ray::new(frame * (origin - Vector3::z() * self.tolerance), axis);
+            let (handle, hit) = candidates.iter()
+                .filter_map(|handle| {
+                    let obj = &objects[*handle];
+                    Some((*handle, obj.shape().cast_ray_and_get_normal(obj.position(), &ray, 2.0 * self.tolerance, true)?))
+                })
+                .min_by(|(_, hit1), (_, hit2)| hit1.toi.total_cmp(&hit2.toi))?;
+
+            // A ray starting inside the object hits it without a normal, the lip is buried in it
+            let facing = hit.normal.norm() > f32::EPSILON && (-hit.normal).angle(&axis) <= self.angle_tolerance;
+
+            if picked.is_some_and(|picked| picked != handle) || !facing {
+                return None;
+            }
+
+            picked = Some(handle);
+        }
+
+        picked
+    }
+}
+
+/// The mechanism of a gripper tool, deciding what it holds when it closes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GripperModel {
+    ParallelJaw(ParallelJaw),
+    Vacuum(VacuumCup),
+}
+
+impl GripperModel {
+    /// The shape of the gripper, open, in the flange frame.
+    pub fn shape(&self) -> SharedShape {
+        match self {
+            GripperModel::ParallelJaw(jaw) => jaw.shape(jaw.max_width),
+            GripperModel::Vacuum(cup) => cup.shape(cup.radius),
+        }
+    }
+}
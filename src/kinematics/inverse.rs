@@ -0,0 +1,86 @@
+use nalgebra::{DVector, Isometry3, Matrix6, Vector6};
+use crate::errors::KinematicsError;
+use crate::kinematics::KinematicChain;
+
+
+/// Damped least-squares inverse kinematics solver.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InverseKinematics {
+    pub max_iterations: usize,
+    /// Maximum residual (in length units for the position and radians for the orientation).
+    pub tolerance: f32,
+    pub damping: f32,
+    /// The largest joint displacement applied by a single iteration.
+    pub max_step: f32,
+}
+
+impl Default for InverseKinematics {
+    fn default() -> Self {
+        Self {
+            max_iterations: 200,
+            tolerance: 1.0e-4,
+            damping: 0.05,
+            max_step: 0.3,
+        }
+    }
+}
+
+impl InverseKinematics {
+    /// The pose error between the current and the target pose: translation first, then rotation.
+    pub fn pose_error(current: &Isometry3<f32>, target: &Isometry3<f32>) -> Vector6<f32> {
+        let lin = target.translation.vector - current.translation.vector;
+        let ang = (target.rotation * current.rotation.inverse()).scaled_axis();
+
+        Vector6::new(lin.x, lin.y, lin.z, ang.x, ang.y, ang.z)
+    }
+
+    /// Finds the joint positions placing `tcp` at `target`, starting the search from `seed`.
+    pub fn solve(
+        &self,
+        chain: &KinematicChain,
+        target: &Isometry3<f32>,
+        tcp: &Isometry3<f32>,
+        seed: &DVector<f32>,
+    ) -> Result<DVector<f32>, KinematicsError> {
+        if seed.len() != chain.ndofs() {
+            return Err(KinematicsError::DofMismatch {
+                expected: chain.ndofs(),
+                found: seed.len(),
+            });
+        }
+
+        let mut q = seed.clone();
+        chain.clamp(&mut q);
+
+        let damping = Matrix6::identity() * self.damping * self.damping;
+        let mut residual = f32::MAX;
+
+        for _ in 0..self.max_iterations {
+            let error = Self::pose_error(&chain.forward(&q, tcp), target);
+            residual = error.fixed_rows::<3>(0).norm().max(error.fixed_rows::<3>(3).norm());
+
+            if residual < self.tolerance {
+                return Ok(q);
+            }
+
+            let jacobian = chain.jacobian(&q, tcp);
+            let jjt = &jacobian * jacobian.transpose() + damping;
+            let solved = match jjt.cholesky() {
+                Some(chol) => chol.solve(&error),
+                None => break,
+            };
+
+            let mut dq = jacobian.transpose() * solved;
+            let largest = dq.amax();
+
+            if largest > self.max_step {
+                dq *= self.max_step / largest;
+            }
+
+            q += dq;
+            chain.clamp(&mut q);
+        }
+
+        Err(KinematicsError::Unreachable { residual })
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use nalgebra::{DVector, Isometry3, Point3, UnitVector3, Vector3};
+use crate::data::random::Random;
+use crate::kinematics::KinematicChain;
+
+
+/// How many approach directions the orientations are grouped into, in every voxel.
+pub const ORIENTATION_BINS: usize = 64;
+
+/// The positions reachable by the tool center point of a chain, on a voxel grid, with the approach directions
+/// reached in every voxel.
+///
+/// The map is stored relative to the base of the chain, so it can be moved with `set_base` to evaluate
+/// another base location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReachabilityMap {
+    pub base: Isometry3<f32>,
+    voxel_size: f32,
+    /// The tool axis whose direction is recorded.
+    approach: UnitVector3<f32>,
+    directions: Vec<Vector3<f32>>,
+    /// The reached direction bins of every non-empty voxel, one bit per bin.
+    voxels: HashMap<[i32; 3], u64>,
+}
+
+impl ReachabilityMap {
+    pub fn new(base: Isometry3<f32>, voxel_size: f32, approach: UnitVector3<f32>) -> Self {
+        Self {
+            base,
+            voxel_size,
+            approach,
+            directions: fibonacci_sphere(ORIENTATION_BINS),
+            voxels: HashMap::new(),
+        }
+    }
+
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    pub fn approach(&self) -> UnitVector3<f32> {
+        self.approach
+    }
+
+    /// The number of reachable voxels.
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    pub fn set_base(&mut self, base: Isometry3<f32>) {
+        self.base = base;
+    }
+
+    fn key(&self, point_wrt_base: &Point3<f32>) -> [i32; 3] {
+        let cell = point_wrt_base.coords / self.voxel_size;
+        [cell.x.floor() as i32, cell.y.floor() as i32, cell.z.floor() as i32]
+    }
+
+    /// The bin of the nearest sampled direction.
+    fn bin(&self, direction: &Vector3<f32>) -> usize {
+        self.directions.iter()
+            .map(|bin| bin.dot(direction))
+            .enumerate()
+            .max_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    /// Records a pose of the tool center point, relative to the base.
+    pub fn insert(&mut self, pose_wrt_base: &Isometry3<f32>) {
+        let key = self.key(&Point3::from(pose_wrt_base.translation.vector));
+        let bin = self.bin(&(pose_wrt_base.rotation * self.approach));
+
+        *self.voxels.entry(key).or_insert(0) |= 1 << bin;
+    }
+
+    /// The number of approach directions reached in the voxel containing `point`, in world space.
+    pub fn orientations(&self, point: &Point3<f32>) -> usize {
+        let key = self.key(&(self.base.inverse() * point));
+        self.voxels.get(&key).map_or(0, |bins| bins.count_ones() as usize)
+    }
+
+    /// The fraction of the approach directions reached at `point`, in world space.
+    pub fn reachability(&self, point: &Point3<f32>) -> f32 {
+        self.orientations(point) as f32 / ORIENTATION_BINS as f32
+    }
+
+    pub fn is_position_reachable(&self, point: &Point3<f32>) -> bool {
+        self.orientations(point) > 0
+    }
+
+    /// Whether a sample reached the voxel of `pose`, in world space, with the same approach direction.
+    ///
+    /// This is a quick estimate from the sampled poses; inverse kinematics gives the exact answer.
+    pub fn is_reachable(&self, pose: &Isometry3<f32>) -> bool {
+        let pose = self.base.inverse() * pose;
+        let key = self.key(&Point3::from(pose.translation.vector));
+        let bin = self.bin(&(pose.rotation * self.approach));
+
+        self.voxels.get(&key).is_some_and(|bins| bins & (1 << bin) != 0)
+    }
+
+    /// Checks every target with `is_reachable`.
+    pub fn check_targets(&self, targets: &[Isometry3<f32>]) -> Vec<bool> {
+        targets.iter().map(|target| self.is_reachable(target)).collect()
+    }
+
+    /// The center of every reachable voxel, in world space, with the number of approach directions reached in it.
+    pub fn voxels(&self) -> impl Iterator<Item = (Point3<f32>, usize)> + '_ {
+        self.voxels.iter().map(|(key, bins)| {
+            let center = Point3::new(key[0] as f32 + 0.5, key[1] as f32 + 0.5, key[2] as f32 + 0.5) * self.voxel_size;
+            (self.base * center, bins.count_ones() as usize)
+        })
+    }
+}
+
+/// Builds reachability maps from random joint positions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReachabilitySampler {
+    pub samples: usize,
+    /// The edge length of the voxels, in length units.
+    pub voxel_size: f32,
+    /// The tool axis whose direction is recorded, the z axis of the tool frame by default.
+    pub approach: UnitVector3<f32>,
+    pub seed: u64,
+}
+
+impl Default for ReachabilitySampler {
+    fn default() -> Self {
+        Self {
+            samples: 100_000,
+            voxel_size: 0.1,
+            approach: Vector3::z_axis(),
+            seed: 0,
+        }
+    }
+}
+
+impl ReachabilitySampler {
+    /// Samples joint positions within the chain limits, unbounded joints over one turn, for the active tool.
+    pub fn generate(&self, chain: &KinematicChain) -> ReachabilityMap {
+        self.generate_filtered(chain, |_| true)
+    }
+
+    /// Like `generate`, only keeping the joint positions accepted by `is_valid`, e.g. the ones without collisions.
+    pub fn generate_filtered(&self, chain: &KinematicChain, is_valid: impl Fn(&DVector<f32>) -> bool) -> ReachabilityMap {
+        let mut map = ReachabilityMap::new(chain.base, self.voxel_size, self.approach);
+        let mut random = Random::new(self.seed);
+        let base_inv = chain.base.inverse();
+
+        for _ in 0..self.samples {
+            let q = chain.random_joints(&mut random);
+
+            if is_valid(&q) {
+                map.insert(&(base_inv * chain.end_effector(&q)));
+            }
+        }
+
+        map
+    }
+}
+
+/// `count` directions spread evenly over the unit sphere.
+fn fibonacci_sphere(count: usize) -> Vec<Vector3<f32>> {
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+
+    (0..count)
+        .map(|i| {
+            let z = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+            let radius = (1.0 - z * z).sqrt();
+            let theta = golden_angle * i as f32;
+
+            Vector3::new(radius * theta.cos(), radius * theta.sin(), z)
+        })
+        .collect()
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use nalgebra::{Isometry3, Point3};
+use parry3d::shape::SharedShape;
+use crate::kinematics::GripperModel;
+
+
+/// An end-of-arm tool mounted on the robot flange.
+#[derive(Clone)]
+pub struct Tool {
+    pub name: String,
+    /// The tool center point, expressed in the flange frame.
+    pub tcp: Isometry3<f32>,
+    /// The tool geometry, expressed in the flange frame.
+    pub shape: Option<SharedShape>,
+    /// The region objects have to touch to be grasped, expressed in the flange frame; the tool shape without it.
+    pub grip_region: Option<SharedShape>,
+    /// The mechanism deciding what the tool holds when it grips, the grip region is used without it.
+    pub gripper: Option<GripperModel>,
+    pub mass: f32,
+    /// The center of gravity, expressed in the flange frame.
+    pub center_of_mass: Point3<f32>,
+}
+
+impl Tool {
+    pub fn new(name: impl Into<String>, tcp: Isometry3<f32>) -> Self {
+        Self {
+            name: name.into(),
+            tcp,
+            shape: None,
+            grip_region: None,
+            gripper: None,
+            mass: 0.0,
+            center_of_mass: Point3::origin(),
+        }
+    }
+
+    pub fn shape(mut self, shape: SharedShape) -> Self {
+        self.shape = Some(shape);
+
+        self
+    }
+
+    pub fn grip_region(mut self, region: SharedShape) -> Self {
+        self.grip_region = Some(region);
+
+        self
+    }
+
+    /// Makes the tool a gripper of the given model, shaped like it unless it already has a shape.
+    pub fn gripper(mut self, model: GripperModel) -> Self {
+        self.shape = self.shape.or_else(|| Some(model.shape()));
+        self.gripper = Some(model);
+
+        self
+    }
+
+    pub fn mass(mut self, mass: f32, center_of_mass: Point3<f32>) -> Self {
+        self.mass = mass;
+        self.center_of_mass = center_of_mass;
+
+        self
+    }
+}
+
+/// The tools of a robot, numbered like the `TOOL n` program command.
+///
+/// Tool 0 is the bare flange unless it is defined explicitly.
+#[derive(Clone, Default)]
+pub struct ToolTable {
+    tools: BTreeMap<usize, Tool>,
+}
+
+impl ToolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the tool `index`, returning the previous one.
+    pub fn insert(&mut self, index: usize, tool: Tool) -> Option<Tool> {
+        self.tools.insert(index, tool)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Tool> {
+        self.tools.remove(&index)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Tool> {
+        self.tools.get(&index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Tool> {
+        self.tools.get_mut(&index)
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        index == 0 || self.tools.contains_key(&index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Tool)> {
+        self.tools.iter().map(|(index, tool)| (*index, tool))
+    }
+
+    /// The tool center point of the tool `index`, in the flange frame.
+    pub fn tcp(&self, index: usize) -> Option<Isometry3<f32>> {
+        match self.tools.get(&index) {
+            Some(tool) => Some(tool.tcp),
+            None => (index == 0).then(Isometry3::identity),
+        }
+    }
+
+    /// The tool center points of every defined tool, as used by post-processors.
+    pub fn tcp_frames(&self) -> BTreeMap<usize, Isometry3<f32>> {
+        self.tools.iter().map(|(index, tool)| (*index, tool.tcp)).collect()
+    }
+
+    /// Sets the tool center points of `frames`, adding a bare tool for each index not defined yet.
+    pub fn set_tcp_frames(&mut self, frames: &BTreeMap<usize, Isometry3<f32>>) {
+        for (index, tcp) in frames {
+            self.tools.entry(*index)
+                .and_modify(|tool| tool.tcp = *tcp)
+                .or_insert_with(|| Tool::new(format!("Tool {}", index), *tcp));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use nalgebra::Isometry3;
+use crate::mesh::{ObjectHandle, ObjectSet};
+
+
+/// A named user frame that motion targets can be expressed in.
+///
+/// A work object attached to an object, such as a fixture, follows it when it moves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkObject {
+    pub name: String,
+    pub object: Option<ObjectHandle>,
+    /// The user frame, expressed in the frame of `object` or in world space.
+    pub frame: Isometry3<f32>,
+}
+
+impl WorkObject {
+    /// A work object fixed in world space.
+    pub fn new(name: impl Into<String>, frame: Isometry3<f32>) -> Self {
+        Self {
+            name: name.into(),
+            object: None,
+            frame,
+        }
+    }
+
+    /// A work object moving along with `object`.
+    pub fn attached(name: impl Into<String>, object: ObjectHandle, frame: Isometry3<f32>) -> Self {
+        Self {
+            name: name.into(),
+            object: Some(object),
+            frame,
+        }
+    }
+
+    /// The world-space user frame, or `None` if the object it is attached to doesn't exist.
+    pub fn world_frame(&self, objects: &ObjectSet) -> Option<Isometry3<f32>> {
+        match self.object {
+            Some(handle) => objects.get(handle).map(|obj| obj.position() * self.frame),
+            None => Some(self.frame),
+        }
+    }
+}
@@ -2,9 +2,16 @@ extern crate core;
 
 pub mod mesh;
 pub mod data;
-mod errors;
+pub mod errors;
 pub mod pipeline;
 pub mod joint;
+pub mod kinematics;
+pub mod dynamics;
+pub mod program;
+pub mod frames;
+pub mod pose;
+pub mod planning;
+pub mod signals;
 
 pub const DOF: usize = 6;
 
@@ -5,4 +5,5 @@ mod object_parameters;
 
 pub use self::object::{ObjectBuilder, SceneObject};
 pub use self::object_set::ObjectSet;
-pub use self::object_parameters::ObjectHandle;
\ No newline at end of file
+pub use self::object_parameters::{CollisionGroups, ObjectHandle, ObjectVelocity};
+pub use parry3d::mass_properties::MassProperties;
\ No newline at end of file
@@ -1,9 +1,10 @@
-use nalgebra::{Isometry3, Vector3};
+use nalgebra::{Isometry3, UnitQuaternion, Vector3};
+use parry3d::mass_properties::MassProperties;
 use parry3d::math::{AngVector, Rotation};
 use parry3d::shape::{Shape, SharedShape};
 use crate::data::space::Index;
 use crate::mesh::ObjectHandle;
-use super::object_parameters::{ObjectParent, ObjectPosition, ObjectShape, ObjectFlags, ObjectChanges};
+use super::object_parameters::{CollisionGroups, ObjectParent, ObjectPosition, ObjectShape, ObjectFlags, ObjectChanges, ObjectVelocity};
 
 
 #[derive(Clone)]
@@ -13,6 +14,9 @@ pub struct SceneObject {
     pub(crate) position: ObjectPosition,
     pub(crate) changes: ObjectChanges,
     pub(crate) flags: ObjectFlags,
+    pub(crate) collision_groups: CollisionGroups,
+    pub(crate) mass_properties: MassProperties,
+    pub(crate) velocity: ObjectVelocity,
     pub user_data: u128,
 }
 
@@ -20,6 +24,12 @@ pub struct SceneObject {
 pub struct ObjectBuilder {
     pub shape: SharedShape,
     pub position: Isometry3<f32>,
+    pub collision_groups: CollisionGroups,
+    /// Used to compute the mass properties from the shape, unless they are given explicitly.
+    pub density: f32,
+    pub mass_properties: Option<MassProperties>,
+    /// Whether the physics pipeline moves the object under gravity and contacts.
+    pub dynamic: bool,
     pub user_data: u128,
 }
 
@@ -28,7 +38,11 @@ impl ObjectBuilder {
         ObjectBuilder {
             shape,
             user_data: 0,
-            position: Default::default()
+            position: Default::default(),
+            collision_groups: CollisionGroups::all(),
+            density: 1.0,
+            mass_properties: None,
+            dynamic: false,
         }
     }
 
@@ -38,9 +52,11 @@ impl ObjectBuilder {
 
     pub fn build(&self) -> SceneObject {
         let shape = self.shape.clone();
-        let flags = ObjectFlags {};
+        let mut flags = ObjectFlags::empty();
+        flags.set(ObjectFlags::DYNAMIC, self.dynamic);
         let position = ObjectPosition(self.position);
         let changes = ObjectChanges::empty();
+        let mass_properties = self.mass_properties.unwrap_or_else(|| self.shape.mass_properties(self.density));
 
         SceneObject {
             shape,
@@ -48,6 +64,9 @@ impl ObjectBuilder {
             position,
             changes,
             flags,
+            collision_groups: self.collision_groups,
+            mass_properties,
+            velocity: ObjectVelocity::default(),
             user_data: self.user_data,
         }
     }
@@ -58,11 +77,42 @@ impl ObjectBuilder {
         self
     }
 
+    pub fn orientation(mut self, orientation: UnitQuaternion<f32>) -> Self {
+        self.position.rotation = orientation;
+
+        self
+    }
+
     pub fn position(mut self, pos: Isometry3<f32>) -> Self {
         self.position = pos;
 
         self
     }
+
+    pub fn collision_groups(mut self, groups: CollisionGroups) -> Self {
+        self.collision_groups = groups;
+
+        self
+    }
+
+    pub fn density(mut self, density: f32) -> Self {
+        self.density = density;
+
+        self
+    }
+
+    /// Overrides the mass properties computed from the shape, expressed in the object frame.
+    pub fn mass_properties(mut self, mass_properties: MassProperties) -> Self {
+        self.mass_properties = Some(mass_properties);
+
+        self
+    }
+
+    pub fn dynamic(mut self, dynamic: bool) -> Self {
+        self.dynamic = dynamic;
+
+        self
+    }
 }
 
 impl SceneObject {
@@ -71,6 +121,73 @@ impl SceneObject {
         self.position.0.rotation = Rotation::new(rotation);
     }
 
+    /// Sets the rotation, see `crate::pose` to build it from Euler angles or vendor conventions.
+    pub fn set_orientation(&mut self, orientation: UnitQuaternion<f32>) {
+        self.changes.insert(ObjectChanges::POSITION);
+        self.position.0.rotation = orientation;
+    }
+
+    pub fn set_position(&mut self, position: Isometry3<f32>) {
+        self.changes.insert(ObjectChanges::POSITION);
+        self.position.0 = position;
+    }
+
+    /// Disabled objects stay in the set but are not drawn.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.is_enabled() != enabled {
+            self.changes.insert(ObjectChanges::MODIFIED);
+            self.flags.set(ObjectFlags::DISABLED, !enabled);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.flags.contains(ObjectFlags::DISABLED)
+    }
+
+    /// Dynamic objects without a parent are moved by the physics pipeline, the other ones by their owner.
+    pub fn is_dynamic(&self) -> bool {
+        self.flags.contains(ObjectFlags::DYNAMIC)
+    }
+
+    pub fn set_dynamic(&mut self, dynamic: bool) {
+        self.flags.set(ObjectFlags::DYNAMIC, dynamic);
+
+        if !dynamic {
+            self.velocity = ObjectVelocity::default();
+        }
+    }
+
+    /// The velocity of a dynamic object, zero for the other ones.
+    pub fn velocity(&self) -> &ObjectVelocity {
+        &self.velocity
+    }
+
+    pub fn set_velocity(&mut self, velocity: ObjectVelocity) {
+        self.velocity = velocity;
+    }
+
+    pub fn set_collision_groups(&mut self, groups: CollisionGroups) {
+        self.collision_groups = groups;
+    }
+
+    pub fn collision_groups(&self) -> CollisionGroups {
+        self.collision_groups
+    }
+
+    /// The mass, center of mass and angular inertia, expressed in the object frame.
+    pub fn mass_properties(&self) -> &MassProperties {
+        &self.mass_properties
+    }
+
+    pub fn set_mass_properties(&mut self, mass_properties: MassProperties) {
+        self.mass_properties = mass_properties;
+    }
+
+    /// Recomputes the mass properties from the shape, with a uniform density.
+    pub fn set_density(&mut self, density: f32) {
+        self.mass_properties = self.shape.mass_properties(density);
+    }
+
     pub fn position(&self) -> &Isometry3<f32> {
         &self.position
     }
@@ -86,6 +203,11 @@ impl SceneObject {
     pub fn parent(&self) -> Option<ObjectHandle> {
         self.parent.map(|p| p.handle)
     }
+
+    /// The position of this object in the frame of its parent, if it has one.
+    pub fn pos_wrt_parent(&self) -> Option<&Isometry3<f32>> {
+        self.parent.as_ref().map(|p| &p.pos_wrt_parent)
+    }
 }
 
 impl Into<SceneObject> for ObjectBuilder {
@@ -1,5 +1,6 @@
 use std::ops::{Deref, DerefMut};
-use nalgebra::Isometry3;
+use nalgebra::{Isometry3, Vector3};
+use parry3d::partitioning::IndexedData;
 use parry3d::shape::SharedShape;
 
 
@@ -16,10 +17,15 @@ pub struct ObjectParent {
 
 pub type ObjectShape = SharedShape;
 
-#[derive(Clone)]
-pub struct ObjectFlags {}
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+/// Collision filtering: two objects can collide if each one is a member of a group the other one's filter accepts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CollisionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct ObjectHandle(pub crate::data::space::Index);
 
 impl ObjectHandle {
@@ -31,6 +37,52 @@ impl ObjectHandle {
     }
 }
 
+impl IndexedData for ObjectHandle {
+    fn default() -> Self {
+        Self::invalid()
+    }
+
+    fn index(&self) -> usize {
+        self.0.index()
+    }
+}
+
+impl CollisionGroups {
+    pub const fn new(memberships: u32, filter: u32) -> Self {
+        Self { memberships, filter }
+    }
+
+    /// Member of every group, collides with every group.
+    pub const fn all() -> Self {
+        Self::new(u32::MAX, u32::MAX)
+    }
+
+    /// Collides with nothing.
+    pub const fn none() -> Self {
+        Self::new(0, 0)
+    }
+
+    pub const fn with_memberships(mut self, memberships: u32) -> Self {
+        self.memberships = memberships;
+        self
+    }
+
+    pub const fn with_filter(mut self, filter: u32) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub const fn test(self, other: Self) -> bool {
+        (self.memberships & other.filter) != 0 && (other.memberships & self.filter) != 0
+    }
+}
+
+impl Default for CollisionGroups {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
 impl ObjectPosition {
     /// The identity position.
     #[must_use]
@@ -39,6 +91,21 @@ impl ObjectPosition {
     }
 }
 
+/// The velocity of a dynamic object: the linear velocity of its center of mass and its angular velocity.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct ObjectVelocity {
+    pub linvel: Vector3<f32>,
+    pub angvel: Vector3<f32>,
+}
+
+bitflags::bitflags! {
+    pub struct ObjectFlags: u32 {
+        const DISABLED = 1 << 0;
+        /// Moved by the physics pipeline instead of by its owner.
+        const DYNAMIC = 1 << 1;
+    }
+}
+
 bitflags::bitflags! {
     pub struct ObjectChanges: u32 {
         const MODIFIED = 1 << 0;
@@ -1,15 +1,20 @@
+use std::collections::HashSet;
 use std::ops::{Index, IndexMut};
 use std::ptr::hash;
 
+use nalgebra::Isometry3;
 use crate::mesh::object::SceneObject;
-use crate::mesh::object_parameters::ObjectHandle;
+use crate::mesh::object_parameters::{ObjectChanges, ObjectHandle, ObjectParent};
 use crate::data::space::Space;
 
 
+#[derive(Clone)]
 pub struct ObjectSet {
     pub objects: Space<SceneObject>,
     pub removed_objects: Vec<ObjectHandle>,
     pub changed_objects: Vec<ObjectHandle>,
+    /// Pairs of objects that never collide, smaller handle first.
+    excluded_pairs: HashSet<(ObjectHandle, ObjectHandle)>,
 }
 
 impl ObjectSet {
@@ -18,6 +23,7 @@ impl ObjectSet {
             objects: Space::new(),
             removed_objects: Vec::new(),
             changed_objects: Vec::new(),
+            excluded_pairs: HashSet::new(),
         }
     }
 
@@ -33,6 +39,129 @@ impl ObjectSet {
         handle
     }
 
+    /// Inserts an object rigidly attached to `parent`, placed at `pos_wrt_parent` in the parent frame.
+    pub fn insert_with_parent(
+        &mut self,
+        obj: impl Into<SceneObject>,
+        parent: ObjectHandle,
+        pos_wrt_parent: Isometry3<f32>,
+    ) -> ObjectHandle {
+        let handle = self.insert(obj);
+        self.set_parent(handle, Some((parent, pos_wrt_parent)));
+
+        handle
+    }
+
+    /// Removes an object along with the objects attached to it, returning it.
+    pub fn remove(&mut self, handle: ObjectHandle) -> Option<SceneObject> {
+        let children: Vec<_> = self.children(handle).collect();
+
+        for child in children {
+            self.remove(child);
+        }
+
+        let obj = self.objects.remove(handle.0)?;
+        self.excluded_pairs.retain(|(handle1, handle2)| *handle1 != handle && *handle2 != handle);
+        self.removed_objects.push(handle);
+
+        Some(obj)
+    }
+
+    /// Attaches `handle` to another object, or detaches it with `None`.
+    /// Returns `false` if either object doesn't exist or the attachment would create a cycle.
+    pub fn set_parent(&mut self, handle: ObjectHandle, parent: Option<(ObjectHandle, Isometry3<f32>)>) -> bool {
+        if let Some((parent, _)) = parent {
+            let mut ancestor = Some(parent);
+
+            while let Some(current) = ancestor {
+                if current == handle || self.get(current).is_none() {
+                    return false;
+                }
+
+                ancestor = self[current].parent();
+            }
+        }
+
+        let position = parent.map(|(parent, pos_wrt_parent)| self.world_position(parent) * pos_wrt_parent);
+
+        match self.get_mut(handle) {
+            Some(obj) => {
+                obj.parent = parent.map(|(handle, pos_wrt_parent)| ObjectParent { handle, pos_wrt_parent });
+                obj.changes.insert(ObjectChanges::PARENT);
+
+                if let Some(position) = position {
+                    obj.set_position(position);
+                }
+            }
+            None => return false,
+        }
+
+        self.changed_objects.push(handle);
+        true
+    }
+
+    /// The objects directly attached to `handle`.
+    pub fn children(&self, handle: ObjectHandle) -> impl Iterator<Item = ObjectHandle> + '_ {
+        self.iter()
+            .filter(move |(_, obj)| obj.parent() == Some(handle))
+            .map(|(child, _)| child)
+    }
+
+    /// The position of `handle` implied by its chain of parents.
+    pub fn world_position(&self, handle: ObjectHandle) -> Isometry3<f32> {
+        let obj = &self[handle];
+
+        match &obj.parent {
+            Some(parent) => self.world_position(parent.handle) * parent.pos_wrt_parent,
+            None => *obj.position(),
+        }
+    }
+
+    /// Moves every attached object along with its parent.
+    pub fn propagate_positions(&mut self) {
+        let attached: Vec<_> = self.iter()
+            .filter(|(_, obj)| obj.parent.is_some())
+            .map(|(handle, _)| handle)
+            .collect();
+
+        for handle in attached {
+            let position = self.world_position(handle);
+
+            if *self[handle].position() != position {
+                self[handle].set_position(position);
+            }
+        }
+    }
+
+    /// Prevents two objects from colliding with each other, regardless of their collision groups.
+    pub fn exclude_pair(&mut self, handle1: ObjectHandle, handle2: ObjectHandle) {
+        self.excluded_pairs.insert(sorted_pair(handle1, handle2));
+    }
+
+    pub fn include_pair(&mut self, handle1: ObjectHandle, handle2: ObjectHandle) {
+        self.excluded_pairs.remove(&sorted_pair(handle1, handle2));
+    }
+
+    pub fn is_pair_excluded(&self, handle1: ObjectHandle, handle2: ObjectHandle) -> bool {
+        self.excluded_pairs.contains(&sorted_pair(handle1, handle2))
+    }
+
+    /// Whether two objects are allowed to collide: both are enabled, their collision groups match,
+    /// the pair isn't excluded and neither object is attached to the other.
+    pub fn can_collide(&self, handle1: ObjectHandle, handle2: ObjectHandle) -> bool {
+        let (obj1, obj2) = match (self.get(handle1), self.get(handle2)) {
+            (Some(obj1), Some(obj2)) if handle1 != handle2 => (obj1, obj2),
+            _ => return false,
+        };
+
+        obj1.is_enabled()
+            && obj2.is_enabled()
+            && obj1.collision_groups().test(obj2.collision_groups())
+            && obj1.parent() != Some(handle2)
+            && obj2.parent() != Some(handle1)
+            && !self.is_pair_excluded(handle1, handle2)
+    }
+
     pub fn get(&self, handle: ObjectHandle) -> Option<&SceneObject> {
         self.objects.get(handle.0)
     }
@@ -63,5 +192,6 @@ impl IndexMut<ObjectHandle> for ObjectSet {
     }
 }
 
-
-
+fn sorted_pair(handle1: ObjectHandle, handle2: ObjectHandle) -> (ObjectHandle, ObjectHandle) {
+    if handle1 < handle2 { (handle1, handle2) } else { (handle2, handle1) }
+}
\ No newline at end of file
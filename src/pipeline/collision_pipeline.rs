@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use nalgebra::{Point3, Vector3};
+use parry3d::query::{ContactManifold, ContactManifoldsWorkspace, DefaultQueryDispatcher, PersistentQueryDispatcher};
+use crate::mesh::{ObjectHandle, ObjectSet};
+use crate::pipeline::query_pipeline::QueryPipeline;
+
+
+/// A world-space contact point between two objects.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Contact {
+    pub point1: Point3<f32>,
+    pub point2: Point3<f32>,
+    /// The contact normal, pointing from the first object to the second one.
+    pub normal: Vector3<f32>,
+    /// How deep the objects overlap at this point, negative when they are apart.
+    pub depth: f32,
+}
+
+/// The contacts between two objects whose bounding boxes overlap.
+#[derive(Clone)]
+pub struct ContactPair {
+    pub object1: ObjectHandle,
+    pub object2: ObjectHandle,
+    /// The contact manifolds, in the local frames of the objects.
+    pub manifolds: Vec<ContactManifold<(), ()>>,
+    workspace: Option<ContactManifoldsWorkspace>,
+}
+
+impl ContactPair {
+    fn new(object1: ObjectHandle, object2: ObjectHandle) -> Self {
+        Self {
+            object1,
+            object2,
+            manifolds: Vec::new(),
+            workspace: None,
+        }
+    }
+
+    /// The deepest penetration between the objects, if they overlap.
+    pub fn penetration_depth(&self) -> Option<f32> {
+        self.manifolds.iter()
+            .flat_map(|manifold| manifold.points.iter())
+            .filter(|point| point.dist < 0.0)
+            .map(|point| -point.dist)
+            .reduce(f32::max)
+    }
+
+    pub fn is_intersecting(&self) -> bool {
+        self.penetration_depth().is_some()
+    }
+
+    /// The contact points expressed in world space.
+    pub fn contacts(&self, objects: &ObjectSet) -> Vec<Contact> {
+        let (pos1, pos2) = match (objects.get(self.object1), objects.get(self.object2)) {
+            (Some(obj1), Some(obj2)) => (obj1.position(), obj2.position()),
+            _ => return Vec::new(),
+        };
+        let mut contacts = Vec::new();
+
+        for manifold in &self.manifolds {
+            let sub1 = manifold.subshape_pos1.map_or(*pos1, |sub| pos1 * sub);
+            let sub2 = manifold.subshape_pos2.map_or(*pos2, |sub| pos2 * sub);
+            let normal = sub1 * manifold.local_n1;
+
+            contacts.extend(manifold.points.iter().map(|point| Contact {
+                point1: sub1 * point.local_p1,
+                point2: sub2 * point.local_p2,
+                normal,
+                depth: -point.dist,
+            }));
+        }
+
+        contacts
+    }
+}
+
+/// The collision narrow phase: computes contact manifolds between the pairs found by the query pipeline.
+///
+/// Only pairs allowed by `ObjectSet::can_collide` are checked.
+pub struct CollisionPipeline {
+    pairs: HashMap<(ObjectHandle, ObjectHandle), ContactPair>,
+    /// Contacts between objects closer than this distance are reported before they touch.
+    pub prediction: f32,
+}
+
+impl Default for CollisionPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CollisionPipeline {
+    pub fn new() -> Self {
+        Self {
+            pairs: HashMap::new(),
+            prediction: 0.0,
+        }
+    }
+
+    /// Updates the contacts of every pair of objects whose bounding boxes overlap.
+    pub fn step(&mut self, objects: &ObjectSet, query_pipeline: &mut QueryPipeline) {
+        // The pipeline is shared, its own margin is kept so that the other queries see what they asked for
+        query_pipeline.update_with_margin(objects, query_pipeline.margin.max(self.prediction));
+
+        let mut pairs = HashMap::new();
+
+        for (handle1, handle2) in query_pipeline.overlapping_pairs(objects) {
+            let (obj1, obj2) = (&objects[handle1], &objects[handle2]);
+
+            // Reusing the previous manifolds and workspace lets parry exploit temporal coherence
+            let mut pair = self.pairs.remove(&(handle1, handle2))
+                .unwrap_or_else(|| ContactPair::new(handle1, handle2));
+            let pos12 = obj1.position().inv_mul(obj2.position());
+            let result = DefaultQueryDispatcher.contact_manifolds(
+                &pos12,
+                obj1.shape(),
+                obj2.shape(),
+                self.prediction,
+                &mut pair.manifolds,
+                &mut pair.workspace,
+            );
+
+            // Shape pairs parry can't handle are treated as never touching
+            if result.is_err() {
+                continue;
+            }
+
+            pair.manifolds.retain(|manifold| !manifold.points.is_empty());
+
+            if !pair.manifolds.is_empty() {
+                pairs.insert((handle1, handle2), pair);
+            }
+        }
+
+        self.pairs = pairs;
+    }
+
+    /// The pairs of objects with at least one contact point.
+    pub fn contact_pairs(&self) -> impl Iterator<Item = &ContactPair> {
+        self.pairs.values()
+    }
+
+    pub fn contact_pair(&self, handle1: ObjectHandle, handle2: ObjectHandle) -> Option<&ContactPair> {
+        let key = if handle1 < handle2 { (handle1, handle2) } else { (handle2, handle1) };
+        self.pairs.get(&key)
+    }
+
+    /// The pairs of objects that penetrate each other.
+    pub fn intersecting_pairs(&self) -> impl Iterator<Item = &ContactPair> {
+        self.pairs.values().filter(|pair| pair.is_intersecting())
+    }
+
+    /// Whether `handle` penetrates any other object.
+    pub fn is_colliding(&self, handle: ObjectHandle) -> bool {
+        self.intersecting_pairs().any(|pair| pair.object1 == handle || pair.object2 == handle)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,110 @@
+use nalgebra::{Isometry3, Point3, Vector3};
+use parry3d::bounding_volume::{BoundingVolume, AABB};
+use parry3d::query::{self, NonlinearRigidMotion, TOIStatus};
+use parry3d::shape::Shape;
+use crate::mesh::{ObjectHandle, ObjectSet};
+use crate::pipeline::query_pipeline::QueryPipeline;
+
+
+/// An object moving from `start` to `end` over one step of a motion.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweptObject {
+    pub object: ObjectHandle,
+    pub start: Isometry3<f32>,
+    pub end: Isometry3<f32>,
+}
+
+/// The first contact between a moving object and another object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Impact {
+    /// The fraction of the step at which the objects touch, between 0 and 1.
+    pub time: f32,
+    /// The moving object.
+    pub object1: ObjectHandle,
+    pub object2: ObjectHandle,
+    /// The contact points in world space, undefined if the objects already penetrate at the start of the step.
+    pub point1: Point3<f32>,
+    pub point2: Point3<f32>,
+    /// Whether the objects already penetrated each other at the start of the step.
+    pub penetrating: bool,
+}
+
+impl SweptObject {
+    pub fn new(object: ObjectHandle, start: Isometry3<f32>, end: Isometry3<f32>) -> Self {
+        Self { object, start, end }
+    }
+
+    /// The motion interpolating between both poses, rotating at a constant rate around the center of the shape.
+    fn motion(&self, shape: &dyn Shape) -> NonlinearRigidMotion {
+        let center = *shape.compute_local_bounding_sphere().center();
+        let linvel = self.end * center - self.start * center;
+        let angvel = (self.end.rotation * self.start.rotation.inverse()).scaled_axis();
+
+        NonlinearRigidMotion::new(self.start, center, linvel, angvel)
+    }
+
+    /// Bounds the whole motion: every point of the shape stays within its bounding sphere, which moves linearly.
+    fn swept_aabb(&self, shape: &dyn Shape) -> AABB {
+        let sphere = shape.compute_local_bounding_sphere();
+        let half_extents = Vector3::repeat(sphere.radius());
+
+        AABB::from_half_extents(self.start * sphere.center(), half_extents)
+            .merged(&AABB::from_half_extents(self.end * sphere.center(), half_extents))
+    }
+}
+
+/// Finds the earliest contact of the `swept` objects, with each other or with the objects that stay in place,
+/// between the start and the end of their motion.
+///
+/// `query_pipeline` has to be up to date with the positions of the objects that stay in place. Only pairs allowed
+/// by `ObjectSet::can_collide` are checked, and pairs that already touch at the start but move apart are ignored.
+pub fn first_impact(objects: &ObjectSet, query_pipeline: &QueryPipeline, swept: &[SweptObject]) -> Option<Impact> {
+    let mut first: Option<Impact> = None;
+    let is_swept = |handle: ObjectHandle| swept.iter().any(|s| s.object == handle);
+    let mut check = |swept1: &SweptObject, motion2: NonlinearRigidMotion, handle2: ObjectHandle| {
+        let (shape1, shape2) = (objects[swept1.object].shape(), objects[handle2].shape());
+        let end = first.map_or(1.0, |impact| impact.time);
+        let toi = query::nonlinear_time_of_impact(&swept1.motion(shape1), shape1, &motion2, shape2, 0.0, end, false);
+
+        if let Ok(Some(toi)) = toi {
+            let (pos1, pos2) = (swept1.motion(shape1).position_at_time(toi.toi), motion2.position_at_time(toi.toi));
+
+            first = Some(Impact {
+                time: toi.toi,
+                object1: swept1.object,
+                object2: handle2,
+                point1: pos1 * toi.witness1,
+                point2: pos2 * toi.witness2,
+                penetrating: toi.status == TOIStatus::Penetrating,
+            });
+        }
+    };
+
+    for (i, swept1) in swept.iter().enumerate() {
+        let shape1 = match objects.get(swept1.object) {
+            Some(obj) => obj.shape(),
+            None => continue,
+        };
+        let aabb1 = swept1.swept_aabb(shape1);
+
+        for handle2 in query_pipeline.intersect_aabb(&aabb1) {
+            if !is_swept(handle2) && objects.can_collide(swept1.object, handle2) {
+                check(swept1, NonlinearRigidMotion::constant_position(*objects[handle2].position()), handle2);
+            }
+        }
+
+        for swept2 in &swept[i + 1..] {
+            if !objects.can_collide(swept1.object, swept2.object) {
+                continue;
+            }
+
+            let shape2 = objects[swept2.object].shape();
+
+            if aabb1.intersects(&swept2.swept_aabb(shape2)) {
+                check(swept1, swept2.motion(shape2), swept2.object);
+            }
+        }
+    }
+
+    first
+}
\ No newline at end of file
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use nalgebra::Point3;
+use parry3d::query::{self, ClosestPoints};
+use crate::mesh::{ObjectHandle, ObjectSet};
+
+
+/// The clearances below which an obstacle is reported.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SafetyMargin {
+    pub warning: f32,
+    pub stop: f32,
+}
+
+impl SafetyMargin {
+    pub fn new(warning: f32, stop: f32) -> Self {
+        Self { warning, stop }
+    }
+
+    pub fn status(&self, distance: f32) -> SafetyStatus {
+        if distance <= self.stop {
+            SafetyStatus::Stop
+        } else if distance <= self.warning {
+            SafetyStatus::Warning
+        } else {
+            SafetyStatus::Clear
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SafetyStatus {
+    Clear,
+    Warning,
+    Stop,
+}
+
+/// The closest points between a monitored object and an obstacle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Clearance {
+    pub monitored: ObjectHandle,
+    pub obstacle: ObjectHandle,
+    /// The distance between the objects, negative when they penetrate each other.
+    pub distance: f32,
+    /// The closest point on the monitored object, in world space.
+    pub point1: Point3<f32>,
+    /// The closest point on the obstacle, in world space.
+    pub point2: Point3<f32>,
+    pub status: SafetyStatus,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Obstacle {
+    margin: SafetyMargin,
+    clearance: Option<Clearance>,
+    /// The smallest clearance recorded, with its timestep.
+    minimum: Option<(Clearance, usize)>,
+}
+
+/// Computes the clearance between the monitored objects, typically the robot links and tools, and a set of obstacles.
+#[derive(Clone, Debug, Default)]
+pub struct DistanceMonitor {
+    monitored: Vec<ObjectHandle>,
+    obstacles: BTreeMap<ObjectHandle, Obstacle>,
+}
+
+impl DistanceMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn monitored(&self) -> &[ObjectHandle] {
+        &self.monitored
+    }
+
+    pub fn set_monitored(&mut self, monitored: Vec<ObjectHandle>) {
+        self.monitored = monitored;
+    }
+
+    /// Watches the distance to `obstacle`, replacing its margin if it was already watched.
+    pub fn add_obstacle(&mut self, obstacle: ObjectHandle, margin: SafetyMargin) {
+        self.obstacles.entry(obstacle)
+            .and_modify(|obstacle| obstacle.margin = margin)
+            .or_insert(Obstacle { margin, clearance: None, minimum: None });
+    }
+
+    pub fn remove_obstacle(&mut self, obstacle: ObjectHandle) {
+        self.obstacles.remove(&obstacle);
+    }
+
+    pub fn margin(&self, obstacle: ObjectHandle) -> Option<SafetyMargin> {
+        self.obstacles.get(&obstacle).map(|obstacle| obstacle.margin)
+    }
+
+    /// Computes the clearance to every obstacle at the current object positions. Disabled objects are ignored.
+    pub fn step(&mut self, objects: &ObjectSet, timestep: usize) {
+        let monitored: Vec<_> = self.monitored.iter()
+            .filter_map(|handle| Some((*handle, objects.get(*handle).filter(|obj| obj.is_enabled())?)))
+            .collect();
+
+        for (handle, obstacle) in &mut self.obstacles {
+            obstacle.clearance = None;
+
+            let obj2 = match objects.get(*handle) {
+                Some(obj) if obj.is_enabled() => obj,
+                _ => continue,
+            };
+
+            for (monitored, obj1) in &monitored {
+                let (pos1, pos2) = (obj1.position(), obj2.position());
+                let closest = match query::closest_points(pos1, obj1.shape(), pos2, obj2.shape(), f32::MAX) {
+                    Ok(ClosestPoints::WithinMargin(point1, point2)) => {
+                        Some((nalgebra::distance(&point1, &point2), point1, point2))
+                    }
+                    // Closest points are undefined for penetrating shapes, the contact gives the penetration depth
+                    Ok(ClosestPoints::Intersecting) => query::contact(pos1, obj1.shape(), pos2, obj2.shape(), 0.0)
+                        .ok()
+                        .flatten()
+                        .map(|contact| (contact.dist, contact.point1, contact.point2)),
+                    _ => None,
+                };
+
+                if let Some((distance, point1, point2)) = closest {
+                    if obstacle.clearance.is_none_or(|clearance| distance < clearance.distance) {
+                        obstacle.clearance = Some(Clearance {
+                            monitored: *monitored,
+                            obstacle: *handle,
+                            distance,
+                            point1,
+                            point2,
+                            status: obstacle.margin.status(distance),
+                        });
+                    }
+                }
+            }
+
+            if let Some(clearance) = obstacle.clearance {
+                if obstacle.minimum.is_none_or(|(minimum, _)| clearance.distance < minimum.distance) {
+                    obstacle.minimum = Some((clearance, timestep));
+                }
+            }
+        }
+    }
+
+    /// The current clearance to `obstacle`, if it was computed.
+    pub fn clearance(&self, obstacle: ObjectHandle) -> Option<&Clearance> {
+        self.obstacles.get(&obstacle)?.clearance.as_ref()
+    }
+
+    pub fn clearances(&self) -> impl Iterator<Item = &Clearance> {
+        self.obstacles.values().filter_map(|obstacle| obstacle.clearance.as_ref())
+    }
+
+    /// The smallest clearance to `obstacle` since the last reset, and the timestep it was recorded at.
+    pub fn minimum(&self, obstacle: ObjectHandle) -> Option<(&Clearance, usize)> {
+        self.obstacles.get(&obstacle)?.minimum.as_ref().map(|(clearance, timestep)| (clearance, *timestep))
+    }
+
+    pub fn reset_minimums(&mut self) {
+        for obstacle in self.obstacles.values_mut() {
+            obstacle.minimum = None;
+        }
+    }
+
+    /// The most severe status over all obstacles.
+    pub fn status(&self) -> SafetyStatus {
+        self.clearances()
+            .map(|clearance| clearance.status)
+            .max()
+            .unwrap_or(SafetyStatus::Clear)
+    }
+}
\ No newline at end of file
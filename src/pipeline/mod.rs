@@ -1 +1,5 @@
-pub mod query_pipeline;
\ No newline at end of file
+pub mod query_pipeline;
+pub mod collision_pipeline;
+pub mod distance_monitor;
+pub mod continuous_collision;
+pub mod physics_pipeline;
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use nalgebra::{Isometry3, Matrix3, Point3, Translation3, UnitQuaternion, Vector3};
+use crate::mesh::{ObjectHandle, ObjectSet, ObjectVelocity};
+use crate::pipeline::collision_pipeline::CollisionPipeline;
+use crate::pipeline::query_pipeline::QueryPipeline;
+
+
+/// The state of an object during a solver substep.
+struct Body {
+    /// The reference point of the velocity: the center of mass of dynamic objects, the origin of the other ones.
+    center: Point3<f32>,
+    linvel: Vector3<f32>,
+    angvel: Vector3<f32>,
+    inv_mass: f32,
+    /// The inverse angular inertia in world space.
+    inv_inertia: Matrix3<f32>,
+    simulated: bool,
+}
+
+impl Body {
+    fn velocity_at(&self, point: &Point3<f32>) -> Vector3<f32> {
+        self.linvel + self.angvel.cross(&(point - self.center))
+    }
+
+    fn apply_impulse(&mut self, impulse: &Vector3<f32>, point: &Point3<f32>) {
+        if self.simulated {
+            self.linvel += impulse * self.inv_mass;
+            self.angvel += self.inv_inertia * (point - self.center).cross(impulse);
+        }
+    }
+
+    /// The inverse of the mass seen by an impulse along `direction` at `point`.
+    fn inv_effective_mass(&self, direction: &Vector3<f32>, point: &Point3<f32>) -> f32 {
+        if !self.simulated {
+            return 0.0;
+        }
+
+        let arm = (point - self.center).cross(direction);
+        self.inv_mass + arm.dot(&(self.inv_inertia * arm))
+    }
+}
+
+/// A contact point between two bodies, with its accumulated impulses.
+struct ContactConstraint {
+    body1: usize,
+    body2: usize,
+    point: Point3<f32>,
+    normal: Vector3<f32>,
+    tangents: [Vector3<f32>; 2],
+    /// The normal velocity the contact has to reach: separating when the objects overlap, approaching at most
+    /// by the gap when they are apart.
+    target: f32,
+    normal_impulse: f32,
+    tangent_impulses: [f32; 2],
+}
+
+/// Rigid-body simulation of the dynamic objects: gravity, contacts and friction, solved with sequential impulses.
+///
+/// Dynamic objects without a parent are simulated. The other objects are kinematic: they push the simulated ones
+/// with the velocity estimated from their motion between steps, without being affected.
+pub struct PhysicsPipeline {
+    pub gravity: Vector3<f32>,
+    /// The number of solver steps in every call to `step`.
+    pub substeps: usize,
+    /// The number of velocity iterations of every substep.
+    pub iterations: usize,
+    /// The Coulomb friction coefficient of every contact.
+    pub friction: f32,
+    /// The fraction of the normal velocity restored after an impact.
+    pub restitution: f32,
+    /// The fraction of the velocities lost per second.
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    /// The penetration tolerated without correction, keeping resting contacts stable.
+    pub allowed_penetration: f32,
+    /// The fraction of the penetration corrected per substep.
+    pub correction: f32,
+    collisions: CollisionPipeline,
+    /// The positions of the kinematic objects at the previous step, to estimate their velocities.
+    previous: HashMap<ObjectHandle, Isometry3<f32>>,
+}
+
+impl Default for PhysicsPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsPipeline {
+    pub fn new() -> Self {
+        let mut collisions = CollisionPipeline::new();
+        collisions.prediction = 0.05;
+
+        Self {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            substeps: 4,
+            iterations: 10,
+            friction: 0.5,
+            restitution: 0.0,
+            linear_damping: 0.01,
+            angular_damping: 0.05,
+            allowed_penetration: 0.005,
+            correction: 0.2,
+            collisions,
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Contacts between objects closer than this distance are solved before they touch.
+    pub fn prediction(&self) -> f32 {
+        self.collisions.prediction
+    }
+
+    pub fn set_prediction(&mut self, prediction: f32) {
+        self.collisions.prediction = prediction;
+    }
+
+    /// Forgets the motion of the kinematic objects, e.g. after they are teleported.
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    /// Advances the simulated objects by `dt`, the kinematic objects being already at their new positions.
+    pub fn step(&mut self, objects: &mut ObjectSet, query_pipeline: &mut QueryPipeline, dt: f32) {
+        let substeps = self.substeps.max(1);
+        let h = dt / substeps as f32;
+
+        if dt <= 0.0 {
+            return;
+        }
+
+        let is_simulated = |objects: &ObjectSet, handle: ObjectHandle| {
+            let obj = &objects[handle];
+            obj.is_dynamic() && obj.is_enabled() && obj.parent().is_none()
+        };
+
+        let kinematic_velocities: HashMap<_, _> = objects.iter()
+            .filter_map(|(handle, obj)| {
+                let previous = self.previous.get(&handle)?;
+                let position = obj.position();
+                let linvel = (position.translation.vector - previous.translation.vector) / dt;
+                let angvel = (position.rotation * previous.rotation.inverse()).scaled_axis() / dt;
+                Some((handle, ObjectVelocity { linvel, angvel }))
+            })
+            .collect();
+
+        if objects.iter().any(|(handle, _)| is_simulated(objects, handle)) {
+            for _ in 0..substeps {
+                self.collisions.step(objects, query_pipeline);
+                self.substep(objects, &kinematic_velocities, &is_simulated, h);
+            }
+        }
+
+        self.previous = objects.iter()
+            .filter(|(handle, _)| !is_simulated(objects, *handle))
+            .map(|(handle, obj)| (handle, *obj.position()))
+            .collect();
+    }
+
+    fn substep(
+        &self,
+        objects: &mut ObjectSet,
+        kinematic_velocities: &HashMap<ObjectHandle, ObjectVelocity>,
+        is_simulated: &dyn Fn(&ObjectSet, ObjectHandle) -> bool,
+        h: f32,
+    ) {
+        let mut bodies = Vec::new();
+        let mut indices = HashMap::new();
+        let mut body_index = |objects: &ObjectSet, bodies: &mut Vec<Body>, handle: ObjectHandle| {
+            *indices.entry(handle).or_insert_with(|| {
+                let obj = &objects[handle];
+                let position = obj.position();
+
+                bodies.push(if is_simulated(objects, handle) {
+                    let properties = obj.mass_properties();
+                    let rotation = position.rotation.to_rotation_matrix();
+                    let velocity = obj.velocity();
+
+                    Body {
+                        center: position * properties.local_com,
+                        linvel: velocity.linvel + self.gravity * h,
+                        angvel: velocity.angvel,
+                        inv_mass: properties.inv_mass,
+                        inv_inertia: rotation.matrix()
+                            * properties.reconstruct_inverse_inertia_matrix()
+                            * rotation.matrix().transpose(),
+                        simulated: true,
+                    }
+                } else {
+                    let velocity = kinematic_velocities.get(&handle).copied().unwrap_or_default();
+
+                    Body {
+                        center: Point3::from(position.translation.vector),
+                        linvel: velocity.linvel,
+                        angvel: velocity.angvel,
+                        inv_mass: 0.0,
+                        inv_inertia: Matrix3::zeros(),
+                        simulated: false,
+                    }
+                });
+
+                bodies.len() - 1
+            })
+        };
+
+        // Every simulated object falls, even without contacts
+        let simulated: Vec<_> = objects.iter()
+            .map(|(handle, _)| handle)
+            .filter(|handle| is_simulated(objects, *handle))
+            .collect();
+
+        for handle in &simulated {
+            body_index(objects, &mut bodies, *handle);
+        }
+
+        let mut constraints = Vec::new();
+
+        for pair in self.collisions.contact_pairs() {
+            if !is_simulated(objects, pair.object1) && !is_simulated(objects, pair.object2) {
+                continue;
+            }
+
+            let body1 = body_index(objects, &mut bodies, pair.object1);
+            let body2 = body_index(objects, &mut bodies, pair.object2);
+
+            for contact in pair.contacts(objects) {
+                let normal = contact.normal;
+                let point = Point3::from((contact.point1.coords + contact.point2.coords) / 2.0);
+                let tangent = normal.cross(&Vector3::x()).try_normalize(1.0e-3)
+                    .unwrap_or_else(|| normal.cross(&Vector3::y()).normalize());
+
+                // Impacts bounce back, resting contacts only get the penetration corrected. Objects meeting during
+                // the substep bounce already, closing the gap would stop them before they ever overlap
+                let approach = (bodies[body1].velocity_at(&point) - bodies[body2].velocity_at(&point)).dot(&normal);
+                let bounce = if approach > 1.0 { self.restitution * approach } else { 0.0 };
+                let target = if contact.depth > 0.0 {
+                    bounce.max(self.correction * (contact.depth - self.allowed_penetration).max(0.0) / h)
+                } else if bounce > 0.0 && approach * h > -contact.depth {
+                    bounce
+                } else {
+                    contact.depth / h
+                };
+
+                constraints.push(ContactConstraint {
+                    body1,
+                    body2,
+                    point,
+                    normal,
+                    tangents: [tangent, normal.cross(&tangent)],
+                    target,
+                    normal_impulse: 0.0,
+                    tangent_impulses: [0.0; 2],
+                });
+            }
+        }
+
+        for _ in 0..self.iterations {
+            for constraint in &mut constraints {
+                self.solve(&mut bodies, constraint);
+            }
+        }
+
+        let damping = (
+            1.0 / (1.0 + h * self.linear_damping),
+            1.0 / (1.0 + h * self.angular_damping),
+        );
+
+        for handle in simulated {
+            let body = &bodies[indices[&handle]];
+            let obj = &mut objects[handle];
+            let velocity = ObjectVelocity { linvel: body.linvel * damping.0, angvel: body.angvel * damping.1 };
+
+            let rotation = UnitQuaternion::from_scaled_axis(velocity.angvel * h) * obj.position().rotation;
+            let center = body.center + velocity.linvel * h;
+            let translation = center - rotation * obj.mass_properties().local_com;
+
+            obj.set_position(Isometry3::from_parts(Translation3::from(translation), rotation));
+            obj.set_velocity(velocity);
+        }
+    }
+
+    /// Applies the impulses bringing the relative velocity at a contact to its target, the normal one pushing only
+    /// and the tangent ones bounded by the friction cone.
+    fn solve(&self, bodies: &mut [Body], constraint: &mut ContactConstraint) {
+        let (body1, body2) = (constraint.body1, constraint.body2);
+        let point = constraint.point;
+        let relative_velocity = |bodies: &[Body]| bodies[body2].velocity_at(&point) - bodies[body1].velocity_at(&point);
+        let inv_effective_mass = |bodies: &[Body], direction: &Vector3<f32>| {
+            bodies[body1].inv_effective_mass(direction, &point) + bodies[body2].inv_effective_mass(direction, &point)
+        };
+        let apply = |bodies: &mut [Body], impulse: Vector3<f32>| {
+            bodies[body1].apply_impulse(&-impulse, &point);
+            bodies[body2].apply_impulse(&impulse, &point);
+        };
+
+        let normal = constraint.normal;
+        let k = inv_effective_mass(bodies, &normal);
+
+        if k <= f32::EPSILON {
+            return;
+        }
+
+        let error = constraint.target - relative_velocity(bodies).dot(&normal);
+        let total = (constraint.normal_impulse + error / k).max(0.0);
+        apply(bodies, normal * (total - constraint.normal_impulse));
+        constraint.normal_impulse = total;
+
+        let limit = self.friction * constraint.normal_impulse;
+
+        for (tangent, accumulated) in constraint.tangents.iter().zip(&mut constraint.tangent_impulses) {
+            let k = inv_effective_mass(bodies, tangent);
+
+            if k <= f32::EPSILON {
+                continue;
+            }
+
+            let total = (*accumulated - relative_velocity(bodies).dot(tangent) / k).clamp(-limit, limit);
+            apply(bodies, tangent * (total - *accumulated));
+            *accumulated = total;
+        }
+    }
+}
@@ -1,4 +1,94 @@
+use parry3d::bounding_volume::{BoundingVolume, AABB};
+use nalgebra::Isometry3;
+use parry3d::partitioning::QBVH;
+use parry3d::query;
+use parry3d::shape::Shape;
+use crate::mesh::{CollisionGroups, ObjectHandle, ObjectSet};
+
+
+/// A bounding volume hierarchy over the enabled scene objects, used as the collision broad phase.
 #[derive(Clone)]
 pub struct QueryPipeline {
+    qbvh: QBVH<ObjectHandle>,
+    /// How much the object AABBs are enlarged, so that nearly touching objects are reported too.
+    pub margin: f32,
+}
+
+impl Default for QueryPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryPipeline {
+    pub fn new() -> Self {
+        Self {
+            qbvh: QBVH::new(),
+            margin: 0.0,
+        }
+    }
+
+    /// Rebuilds the hierarchy from the current object positions.
+    pub fn update(&mut self, objects: &ObjectSet) {
+        self.update_with_margin(objects, self.margin);
+    }
+
+    /// Rebuilds the hierarchy with the AABBs enlarged by `margin` instead of `self.margin`, which is left unchanged.
+    pub fn update_with_margin(&mut self, objects: &ObjectSet, margin: f32) {
+        let aabbs: Vec<_> = objects.iter()
+            .filter(|(_, obj)| obj.is_enabled())
+            .map(|(handle, obj)| (handle, obj.shape().compute_aabb(obj.position()).loosened(margin)))
+            .collect();
+
+        self.qbvh.clear_and_rebuild(aabbs.into_iter(), 0.0);
+    }
+
+    /// The objects whose AABB intersects `aabb`.
+    pub fn intersect_aabb(&self, aabb: &AABB) -> Vec<ObjectHandle> {
+        let mut result = Vec::new();
+        self.qbvh.intersect_aabb(aabb, &mut result);
+
+        result
+    }
+
+    /// The objects that `shape` placed at `position` intersects, among the ones its collision groups accept.
+    pub fn intersect_shape(
+        &self,
+        objects: &ObjectSet,
+        position: &Isometry3<f32>,
+        shape: &dyn Shape,
+        groups: CollisionGroups,
+    ) -> Vec<ObjectHandle> {
+        let aabb = shape.compute_aabb(position);
+
+        self.intersect_aabb(&aabb).into_iter()
+            .filter(|handle| match objects.get(*handle) {
+                Some(obj) if obj.is_enabled() && groups.test(obj.collision_groups()) => {
+                    query::intersection_test(position, shape, obj.position(), obj.shape()).unwrap_or(false)
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// The pairs of objects whose AABBs overlap and that can collide, each pair once with the smaller handle first.
+    pub fn overlapping_pairs(&self, objects: &ObjectSet) -> Vec<(ObjectHandle, ObjectHandle)> {
+        let mut pairs = Vec::new();
+
+        // Objects removed from the set since the last update leave stale leaves in the tree, disabled ones aren't in it
+        for (node, handle) in self.qbvh.iter_data() {
+            let aabb = match self.qbvh.node_aabb(node) {
+                Some(aabb) if objects.get(*handle).is_some() => aabb,
+                _ => continue,
+            };
+
+            pairs.extend(
+                self.intersect_aabb(&aabb).into_iter()
+                    .filter(|other| handle < other && objects.can_collide(*handle, *other))
+                    .map(|other| (*handle, other))
+            );
+        }
 
+        pairs
+    }
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+mod base_placement;
+mod checker;
+mod constraint;
+mod prm;
+mod rrt;
+mod smoothing;
+
+
+pub use self::base_placement::{BasePlacement, BasePlacementSearch};
+pub use self::checker::{SceneChecker, StateChecker};
+pub use self::constraint::{ConstrainedChecker, ConstraintProjector, TaskConstraint};
+pub use self::prm::Prm;
+pub use self::rrt::RrtConnect;
+pub use self::smoothing::{path_length, ptp_commands, PathSmoother};
@@ -0,0 +1,231 @@
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+use nalgebra::{DVector, Isometry3, Point3, Translation3, UnitQuaternion, UnitVector3, Vector3};
+use crate::data::random::Random;
+use crate::kinematics::{InverseKinematics, KinematicChain, ReachabilityMap};
+use crate::mesh::ObjectSet;
+use crate::pipeline::query_pipeline::QueryPipeline;
+use crate::planning::{SceneChecker, StateChecker};
+
+
+/// A base location evaluated against a set of targets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasePlacement {
+    pub base: Isometry3<f32>,
+    /// The joint positions reaching every target without collision, if any were found.
+    pub solutions: Vec<Option<DVector<f32>>>,
+    /// The mean manipulability over the reached targets.
+    pub manipulability: f32,
+}
+
+impl BasePlacement {
+    pub fn reached(&self) -> usize {
+        self.solutions.iter().filter(|q| q.is_some()).count()
+    }
+
+    /// Ranks placements by reached targets first, then by manipulability.
+    pub fn compare(&self, other: &BasePlacement) -> Ordering {
+        self.reached().cmp(&other.reached())
+            .then(self.manipulability.total_cmp(&other.manipulability))
+    }
+}
+
+/// The inputs shared by the evaluation of every base location.
+struct Problem<'a> {
+    chain: &'a KinematicChain,
+    objects: &'a ObjectSet,
+    query_pipeline: &'a QueryPipeline,
+    targets: &'a [Isometry3<f32>],
+    random: Random,
+}
+
+/// Searches the base location of a chain reaching the most targets, on a grid of positions and rotations around
+/// the `up` axis, then refines the best ones locally.
+///
+/// The reachability map ranks the grid cheaply; only the best `candidates` are solved with inverse kinematics and
+/// checked for collisions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BasePlacementSearch {
+    /// The corners of the region the base origin is searched in, in world space.
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+    /// The number of grid positions along each axis.
+    pub steps: [usize; 3],
+    /// The number of rotations around `up` tried at every grid position.
+    pub rotations: usize,
+    pub up: UnitVector3<f32>,
+    pub candidates: usize,
+    /// The number of local improvement steps applied to every candidate.
+    pub refinement_iterations: usize,
+    pub ik: InverseKinematics,
+    /// The number of random starting points tried by inverse kinematics, after the current solution.
+    pub restarts: usize,
+    pub seed: u64,
+}
+
+impl BasePlacementSearch {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self {
+            min,
+            max,
+            steps: [10, 1, 10],
+            rotations: 8,
+            up: Vector3::y_axis(),
+            candidates: 5,
+            refinement_iterations: 20,
+            ik: InverseKinematics::default(),
+            restarts: 4,
+            seed: 0,
+        }
+    }
+
+    /// The base at `position`, rotated by `angle` around `up` from the base of `chain`.
+    fn base(&self, chain: &KinematicChain, position: &Point3<f32>, angle: f32) -> Isometry3<f32> {
+        Isometry3::from_parts(
+            Translation3::from(position.coords),
+            UnitQuaternion::from_axis_angle(&self.up, angle) * chain.base.rotation,
+        )
+    }
+
+    fn grid_position(&self, i: [usize; 3]) -> Point3<f32> {
+        let coordinate = |axis: usize| match self.steps[axis] {
+            0 | 1 => (self.min[axis] + self.max[axis]) / 2.0,
+            steps => self.min[axis] + (self.max[axis] - self.min[axis]) * i[axis] as f32 / (steps - 1) as f32,
+        };
+
+        Point3::new(coordinate(0), coordinate(1), coordinate(2))
+    }
+
+    /// Finds the best base for `chain` to reach `targets`, avoiding the objects of the scene.
+    ///
+    /// `map` has to be generated for `chain` and its active tool. `query_pipeline` has to be up to date with
+    /// the scene; the link objects are checked at the solved joint positions, whatever their current position.
+    pub fn search(
+        &self,
+        chain: &KinematicChain,
+        map: &ReachabilityMap,
+        objects: &ObjectSet,
+        query_pipeline: &QueryPipeline,
+        targets: &[Isometry3<f32>],
+    ) -> Option<BasePlacement> {
+        let mut map = map.clone();
+        let mut grid = Vec::new();
+
+        for i in 0..self.steps[0].max(1) {
+            for j in 0..self.steps[1].max(1) {
+                for k in 0..self.steps[2].max(1) {
+                    let position = self.grid_position([i, j, k]);
+
+                    for r in 0..self.rotations.max(1) {
+                        let angle = 2.0 * PI * r as f32 / self.rotations.max(1) as f32;
+                        map.set_base(self.base(chain, &position, angle));
+
+                        let score = targets.iter()
+                            .map(|target| {
+                                if map.is_reachable(target) {
+                                    2
+                                } else {
+                                    map.is_position_reachable(&Point3::from(target.translation.vector)) as usize
+                                }
+                            })
+                            .sum::<usize>();
+                        grid.push((score, position, angle));
+                    }
+                }
+            }
+        }
+
+        grid.sort_by(|(s1, ..), (s2, ..)| s2.cmp(s1));
+
+        let mut problem = Problem { chain, objects, query_pipeline, targets, random: Random::new(self.seed) };
+        let spacing = (self.max - self.min).component_div(&Vector3::from_iterator(
+            self.steps.iter().map(|steps| steps.max(&2).saturating_sub(1) as f32),
+        ));
+        let angle_spacing = 2.0 * PI / self.rotations.max(1) as f32;
+
+        grid.into_iter()
+            .take(self.candidates)
+            .map(|(_, position, angle)| self.refine(&mut problem, position, angle, spacing / 2.0, angle_spacing / 2.0))
+            .max_by(|p1, p2| p1.compare(p2))
+    }
+
+    /// Pattern search around a candidate: moves along each axis while it improves, halving the steps otherwise.
+    fn refine(
+        &self,
+        problem: &mut Problem,
+        mut position: Point3<f32>,
+        mut angle: f32,
+        mut step: Vector3<f32>,
+        mut angle_step: f32,
+    ) -> BasePlacement {
+        let mut best = self.evaluate(problem, self.base(problem.chain, &position, angle), None);
+
+        for _ in 0..self.refinement_iterations {
+            let mut moves: Vec<_> = (0..3)
+                .filter(|axis| step[*axis] > 0.0)
+                .flat_map(|axis| [-1.0, 1.0].map(|sign| (Vector3::ith(axis, sign * step[axis]), 0.0)))
+                .collect();
+            moves.extend([(Vector3::zeros(), -angle_step), (Vector3::zeros(), angle_step)]);
+
+            let mut improved = false;
+
+            for (offset, rotation) in moves {
+                let moved = (position + offset).coords.sup(&self.min.coords).inf(&self.max.coords).into();
+                let base = self.base(problem.chain, &moved, angle + rotation);
+                let placement = self.evaluate(problem, base, Some(&best));
+
+                if placement.compare(&best) == Ordering::Greater {
+                    best = placement;
+                    position = moved;
+                    angle += rotation;
+                    improved = true;
+                }
+            }
+
+            if !improved {
+                step /= 2.0;
+                angle_step /= 2.0;
+            }
+        }
+
+        best
+    }
+
+    /// Solves every target from `base`, starting from the solutions of `previous` when there are some.
+    fn evaluate(&self, problem: &mut Problem, base: Isometry3<f32>, previous: Option<&BasePlacement>) -> BasePlacement {
+        let mut chain = problem.chain.clone();
+        chain.base = base;
+
+        let checker = SceneChecker::new(&chain, problem.objects, problem.query_pipeline);
+        let bounds = checker.bounds();
+        let random = &mut problem.random;
+        let mut manipulability = 0.0;
+
+        let solutions: Vec<_> = problem.targets.iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let previous = previous.and_then(|placement| placement.solutions[i].clone());
+                let seeds = previous.into_iter()
+                    .chain(std::iter::once(chain.zeros()))
+                    .chain((0..self.restarts).map(|_| {
+                        DVector::from_iterator(bounds.len(), bounds.iter().map(|(min, max)| random.range(*min, *max)))
+                    }));
+
+                let q = seeds
+                    .filter_map(|seed| self.ik.solve(&chain, target, &chain.tool, &seed).ok())
+                    .find(|q| checker.is_valid(q))?;
+
+                manipulability += chain.manipulability(&q);
+                Some(q)
+            })
+            .collect();
+
+        let reached = solutions.iter().filter(|q| q.is_some()).count();
+
+        BasePlacement {
+            base,
+            solutions,
+            manipulability: if reached > 0 { manipulability / reached as f32 } else { 0.0 },
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use nalgebra::{DVector, Isometry3};
+use parry3d::query;
+use crate::kinematics::KinematicChain;
+use crate::mesh::{ObjectHandle, ObjectSet};
+use crate::pipeline::query_pipeline::QueryPipeline;
+
+
+/// Decides which joint configurations a planner may go through.
+pub trait StateChecker {
+    fn ndofs(&self) -> usize;
+
+    /// The range each joint is sampled in.
+    fn bounds(&self) -> Vec<(f32, f32)>;
+
+    fn is_valid(&self, q: &DVector<f32>) -> bool;
+
+    /// The largest joint displacement between two configurations checked along a motion.
+    fn resolution(&self) -> f32 {
+        0.05
+    }
+
+    /// Moves a sampled configuration onto the constraints of the checker, if it has any.
+    ///
+    /// Returns `None` when no configuration nearby satisfies them.
+    fn project(&self, q: DVector<f32>) -> Option<DVector<f32>> {
+        Some(q)
+    }
+
+    /// Checks the straight joint motion from `q0`, assumed valid, to `q1`.
+    fn is_motion_valid(&self, q0: &DVector<f32>, q1: &DVector<f32>) -> bool {
+        let steps = ((q1 - q0).amax() / self.resolution()).ceil().max(1.0) as usize;
+        (1..=steps).all(|i| self.is_valid(&q0.lerp(q1, i as f32 / steps as f32)))
+    }
+}
+
+/// Checks the configurations of a chain against the joint limits and the collisions of its link objects,
+/// with each other and with the rest of the scene.
+///
+/// The query pipeline has to be up to date with the scene; the link objects are placed at the checked configuration,
+/// whatever their current position. Pairs are filtered by `ObjectSet::can_collide`.
+pub struct SceneChecker<'a> {
+    pub chain: &'a KinematicChain,
+    pub objects: &'a ObjectSet,
+    pub query_pipeline: &'a QueryPipeline,
+    /// Objects moving with the flange, like the tool, with their pose in the flange frame.
+    pub attached: Vec<(ObjectHandle, Isometry3<f32>)>,
+    pub resolution: f32,
+}
+
+impl<'a> SceneChecker<'a> {
+    pub fn new(chain: &'a KinematicChain, objects: &'a ObjectSet, query_pipeline: &'a QueryPipeline) -> Self {
+        Self {
+            chain,
+            objects,
+            query_pipeline,
+            attached: Vec::new(),
+            resolution: 0.05,
+        }
+    }
+
+    pub fn attach(mut self, object: ObjectHandle, pos_wrt_flange: Isometry3<f32>) -> Self {
+        self.attached.push((object, pos_wrt_flange));
+        self
+    }
+
+    /// The objects moving with the chain and their positions at `q`.
+    fn moving_objects(&self, q: &DVector<f32>) -> Vec<(ObjectHandle, Isometry3<f32>)> {
+        let flange = self.chain.flange_pose(q);
+        let links = self.chain.link_poses(q).into_iter()
+            .zip(self.chain.links.iter())
+            .filter_map(|(pos, link)| Some((link.object?, pos)));
+        let attached = self.attached.iter().map(|(handle, pos)| (*handle, flange * pos));
+
+        links.chain(attached).collect()
+    }
+}
+
+impl StateChecker for SceneChecker<'_> {
+    fn ndofs(&self) -> usize {
+        self.chain.ndofs()
+    }
+
+    /// The joint limits, unbounded joints over one turn.
+    fn bounds(&self) -> Vec<(f32, f32)> {
+        self.chain.sampling_bounds()
+    }
+
+    fn is_valid(&self, q: &DVector<f32>) -> bool {
+        if q.len() != self.ndofs() || !self.chain.within_limits(q) {
+            return false;
+        }
+
+        let moving = self.moving_objects(q);
+        let is_moving = |handle: ObjectHandle| moving.iter().any(|(moving, _)| *moving == handle);
+        let intersects = |handle1: ObjectHandle, pos1: &Isometry3<f32>, handle2: ObjectHandle, pos2: &Isometry3<f32>| {
+            self.objects.can_collide(handle1, handle2) && query::intersection_test(
+                pos1, self.objects[handle1].shape(), pos2, self.objects[handle2].shape(),
+            ).unwrap_or(false)
+        };
+
+        for (i, (handle1, pos1)) in moving.iter().enumerate() {
+            let obj1 = match self.objects.get(*handle1) {
+                Some(obj) => obj,
+                None => continue,
+            };
+
+            for handle2 in self.query_pipeline.intersect_aabb(&obj1.shape().compute_aabb(pos1)) {
+                if !is_moving(handle2) && intersects(*handle1, pos1, handle2, self.objects[handle2].position()) {
+                    return false;
+                }
+            }
+
+            for (handle2, pos2) in &moving[i + 1..] {
+                if intersects(*handle1, pos1, *handle2, pos2) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn resolution(&self) -> f32 {
+        self.resolution
+    }
+}
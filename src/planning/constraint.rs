@@ -0,0 +1,194 @@
+use nalgebra::{DMatrix, DVector, Isometry3, Point3, RowVector6, UnitVector3, Vector3, Vector6};
+use crate::kinematics::KinematicChain;
+use crate::planning::StateChecker;
+
+
+/// A condition on the pose of the tool center point, to hold all along a planned path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TaskConstraint {
+    /// Keeps `axis`, in the tool frame, within `tolerance` radians of `direction`, in world space.
+    Orientation {
+        axis: UnitVector3<f32>,
+        direction: UnitVector3<f32>,
+        tolerance: f32,
+    },
+    /// Keeps the tool center point within `tolerance` of the plane through `point`, normal to `normal`.
+    OnPlane {
+        point: Point3<f32>,
+        normal: UnitVector3<f32>,
+        tolerance: f32,
+    },
+}
+
+impl TaskConstraint {
+    pub fn orientation(axis: UnitVector3<f32>, direction: UnitVector3<f32>, tolerance: f32) -> Self {
+        TaskConstraint::Orientation { axis, direction, tolerance }
+    }
+
+    pub fn on_plane(point: Point3<f32>, normal: UnitVector3<f32>, tolerance: f32) -> Self {
+        TaskConstraint::OnPlane { point, normal, tolerance }
+    }
+
+    /// How far `pose` is outside the tolerance, zero when it satisfies the constraint.
+    ///
+    /// Also returns the gradient of the violation with respect to a twist of the pose (linear part first),
+    /// which is zero when the constraint is satisfied.
+    pub fn violation(&self, pose: &Isometry3<f32>) -> (f32, Vector6<f32>) {
+        match *self {
+            TaskConstraint::Orientation { axis, direction, tolerance } => {
+                let current = pose.rotation * axis;
+                let angle = current.angle(&direction);
+
+                if angle <= tolerance {
+                    return (0.0, Vector6::zeros());
+                }
+
+                // Rotating around `current × direction` brings the axis toward the direction, any perpendicular
+                // rotation works for opposite directions
+                let normal = UnitVector3::try_new(current.cross(&direction), 1.0e-6)
+                    .or_else(|| UnitVector3::try_new(current.cross(&Vector3::x()), 1.0e-6))
+                    .unwrap_or_else(Vector3::y_axis);
+
+                (angle - tolerance, Vector6::new(0.0, 0.0, 0.0, -normal.x, -normal.y, -normal.z))
+            }
+            TaskConstraint::OnPlane { point, normal, tolerance } => {
+                let distance = normal.dot(&(pose.translation.vector - point.coords));
+
+                if distance.abs() <= tolerance {
+                    return (0.0, Vector6::zeros());
+                }
+
+                let gradient = normal.into_inner() * distance.signum();
+                (distance.abs() - tolerance, Vector6::new(gradient.x, gradient.y, gradient.z, 0.0, 0.0, 0.0))
+            }
+        }
+    }
+}
+
+/// Moves joint configurations onto the set satisfying task constraints, with damped least-squares steps.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConstraintProjector {
+    pub max_iterations: usize,
+    /// Largest violation accepted (in length units for positions and radians for orientations).
+    pub tolerance: f32,
+    pub damping: f32,
+    /// The largest joint displacement applied by a single iteration.
+    pub max_step: f32,
+}
+
+impl Default for ConstraintProjector {
+    fn default() -> Self {
+        Self {
+            max_iterations: 50,
+            tolerance: 1.0e-3,
+            damping: 0.01,
+            max_step: 0.3,
+        }
+    }
+}
+
+impl ConstraintProjector {
+    /// The largest violation of `constraints` by the pose of `tcp` at `q`.
+    pub fn violation(chain: &KinematicChain, tcp: &Isometry3<f32>, constraints: &[TaskConstraint], q: &DVector<f32>) -> f32 {
+        let pose = chain.forward(q, tcp);
+        constraints.iter().map(|constraint| constraint.violation(&pose).0).fold(0.0, f32::max)
+    }
+
+    /// Finds a configuration near `q`, within the joint limits, where `tcp` satisfies all the `constraints`.
+    pub fn project(
+        &self,
+        chain: &KinematicChain,
+        tcp: &Isometry3<f32>,
+        constraints: &[TaskConstraint],
+        q: &DVector<f32>,
+    ) -> Option<DVector<f32>> {
+        let mut q = q.clone();
+        chain.clamp(&mut q);
+
+        for _ in 0..self.max_iterations {
+            let pose = chain.forward(&q, tcp);
+            let violations: Vec<_> = constraints.iter().map(|constraint| constraint.violation(&pose)).collect();
+
+            if violations.iter().all(|(violation, _)| *violation <= self.tolerance) {
+                return Some(q);
+            }
+
+            // One row per constraint: the violation gradient in joint space
+            let jacobian = chain.jacobian(&q, tcp);
+            let rows: Vec<_> = violations.iter()
+                .map(|(_, gradient)| RowVector6::from(gradient.transpose()) * &jacobian)
+                .collect();
+            let jacobian = DMatrix::from_rows(&rows);
+            let error = DVector::from_iterator(violations.len(), violations.iter().map(|(violation, _)| -violation));
+
+            let jjt = &jacobian * jacobian.transpose()
+                + DMatrix::identity(violations.len(), violations.len()) * self.damping * self.damping;
+            let solved = jjt.cholesky()?.solve(&error);
+
+            let mut dq = jacobian.transpose() * solved;
+            let largest = dq.amax();
+
+            if largest > self.max_step {
+                dq *= self.max_step / largest;
+            }
+
+            q += dq;
+            chain.clamp(&mut q);
+        }
+
+        None
+    }
+}
+
+/// Restricts another checker to the configurations where the tool center point satisfies task constraints.
+///
+/// Planners move their samples onto the constraints with `StateChecker::project`, so a path made of the projected
+/// configurations follows the constraints, up to `slack` between two of them. Roadmaps connect their nodes with
+/// straight joint motions, they need many more samples than trees to follow constraints.
+pub struct ConstrainedChecker<'a> {
+    pub inner: &'a dyn StateChecker,
+    pub chain: &'a KinematicChain,
+    pub tcp: Isometry3<f32>,
+    pub constraints: Vec<TaskConstraint>,
+    pub projector: ConstraintProjector,
+    /// Largest violation accepted along motions.
+    pub slack: f32,
+}
+
+impl<'a> ConstrainedChecker<'a> {
+    /// Constrains the active tool of `chain`.
+    pub fn new(inner: &'a dyn StateChecker, chain: &'a KinematicChain, constraints: Vec<TaskConstraint>) -> Self {
+        Self {
+            inner,
+            chain,
+            tcp: chain.tool,
+            constraints,
+            projector: ConstraintProjector::default(),
+            slack: 0.01,
+        }
+    }
+}
+
+impl StateChecker for ConstrainedChecker<'_> {
+    fn ndofs(&self) -> usize {
+        self.inner.ndofs()
+    }
+
+    fn bounds(&self) -> Vec<(f32, f32)> {
+        self.inner.bounds()
+    }
+
+    fn is_valid(&self, q: &DVector<f32>) -> bool {
+        q.len() == self.ndofs()
+            && ConstraintProjector::violation(self.chain, &self.tcp, &self.constraints, q) <= self.slack
+            && self.inner.is_valid(q)
+    }
+
+    fn resolution(&self) -> f32 {
+        self.inner.resolution()
+    }
+
+    fn project(&self, q: DVector<f32>) -> Option<DVector<f32>> {
+        self.projector.project(self.chain, &self.tcp, &self.constraints, &q)
+    }
+}
@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use nalgebra::DVector;
+use crate::data::random::Random;
+use crate::errors::PlanningError;
+use crate::planning::rrt::check_endpoints;
+use crate::planning::StateChecker;
+
+
+/// A probabilistic roadmap: valid configurations connected by valid motions.
+///
+/// The roadmap is kept between queries, it has to be cleared when the scene changes.
+#[derive(Clone, Debug)]
+pub struct Prm {
+    nodes: Vec<DVector<f32>>,
+    /// The neighbors of every node, with the length of the motion to them.
+    edges: Vec<Vec<(usize, f32)>>,
+    /// How many nearest nodes each new node tries to connect to.
+    pub neighbors: usize,
+    random: Random,
+}
+
+/// A node to visit by Dijkstra's algorithm, the cheapest first.
+#[derive(Copy, Clone, PartialEq)]
+struct Candidate {
+    cost: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Prm {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            neighbors: 10,
+            random: Random::new(seed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+    /// Samples `samples` configurations and adds the valid ones to the roadmap.
+    pub fn grow(&mut self, checker: &dyn StateChecker, samples: usize) {
+        let bounds = checker.bounds();
+
+        for _ in 0..samples {
+            let random = &mut self.random;
+            let q = DVector::from_iterator(bounds.len(), bounds.iter().map(|(min, max)| random.range(*min, *max)));
+
+            match checker.project(q) {
+                Some(q) if checker.is_valid(&q) => {
+                    self.insert(checker, q);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds a valid configuration, connected to its nearest neighbors.
+    fn insert(&mut self, checker: &dyn StateChecker, q: DVector<f32>) -> usize {
+        let mut distances: Vec<_> = self.nodes.iter()
+            .enumerate()
+            .map(|(i, other)| (i, (other - &q).norm()))
+            .collect();
+        distances.sort_by(|(_, d1), (_, d2)| d1.total_cmp(d2));
+
+        let index = self.nodes.len();
+        self.edges.push(Vec::new());
+
+        for (other, distance) in distances.into_iter().take(self.neighbors) {
+            if checker.is_motion_valid(&q, &self.nodes[other]) {
+                self.edges[index].push((other, distance));
+                self.edges[other].push((index, distance));
+            }
+        }
+
+        self.nodes.push(q);
+        index
+    }
+
+    /// Finds the shortest path through the roadmap from `start` to `goal`, both included.
+    ///
+    /// Both configurations are added to the roadmap, so later queries can reuse them.
+    pub fn plan(
+        &mut self,
+        checker: &dyn StateChecker,
+        start: &DVector<f32>,
+        goal: &DVector<f32>,
+    ) -> Result<Vec<DVector<f32>>, PlanningError> {
+        check_endpoints(checker, start, goal)?;
+
+        if checker.is_motion_valid(start, goal) {
+            return Ok(vec![start.clone(), goal.clone()]);
+        }
+
+        let start = self.insert(checker, start.clone());
+        let goal = self.insert(checker, goal.clone());
+
+        self.shortest_path(start, goal)
+            .map(|path| path.into_iter().map(|node| self.nodes[node].clone()).collect())
+            .ok_or(PlanningError::NoPath)
+    }
+
+    fn shortest_path(&self, start: usize, goal: usize) -> Option<Vec<usize>> {
+        let mut costs = vec![f32::INFINITY; self.nodes.len()];
+        let mut previous = vec![None; self.nodes.len()];
+        let mut queue = BinaryHeap::new();
+
+        costs[start] = 0.0;
+        queue.push(Candidate { cost: 0.0, node: start });
+
+        while let Some(Candidate { cost, node }) = queue.pop() {
+            if node == goal {
+                let mut path = vec![goal];
+
+                while let Some(node) = previous[*path.last().unwrap()] {
+                    path.push(node);
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            if cost > costs[node] {
+                continue;
+            }
+
+            for (next, length) in &self.edges[node] {
+                let cost = cost + length;
+
+                if cost < costs[*next] {
+                    costs[*next] = cost;
+                    previous[*next] = Some(node);
+                    queue.push(Candidate { cost, node: *next });
+                }
+            }
+        }
+
+        None
+    }
+}
\ No newline at end of file
@@ -0,0 +1,176 @@
+use nalgebra::DVector;
+use crate::data::random::Random;
+use crate::errors::PlanningError;
+use crate::planning::StateChecker;
+
+
+/// Bidirectional rapidly-exploring random trees, growing from the start and the goal until they meet.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RrtConnect {
+    pub max_iterations: usize,
+    /// The largest joint displacement of a single tree extension, in radians.
+    pub step_size: f32,
+    pub seed: u64,
+}
+
+impl Default for RrtConnect {
+    fn default() -> Self {
+        Self {
+            max_iterations: 5000,
+            step_size: 0.2,
+            seed: 0,
+        }
+    }
+}
+
+struct Node {
+    q: DVector<f32>,
+    parent: Option<usize>,
+}
+
+enum Extension {
+    Reached(usize),
+    Advanced(usize),
+    Trapped,
+}
+
+impl RrtConnect {
+    /// Finds a path from `start` to `goal`, both included. The path isn't smoothed.
+    pub fn plan(
+        &self,
+        checker: &dyn StateChecker,
+        start: &DVector<f32>,
+        goal: &DVector<f32>,
+    ) -> Result<Vec<DVector<f32>>, PlanningError> {
+        check_endpoints(checker, start, goal)?;
+
+        if checker.is_motion_valid(start, goal) {
+            return Ok(vec![start.clone(), goal.clone()]);
+        }
+
+        let mut random = Random::new(self.seed);
+        let bounds = checker.bounds();
+        let mut tree_a = vec![Node { q: start.clone(), parent: None }];
+        let mut tree_b = vec![Node { q: goal.clone(), parent: None }];
+        let mut a_is_start = true;
+
+        for _ in 0..self.max_iterations {
+            let sample = DVector::from_iterator(bounds.len(), bounds.iter().map(|(min, max)| random.range(*min, *max)));
+
+            let new = match self.extend(checker, &mut tree_a, &sample) {
+                Extension::Reached(new) | Extension::Advanced(new) => new,
+                Extension::Trapped => {
+                    std::mem::swap(&mut tree_a, &mut tree_b);
+                    a_is_start = !a_is_start;
+                    continue;
+                }
+            };
+            let target = tree_a[new].q.clone();
+
+            if let Some(reached) = self.connect(checker, &mut tree_b, &target) {
+                let (mut path, mut rest) = (branch(&tree_a, new), branch(&tree_b, reached));
+                path.reverse();
+                rest.remove(0);
+                path.extend(rest);
+
+                if !a_is_start {
+                    path.reverse();
+                }
+
+                return Ok(path);
+            }
+
+            std::mem::swap(&mut tree_a, &mut tree_b);
+            a_is_start = !a_is_start;
+        }
+
+        Err(PlanningError::NoPath)
+    }
+
+    /// Grows the tree by one step from its node nearest to `target`.
+    fn extend(&self, checker: &dyn StateChecker, tree: &mut Vec<Node>, target: &DVector<f32>) -> Extension {
+        let nearest = nearest(tree.iter().map(|node| &node.q), target);
+        let from = &tree[nearest].q;
+        let delta = target - from;
+        let distance = delta.norm();
+        let q = if distance <= self.step_size {
+            target.clone()
+        } else {
+            from + delta * (self.step_size / distance)
+        };
+
+        // The projection onto the constraints may move the step away from the target
+        let q = match checker.project(q) {
+            Some(q) if (target - &q).norm() < distance => q,
+            _ => return Extension::Trapped,
+        };
+        let reached = (target - &q).norm() <= 1.0e-5;
+
+        if !checker.is_motion_valid(from, &q) {
+            return Extension::Trapped;
+        }
+
+        tree.push(Node { q, parent: Some(nearest) });
+
+        if reached {
+            Extension::Reached(tree.len() - 1)
+        } else {
+            Extension::Advanced(tree.len() - 1)
+        }
+    }
+
+    /// Grows the tree toward `target` until it reaches it or gets stuck.
+    fn connect(&self, checker: &dyn StateChecker, tree: &mut Vec<Node>, target: &DVector<f32>) -> Option<usize> {
+        loop {
+            match self.extend(checker, tree, target) {
+                Extension::Reached(node) => return Some(node),
+                Extension::Advanced(_) => {}
+                Extension::Trapped => return None,
+            }
+        }
+    }
+}
+
+/// The configurations from `node` back to the root of its tree.
+fn branch(tree: &[Node], node: usize) -> Vec<DVector<f32>> {
+    let mut path = Vec::new();
+    let mut current = Some(node);
+
+    while let Some(node) = current {
+        path.push(tree[node].q.clone());
+        current = tree[node].parent;
+    }
+
+    path
+}
+
+pub(crate) fn nearest<'a>(configurations: impl Iterator<Item = &'a DVector<f32>>, q: &DVector<f32>) -> usize {
+    configurations
+        .map(|other| (other - q).norm_squared())
+        .enumerate()
+        .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+pub(crate) fn check_endpoints(
+    checker: &dyn StateChecker,
+    start: &DVector<f32>,
+    goal: &DVector<f32>,
+) -> Result<(), PlanningError> {
+    for q in [start, goal] {
+        if q.len() != checker.ndofs() {
+            return Err(PlanningError::DofMismatch { expected: checker.ndofs(), found: q.len() });
+        }
+    }
+
+    if !checker.is_valid(start) {
+        return Err(PlanningError::InvalidStart);
+    }
+
+    if !checker.is_valid(goal) {
+        return Err(PlanningError::InvalidGoal);
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,62 @@
+use nalgebra::DVector;
+use crate::data::random::Random;
+use crate::planning::StateChecker;
+use crate::program::{Command, Motion, MotionKind, Target};
+
+
+/// Shortens planned paths by replacing random sections with direct joint motions, where these are valid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathSmoother {
+    pub iterations: usize,
+    pub seed: u64,
+}
+
+impl Default for PathSmoother {
+    fn default() -> Self {
+        Self {
+            iterations: 200,
+            seed: 0,
+        }
+    }
+}
+
+impl PathSmoother {
+    pub fn smooth(&self, checker: &dyn StateChecker, path: &[DVector<f32>]) -> Vec<DVector<f32>> {
+        let mut path = path.to_vec();
+        let mut random = Random::new(self.seed);
+
+        for _ in 0..self.iterations {
+            if path.len() <= 2 {
+                break;
+            }
+
+            let i = random.index(path.len());
+            let j = random.index(path.len());
+            let (i, j) = if i < j { (i, j) } else { (j, i) };
+
+            if j > i + 1 && checker.is_motion_valid(&path[i], &path[j]) {
+                path.drain(i + 1..j);
+            }
+        }
+
+        path
+    }
+}
+
+/// The length of a path in joint space.
+pub fn path_length(path: &[DVector<f32>]) -> f32 {
+    path.windows(2).map(|pair| (&pair[1] - &pair[0]).norm()).sum()
+}
+
+/// `PTP` motions through every configuration of a path but the first one.
+pub fn ptp_commands(path: &[DVector<f32>], velocity: Option<f32>) -> Vec<Command> {
+    path.iter()
+        .skip(1)
+        .map(|q| Command::Move(Motion {
+            kind: MotionKind::Ptp,
+            target: Target::Joints(q.clone()),
+            via: None,
+            velocity,
+        }))
+        .collect()
+}
@@ -0,0 +1,221 @@
+use std::f64::consts::PI;
+use nalgebra::{Isometry3, Matrix3, Quaternion, Rotation3, Translation3, Unit, UnitQuaternion, Vector3};
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AngleUnit {
+    Radians,
+    Degrees,
+}
+
+impl AngleUnit {
+    pub fn to_radians(self, angle: f32) -> f32 {
+        match self {
+            AngleUnit::Radians => angle,
+            AngleUnit::Degrees => angle.to_radians(),
+        }
+    }
+
+    pub fn from_radians(self, angle: f32) -> f32 {
+        match self {
+            AngleUnit::Radians => angle,
+            AngleUnit::Degrees => angle.to_degrees(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+impl Axis {
+    pub fn unit_vector(self) -> Unit<Vector3<f32>> {
+        match self {
+            Axis::X => Vector3::x_axis(),
+            Axis::Y => Vector3::y_axis(),
+            Axis::Z => Vector3::z_axis(),
+        }
+    }
+}
+
+/// An Euler angle convention: three rotations about the axes of the moving frame (intrinsic)
+/// or of the fixed frame (extrinsic), applied in order.
+///
+/// Both Tait-Bryan (`ZYX`) and proper Euler (`ZYZ`) sets are supported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EulerSet {
+    pub axes: [Axis; 3],
+    pub intrinsic: bool,
+}
+
+impl EulerSet {
+    /// Yaw, pitch, roll about the moving axes. This is the KUKA A, B, C convention.
+    pub const ZYX: Self = Self::intrinsic(Axis::Z, Axis::Y, Axis::X);
+    pub const ZYZ: Self = Self::intrinsic(Axis::Z, Axis::Y, Axis::Z);
+    pub const XYZ: Self = Self::intrinsic(Axis::X, Axis::Y, Axis::Z);
+    /// Roll, pitch, yaw about the fixed axes, the same rotation as `ZYX` with the angles reversed.
+    pub const RPY: Self = Self::extrinsic(Axis::X, Axis::Y, Axis::Z);
+
+    /// Rotations about the moving axes. Two consecutive axes can't be the same.
+    pub const fn intrinsic(first: Axis, second: Axis, third: Axis) -> Self {
+        assert!(first as usize != second as usize && second as usize != third as usize);
+        Self { axes: [first, second, third], intrinsic: true }
+    }
+
+    /// Rotations about the fixed axes. Two consecutive axes can't be the same.
+    pub const fn extrinsic(first: Axis, second: Axis, third: Axis) -> Self {
+        assert!(first as usize != second as usize && second as usize != third as usize);
+        Self { axes: [first, second, third], intrinsic: false }
+    }
+
+    /// Whether the first and last axes are the same, like in `ZYZ`.
+    pub fn is_proper(&self) -> bool {
+        self.axes[0] == self.axes[2]
+    }
+
+    /// The rotation described by `angles`, given in the order of the axes.
+    pub fn to_rotation(&self, angles: [f32; 3], unit: AngleUnit) -> UnitQuaternion<f32> {
+        let [r1, r2, r3] = [0, 1, 2].map(|i| {
+            UnitQuaternion::from_axis_angle(&self.axes[i].unit_vector(), unit.to_radians(angles[i]))
+        });
+
+        if self.intrinsic {
+            r1 * r2 * r3
+        } else {
+            r3 * r2 * r1
+        }
+    }
+
+    /// The angles describing `rotation`, in the order of the axes.
+    ///
+    /// The middle angle is in [-90°, 90°] for Tait-Bryan sets and [0°, 180°] for proper sets, the others
+    /// in [-180°, 180°]. At a singularity only the sum or difference of the first and third angles is defined,
+    /// and all of it is put on the first one.
+    pub fn from_rotation(&self, rotation: &UnitQuaternion<f32>, unit: AngleUnit) -> [f32; 3] {
+        // Bernardes & Viollet's direct method, formulated for extrinsic rotations:
+        // an intrinsic set is the extrinsic set with its axes and angles reversed.
+        let q = rotation.cast::<f64>();
+        let mut axes = self.axes.map(|axis| axis as usize);
+
+        if self.intrinsic {
+            axes.reverse();
+        }
+
+        let [i, j, mut k] = axes;
+        let proper = i == k;
+
+        if proper {
+            k = 3 - i - j;
+        }
+
+        let sign = ((i as i64 - j as i64) * (j as i64 - k as i64) * (k as i64 - i as i64) / 2) as f64;
+        let v = [q.i, q.j, q.k];
+        let (a, b, c, d) = if proper {
+            (q.w, v[i], v[j], v[k] * sign)
+        } else {
+            (q.w - v[j], v[i] + v[k] * sign, v[j] + q.w, v[k] * sign - v[i])
+        };
+
+        let mut second = 2.0 * c.hypot(d).atan2(a.hypot(b));
+        let half_sum = b.atan2(a);
+        let half_diff = d.atan2(c);
+        let (mut first, mut third);
+
+        if second.abs() <= SINGULARITY_EPS || (second - PI).abs() <= SINGULARITY_EPS {
+            let defined = if second.abs() <= SINGULARITY_EPS { 2.0 * half_sum } else { 2.0 * half_diff };
+            let sum = second.abs() <= SINGULARITY_EPS;
+
+            // `first` is applied first: it is the last angle of an intrinsic set
+            if self.intrinsic {
+                first = 0.0;
+                third = defined;
+            } else {
+                first = if sum { defined } else { -defined };
+                third = 0.0;
+            }
+        } else {
+            first = half_sum - half_diff;
+            third = half_sum + half_diff;
+        }
+
+        if !proper {
+            third *= sign;
+            second -= PI / 2.0;
+        }
+
+        first = wrap(first);
+        third = wrap(third);
+
+        let angles = if self.intrinsic { [third, second, first] } else { [first, second, third] };
+        angles.map(|angle| unit.from_radians(angle as f32))
+    }
+}
+
+const SINGULARITY_EPS: f64 = 1.0e-6;
+
+/// Wraps an angle to (-π, π].
+fn wrap(angle: f64) -> f64 {
+    let wrapped = (angle + PI).rem_euclid(2.0 * PI) - PI;
+
+    if wrapped <= -PI { wrapped + 2.0 * PI } else { wrapped }
+}
+
+pub fn rotation_matrix(rotation: &UnitQuaternion<f32>) -> Matrix3<f32> {
+    rotation.to_rotation_matrix().into_inner()
+}
+
+/// The rotation closest to `matrix`, which doesn't need to be exactly orthonormal.
+pub fn from_rotation_matrix(matrix: &Matrix3<f32>) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix(matrix))
+}
+
+/// KUKA A, B, C angles in degrees: rotations about Z, Y and X of the moving frame.
+pub fn kuka_abc(rotation: &UnitQuaternion<f32>) -> [f32; 3] {
+    EulerSet::ZYX.from_rotation(rotation, AngleUnit::Degrees)
+}
+
+pub fn from_kuka_abc(abc: [f32; 3]) -> UnitQuaternion<f32> {
+    EulerSet::ZYX.to_rotation(abc, AngleUnit::Degrees)
+}
+
+/// A KUKA `FRAME`, positions in the unit of the translation.
+pub fn kuka_frame(x: f32, y: f32, z: f32, a: f32, b: f32, c: f32) -> Isometry3<f32> {
+    Isometry3::from_parts(Translation3::new(x, y, z), from_kuka_abc([a, b, c]))
+}
+
+/// An ABB orientation `[q1, q2, q3, q4]`: scalar first, with `q1 >= 0`.
+pub fn abb_quaternion(rotation: &UnitQuaternion<f32>) -> [f32; 4] {
+    let q = rotation.quaternion();
+    let sign = if q.w < 0.0 { -1.0 } else { 1.0 };
+
+    [q.w * sign, q.i * sign, q.j * sign, q.k * sign]
+}
+
+/// Reads an ABB orientation, normalizing it as the controller does.
+pub fn from_abb_quaternion(q: [f32; 4]) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_quaternion(Quaternion::new(q[0], q[1], q[2], q[3]))
+}
+
+/// A Universal Robots rotation vector: the rotation axis scaled by the angle in radians.
+pub fn ur_rotvec(rotation: &UnitQuaternion<f32>) -> Vector3<f32> {
+    rotation.scaled_axis()
+}
+
+pub fn from_ur_rotvec(rotvec: Vector3<f32>) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_scaled_axis(rotvec)
+}
+
+/// A Universal Robots pose `p[x, y, z, rx, ry, rz]`, positions in the unit of the translation.
+pub fn ur_pose(pose: &Isometry3<f32>) -> [f32; 6] {
+    let t = pose.translation.vector;
+    let r = ur_rotvec(&pose.rotation);
+
+    [t.x, t.y, t.z, r.x, r.y, r.z]
+}
+
+pub fn from_ur_pose(p: [f32; 6]) -> Isometry3<f32> {
+    Isometry3::from_parts(Translation3::new(p[0], p[1], p[2]), from_ur_rotvec(Vector3::new(p[3], p[4], p[5])))
+}
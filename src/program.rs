@@ -0,0 +1,13 @@
+mod ast;
+mod lexer;
+mod parser;
+mod motion;
+mod interpreter;
+pub mod export;
+pub mod import;
+
+
+pub use self::ast::{Command, Condition, Instruction, Motion, MotionKind, Program, Target};
+pub use self::parser::parse;
+pub use self::motion::{MotionPath, MotionSample, MotionSegment};
+pub use self::interpreter::{Interpreter, MotionSettings, ProgramContext, ProgramState};
\ No newline at end of file
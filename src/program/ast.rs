@@ -0,0 +1,99 @@
+use nalgebra::{DVector, Isometry3};
+
+
+/// A motion destination, either literal or referring to a program variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Named(String),
+    /// Joint positions, in radians.
+    Joints(DVector<f32>),
+    /// A cartesian pose of the tool center point, expressed in the active base frame.
+    Pose(Isometry3<f32>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MotionKind {
+    /// Point-to-point motion interpolated in joint space.
+    Ptp,
+    /// Straight line motion of the tool center point.
+    Lin,
+    /// Circular motion of the tool center point through an auxiliary point.
+    Circ,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Motion {
+    pub kind: MotionKind,
+    pub target: Target,
+    /// The auxiliary point of a `CIRC` motion.
+    pub via: Option<Target>,
+    /// Percent of the maximum joint velocity for `PTP`, tool speed for `LIN` and `CIRC`.
+    pub velocity: Option<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Input { port: usize, value: bool },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Assign { name: String, target: Target },
+    Tool(usize),
+    Base(usize),
+    Move(Motion),
+    SetOutput { port: usize, value: bool },
+    /// Closes (`GRIP`) or opens (`RELEASE`) the gripper of the active tool.
+    Gripper(bool),
+    Wait(Condition),
+    WaitTime(f32),
+    Label(String),
+    Goto { label: String, condition: Option<Condition> },
+    /// Start of a loop; `end` is the index of the matching `EndLoop`.
+    Loop { count: Option<u32>, end: usize },
+    /// End of a loop; `start` is the index of the matching `Loop`.
+    EndLoop { start: usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub command: Command,
+    /// The 1-based source line of this instruction, or 0 if it wasn't parsed from text.
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Program {
+    pub name: String,
+    pub instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            instructions: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, command: Command) -> &mut Self {
+        self.instructions.push(Instruction { command, line: 0 });
+
+        self
+    }
+
+    /// The index of the instruction declaring the label `name`.
+    pub fn label(&self, name: &str) -> Option<usize> {
+        self.instructions.iter().position(|i| match &i.command {
+            Command::Label(label) => label.eq_ignore_ascii_case(name),
+            _ => false,
+        })
+    }
+
+    pub fn motions(&self) -> impl Iterator<Item = &Motion> {
+        self.instructions.iter().filter_map(|i| match &i.command {
+            Command::Move(motion) => Some(motion),
+            _ => None,
+        })
+    }
+}
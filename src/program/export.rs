@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use nalgebra::{Isometry3, Point3};
+use crate::errors::ExportError;
+use crate::program::ast::{Command, Motion, Program, Target};
+use crate::program::MotionSettings;
+
+mod krl;
+mod rapid;
+mod urscript;
+
+
+pub use self::krl::Krl;
+pub use self::rapid::Rapid;
+pub use self::urscript::UrScript;
+
+/// The cell description exported alongside a program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellData {
+    /// Tool center points wrt. the flange, by tool number.
+    pub tools: BTreeMap<usize, Isometry3<f32>>,
+    /// Base frames wrt. the robot base, by base number.
+    pub bases: BTreeMap<usize, Isometry3<f32>>,
+    /// The length of one scene unit, in meters.
+    pub unit_length: f32,
+    /// The digital output driving the gripper, set by `GRIP` and reset by `RELEASE`.
+    pub gripper_output: Option<usize>,
+    /// The loads carried by the tools, by tool number.
+    pub tool_loads: BTreeMap<usize, ToolLoad>,
+}
+
+/// The mass of a tool, for the load data of the controller.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToolLoad {
+    /// The mass, in kilograms.
+    pub mass: f32,
+    /// The center of gravity, expressed in the flange frame.
+    pub center_of_mass: Point3<f32>,
+}
+
+impl Default for CellData {
+    fn default() -> Self {
+        Self {
+            tools: BTreeMap::new(),
+            bases: BTreeMap::new(),
+            unit_length: 1.0,
+            gripper_output: None,
+            tool_loads: BTreeMap::new(),
+        }
+    }
+}
+
+/// Translates bluster programs to a vendor robot language.
+pub trait PostProcessor {
+    /// The name of the target language.
+    fn name(&self) -> &'static str;
+    fn export(&self, program: &Program, cell: &CellData) -> Result<String, ExportError>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum VariableKind {
+    Joints,
+    Pose,
+}
+
+/// The kind of every program variable, sorted by name.
+pub(crate) fn variable_kinds(program: &Program) -> Result<BTreeMap<String, VariableKind>, ExportError> {
+    let mut kinds = BTreeMap::new();
+
+    for instruction in &program.instructions {
+        let (name, target) = match &instruction.command {
+            Command::Assign { name, target } => (name.to_ascii_lowercase(), target),
+            _ => continue,
+        };
+        let kind = match target {
+            Target::Joints(_) => VariableKind::Joints,
+            Target::Pose(_) => VariableKind::Pose,
+            Target::Named(other) => *kinds.get(&other.to_ascii_lowercase()).ok_or_else(|| {
+                ExportError::UnknownVariable { line: instruction.line, name: other.clone() }
+            })?,
+        };
+
+        match kinds.insert(name.clone(), kind) {
+            Some(previous) if previous != kind => {
+                return Err(ExportError::TypeConflict { line: instruction.line, name });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(kinds)
+}
+
+/// Checks that every tool and base used by the program is described by the cell, and that the cell doesn't
+/// redefine the bare flange or robot base.
+pub(crate) fn check_frames(program: &Program, cell: &CellData) -> Result<(), ExportError> {
+    if cell.tools.contains_key(&0) || cell.tool_loads.contains_key(&0) {
+        return Err(ExportError::ReservedFrame { frame: "tool" });
+    }
+
+    if cell.bases.contains_key(&0) {
+        return Err(ExportError::ReservedFrame { frame: "base" });
+    }
+
+    for instruction in &program.instructions {
+        match instruction.command {
+            Command::Tool(tool) if tool != 0 && !cell.tools.contains_key(&tool) => {
+                return Err(ExportError::UnknownTool { line: instruction.line, tool });
+            }
+            Command::Base(base) if base != 0 && !cell.bases.contains_key(&base) => {
+                return Err(ExportError::UnknownBase { line: instruction.line, base });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The output driving the gripper, required by programs using it.
+pub(crate) fn gripper_output(cell: &CellData, line: usize, language: &'static str) -> Result<usize, ExportError> {
+    cell.gripper_output.ok_or(ExportError::Unsupported { line, language, feature: "gripping without a gripper output" })
+}
+
+/// The speed of a `PTP` motion in percent, full speed without `VEL` like the interpreter.
+pub(crate) fn ptp_percent(motion: &Motion) -> f32 {
+    motion.velocity.unwrap_or(100.0).min(100.0)
+}
+
+/// The tool speed of a `LIN` or `CIRC` motion in m/s, the one of the interpreter without `VEL`.
+pub(crate) fn tool_speed(motion: &Motion, cell: &CellData) -> f32 {
+    motion.velocity.unwrap_or(MotionSettings::default().linear_velocity) * cell.unit_length
+}
+
+pub(crate) fn auxiliary_point(motion: &Motion, line: usize) -> Result<&Target, ExportError> {
+    motion.via.as_ref().ok_or(ExportError::MissingAuxiliaryPoint { line })
+}
+
+/// Formats a number with a fixed precision, without negative zeros.
+pub(crate) fn num(value: f32, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+
+    if text.trim_start_matches('-').chars().all(|c| c == '0' || c == '.') {
+        text.trim_start_matches('-').to_string()
+    } else {
+        text
+    }
+}
+
+/// An indentation-aware text buffer.
+pub(crate) struct Lines {
+    text: String,
+    indent: usize,
+    unit: &'static str,
+}
+
+impl Lines {
+    pub fn new(unit: &'static str) -> Self {
+        Self {
+            text: String::new(),
+            indent: 0,
+            unit,
+        }
+    }
+
+    pub fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.text.push_str(self.unit);
+            }
+        }
+
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    pub fn indent(&mut self) {
+        self.indent += 1;
+    }
+
+    pub fn dedent(&mut self) {
+        self.indent = self.indent.saturating_sub(1);
+    }
+
+    pub fn append(&mut self, other: Lines) {
+        for line in other.text.lines() {
+            self.line(line);
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.text
+    }
+}
@@ -0,0 +1,173 @@
+use nalgebra::{DVector, Isometry3};
+use crate::errors::ExportError;
+use crate::program::ast::{Command, Condition, MotionKind, Program, Target};
+use crate::pose::kuka_abc;
+use crate::program::export::{auxiliary_point, check_frames, gripper_output, num, ptp_percent, tool_speed, variable_kinds, CellData, Lines, PostProcessor, VariableKind};
+
+
+/// KUKA Robot Language post-processor.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Krl;
+
+impl Krl {
+    fn frame(&self, pose: &Isometry3<f32>, cell: &CellData) -> String {
+        let t = pose.translation.vector * cell.unit_length * 1000.0;
+        let [a, b, c] = kuka_abc(&pose.rotation);
+
+        format!(
+            "{{X {}, Y {}, Z {}, A {}, B {}, C {}}}",
+            num(t.x, 3), num(t.y, 3), num(t.z, 3), num(a, 3), num(b, 3), num(c, 3),
+        )
+    }
+
+    fn axis(&self, joints: &DVector<f32>) -> String {
+        let values: Vec<_> = joints.iter()
+            .enumerate()
+            .map(|(i, q)| {
+                let name = if i < 6 { format!("A{}", i + 1) } else { format!("E{}", i - 5) };
+                format!("{} {}", name, num(q.to_degrees(), 3))
+            })
+            .collect();
+
+        format!("{{{}}}", values.join(", "))
+    }
+
+    fn target(&self, target: &Target, cell: &CellData) -> String {
+        match target {
+            Target::Named(name) => name.to_ascii_lowercase(),
+            Target::Joints(joints) => self.axis(joints),
+            Target::Pose(pose) => self.frame(pose, cell),
+        }
+    }
+
+    fn condition(&self, condition: &Condition) -> String {
+        match condition {
+            Condition::Input { port, value: true } => format!("$IN[{}]", port),
+            Condition::Input { port, value: false } => format!("NOT $IN[{}]", port),
+        }
+    }
+}
+
+impl PostProcessor for Krl {
+    fn name(&self) -> &'static str {
+        "KRL"
+    }
+
+    fn export(&self, program: &Program, cell: &CellData) -> Result<String, ExportError> {
+        let variables = variable_kinds(program)?;
+        check_frames(program, cell)?;
+
+        let mut body = Lines::new("  ");
+        let mut counters = 0;
+        let mut depth = 0;
+
+        for instruction in &program.instructions {
+            match &instruction.command {
+                Command::Assign { name, target } => {
+                    body.line(format!("{} = {}", name.to_ascii_lowercase(), self.target(target, cell)));
+                }
+                Command::Tool(0) => body.line("$TOOL = $NULLFRAME"),
+                Command::Tool(tool) => body.line(format!("$TOOL = TOOL_DATA[{}]", tool)),
+                Command::Base(0) => body.line("$BASE = $NULLFRAME"),
+                Command::Base(base) => body.line(format!("$BASE = BASE_DATA[{}]", base)),
+                Command::Move(motion) => {
+                    let target = self.target(&motion.target, cell);
+
+                    match motion.kind {
+                        // The controller keeps speeds until they change, every motion sets its own
+                        MotionKind::Ptp => {
+                            body.line(format!("BAS(#VEL_PTP, {})", num(ptp_percent(motion), 1)));
+                            body.line(format!("PTP {}", target));
+                        }
+                        MotionKind::Lin | MotionKind::Circ => {
+                            body.line(format!("$VEL.CP = {}", num(tool_speed(motion, cell), 4)));
+
+                            if motion.kind == MotionKind::Lin {
+                                body.line(format!("LIN {}", target));
+                            } else {
+                                let via = self.target(auxiliary_point(motion, instruction.line)?, cell);
+                                body.line(format!("CIRC {}, {}", via, target));
+                            }
+                        }
+                    }
+                }
+                Command::SetOutput { port, value } => {
+                    body.line(format!("$OUT[{}] = {}", port, if *value { "TRUE" } else { "FALSE" }));
+                }
+                Command::Gripper(closed) => {
+                    let port = gripper_output(cell, instruction.line, self.name())?;
+                    body.line(format!("$OUT[{}] = {}", port, if *closed { "TRUE" } else { "FALSE" }));
+                }
+                Command::Wait(condition) => body.line(format!("WAIT FOR {}", self.condition(condition))),
+                Command::WaitTime(seconds) => body.line(format!("WAIT SEC {}", num(*seconds, 3))),
+                Command::Label(label) => body.line(format!("{}:", label)),
+                Command::Goto { label, condition: None } => body.line(format!("GOTO {}", label)),
+                Command::Goto { label, condition: Some(condition) } => {
+                    body.line(format!("IF {} THEN", self.condition(condition)));
+                    body.indent();
+                    body.line(format!("GOTO {}", label));
+                    body.dedent();
+                    body.line("ENDIF");
+                }
+                Command::Loop { count: Some(count), .. } => {
+                    depth += 1;
+                    counters = counters.max(depth);
+                    body.line(format!("FOR i{} = 1 TO {}", depth, count));
+                    body.indent();
+                }
+                Command::Loop { count: None, .. } => {
+                    body.line("LOOP");
+                    body.indent();
+                }
+                Command::EndLoop { start } => {
+                    body.dedent();
+
+                    if let Command::Loop { count: Some(_), .. } = program.instructions[*start].command {
+                        depth -= 1;
+                        body.line("ENDFOR");
+                    } else {
+                        body.line("ENDLOOP");
+                    }
+                }
+            }
+        }
+
+        let mut out = Lines::new("  ");
+        out.line(format!("DEF {}()", program.name));
+        out.indent();
+
+        for (name, kind) in &variables {
+            let kind = match kind {
+                VariableKind::Joints => "E6AXIS",
+                VariableKind::Pose => "E6POS",
+            };
+            out.line(format!("DECL {} {}", kind, name));
+        }
+
+        for i in 1..=counters {
+            out.line(format!("DECL INT i{}", i));
+        }
+
+        if !variables.is_empty() || counters > 0 {
+            out.line("");
+        }
+
+        for (tool, frame) in &cell.tools {
+            out.line(format!("TOOL_DATA[{}] = {}", tool, self.frame(frame, cell)));
+        }
+
+        for (base, frame) in &cell.bases {
+            out.line(format!("BASE_DATA[{}] = {}", base, self.frame(frame, cell)));
+        }
+
+        if !cell.tools.is_empty() || !cell.bases.is_empty() {
+            out.line("");
+        }
+
+        out.append(body);
+        out.dedent();
+        out.line("END");
+
+        Ok(out.finish())
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use nalgebra::{DVector, Isometry3, UnitQuaternion};
+use crate::errors::ExportError;
+use crate::pose::abb_quaternion;
+use crate::program::ast::{Command, Condition, Motion, MotionKind, Program, Target};
+use crate::program::export::{auxiliary_point, check_frames, gripper_output, num, ptp_percent, tool_speed, variable_kinds, CellData, Lines, PostProcessor, VariableKind};
+use crate::program::MotionSettings;
+
+
+/// ABB RAPID post-processor.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Rapid;
+
+const EXTERNAL_AXES_UNUSED: &str = "[9E9, 9E9, 9E9, 9E9, 9E9, 9E9]";
+
+/// The TCP speed of `v5000`, the fastest predefined speeddata, in mm/s.
+const MAX_TCP_SPEED: &str = "5000";
+
+impl Rapid {
+    fn position(&self, pose: &Isometry3<f32>, cell: &CellData) -> String {
+        let t = pose.translation.vector * cell.unit_length * 1000.0;
+        format!("[{}, {}, {}]", num(t.x, 3), num(t.y, 3), num(t.z, 3))
+    }
+
+    /// An orientation as the `[q1, q2, q3, q4]` quaternion of RAPID, scalar first.
+    fn orientation(&self, rotation: &UnitQuaternion<f32>) -> String {
+        let [q1, q2, q3, q4] = abb_quaternion(rotation);
+        format!("[{}, {}, {}, {}]", num(q1, 6), num(q2, 6), num(q3, 6), num(q4, 6))
+    }
+
+    fn pose(&self, pose: &Isometry3<f32>, cell: &CellData) -> String {
+        format!("[{}, {}]", self.position(pose, cell), self.orientation(&pose.rotation))
+    }
+
+    fn robtarget(&self, pose: &Isometry3<f32>, cell: &CellData) -> String {
+        format!("[{}, {}, [0, 0, 0, 0], {}]", self.position(pose, cell), self.orientation(&pose.rotation), EXTERNAL_AXES_UNUSED)
+    }
+
+    fn jointtarget(&self, joints: &DVector<f32>) -> String {
+        let mut robot: Vec<_> = joints.iter().take(6).map(|q| num(q.to_degrees(), 3)).collect();
+        let mut external: Vec<_> = joints.iter().skip(6).take(6).map(|q| num(q.to_degrees(), 3)).collect();
+
+        robot.resize(6, "0".to_string());
+        external.resize(6, "9E9".to_string());
+
+        format!("[[{}], [{}]]", robot.join(", "), external.join(", "))
+    }
+
+    fn target(&self, target: &Target, cell: &CellData) -> String {
+        match target {
+            Target::Named(name) => name.to_ascii_lowercase(),
+            Target::Joints(joints) => self.jointtarget(joints),
+            Target::Pose(pose) => self.robtarget(pose, cell),
+        }
+    }
+
+    /// The speeddata of a motion, from the speeds of the interpreter.
+    ///
+    /// A `PTP` motion bounds the reorientation and rotating axis speeds by its joint speed, leaving the TCP speed
+    /// to the controller limit.
+    fn speed(&self, motion: &Motion, cell: &CellData) -> String {
+        let settings = MotionSettings::default();
+
+        match motion.kind {
+            MotionKind::Ptp => {
+                let joint_speed = num((settings.max_joint_velocity * ptp_percent(motion) / 100.0).to_degrees(), 1);
+                format!("[{}, {}, {}, {}]", MAX_TCP_SPEED, joint_speed, MAX_TCP_SPEED, joint_speed)
+            }
+            MotionKind::Lin | MotionKind::Circ => format!(
+                "[{}, {}, {}, {}]",
+                num(tool_speed(motion, cell) * 1000.0, 1),
+                num(settings.angular_velocity.to_degrees(), 1),
+                MAX_TCP_SPEED,
+                num(settings.angular_velocity.to_degrees(), 1),
+            ),
+        }
+    }
+
+    /// The load of a tool, RAPID rejects tools without mass so tools without a load get 1 kg at the flange.
+    fn load(&self, index: usize, cell: &CellData) -> String {
+        match cell.tool_loads.get(&index) {
+            Some(load) => {
+                let center = load.center_of_mass.coords * cell.unit_length * 1000.0;
+                format!(
+                    "[{}, [{}, {}, {}], [1, 0, 0, 0], 0, 0, 0]",
+                    num(load.mass, 3), num(center.x, 3), num(center.y, 3), num(center.z, 3),
+                )
+            }
+            None => "[1, [0, 0, 0], [1, 0, 0, 0], 0, 0, 0]".to_string(),
+        }
+    }
+
+    fn is_joints(&self, target: &Target, variables: &BTreeMap<String, VariableKind>) -> bool {
+        match target {
+            Target::Named(name) => variables.get(&name.to_ascii_lowercase()) == Some(&VariableKind::Joints),
+            Target::Joints(_) => true,
+            Target::Pose(_) => false,
+        }
+    }
+
+    fn condition(&self, condition: &Condition) -> (String, u8) {
+        match condition {
+            Condition::Input { port, value } => (format!("di{}", port), *value as u8),
+        }
+    }
+}
+
+impl PostProcessor for Rapid {
+    fn name(&self) -> &'static str {
+        "RAPID"
+    }
+
+    fn export(&self, program: &Program, cell: &CellData) -> Result<String, ExportError> {
+        let variables = variable_kinds(program)?;
+        check_frames(program, cell)?;
+
+        let mut body = Lines::new("    ");
+        let mut tool = "tool0".to_string();
+        let mut wobj = "wobj0".to_string();
+        let mut depth = 0;
+
+        body.line("ConfJ \\Off;");
+        body.line("ConfL \\Off;");
+
+        for instruction in &program.instructions {
+            match &instruction.command {
+                Command::Assign { name, target } => {
+                    body.line(format!("{} := {};", name.to_ascii_lowercase(), self.target(target, cell)));
+                }
+                Command::Tool(index) => tool = format!("tool{}", index),
+                Command::Base(index) => wobj = format!("wobj{}", index),
+                Command::Move(motion) => {
+                    let joints = self.is_joints(&motion.target, &variables);
+                    let mut target = self.target(&motion.target, cell);
+                    let speed = self.speed(motion, cell);
+
+                    if joints && motion.kind != MotionKind::Ptp {
+                        target = format!("CalcRobT({}, {} \\WObj:={})", target, tool, wobj);
+                    }
+
+                    match motion.kind {
+                        MotionKind::Ptp if joints => {
+                            body.line(format!("MoveAbsJ {}, {}, fine, {};", target, speed, tool));
+                        }
+                        MotionKind::Ptp => {
+                            body.line(format!("MoveJ {}, {}, fine, {} \\WObj:={};", target, speed, tool, wobj));
+                        }
+                        MotionKind::Lin => {
+                            body.line(format!("MoveL {}, {}, fine, {} \\WObj:={};", target, speed, tool, wobj));
+                        }
+                        MotionKind::Circ => {
+                            let via = auxiliary_point(motion, instruction.line)?;
+                            let mut via_target = self.target(via, cell);
+
+                            if self.is_joints(via, &variables) {
+                                via_target = format!("CalcRobT({}, {} \\WObj:={})", via_target, tool, wobj);
+                            }
+
+                            body.line(format!(
+                                "MoveC {}, {}, {}, fine, {} \\WObj:={};",
+                                via_target, target, speed, tool, wobj
+                            ));
+                        }
+                    }
+                }
+                Command::SetOutput { port, value } => body.line(format!("SetDO do{}, {};", port, *value as u8)),
+                Command::Gripper(closed) => {
+                    let port = gripper_output(cell, instruction.line, self.name())?;
+                    body.line(format!("SetDO do{}, {};", port, *closed as u8));
+                }
+                Command::Wait(condition) => {
+                    let (signal, value) = self.condition(condition);
+                    body.line(format!("WaitDI {}, {};", signal, value));
+                }
+                Command::WaitTime(seconds) => body.line(format!("WaitTime {};", num(*seconds, 3))),
+                Command::Label(label) => body.line(format!("{}:", label)),
+                Command::Goto { label, condition: None } => body.line(format!("GOTO {};", label)),
+                Command::Goto { label, condition: Some(condition) } => {
+                    let (signal, value) = self.condition(condition);
+                    body.line(format!("IF {} = {} GOTO {};", signal, value, label));
+                }
+                Command::Loop { count: Some(count), .. } => {
+                    depth += 1;
+                    body.line(format!("FOR i{} FROM 1 TO {} DO", depth, count));
+                    body.indent();
+                }
+                Command::Loop { count: None, .. } => {
+                    body.line("WHILE TRUE DO");
+                    body.indent();
+                }
+                Command::EndLoop { start } => {
+                    body.dedent();
+
+                    if let Command::Loop { count: Some(_), .. } = program.instructions[*start].command {
+                        depth -= 1;
+                        body.line("ENDFOR");
+                    } else {
+                        body.line("ENDWHILE");
+                    }
+                }
+            }
+        }
+
+        let mut out = Lines::new("    ");
+        out.line(format!("MODULE {}", program.name));
+        out.indent();
+
+        for (index, frame) in &cell.tools {
+            out.line(format!(
+                "PERS tooldata tool{} := [TRUE, {}, {}];",
+                index, self.pose(frame, cell), self.load(*index, cell),
+            ));
+        }
+
+        for (index, frame) in &cell.bases {
+            out.line(format!(
+                "PERS wobjdata wobj{} := [FALSE, TRUE, \"\", {}, [[0, 0, 0], [1, 0, 0, 0]]];",
+                index, self.pose(frame, cell),
+            ));
+        }
+
+        for (name, kind) in &variables {
+            let kind = match kind {
+                VariableKind::Joints => "jointtarget",
+                VariableKind::Pose => "robtarget",
+            };
+            out.line(format!("VAR {} {};", kind, name));
+        }
+
+        out.line("");
+        out.line("PROC main()");
+        out.indent();
+        out.append(body);
+        out.dedent();
+        out.line("ENDPROC");
+        out.dedent();
+        out.line("ENDMODULE");
+
+        Ok(out.finish())
+    }
+}
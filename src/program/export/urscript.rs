@@ -0,0 +1,177 @@
+use nalgebra::{DVector, Isometry3};
+use crate::errors::ExportError;
+use crate::pose::ur_rotvec;
+use crate::program::ast::{Command, Condition, MotionKind, Program, Target};
+use crate::program::export::{auxiliary_point, check_frames, gripper_output, num, ptp_percent, tool_speed, variable_kinds, CellData, Lines, PostProcessor, VariableKind};
+
+
+/// Universal Robots URScript post-processor.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UrScript;
+
+/// Default joint speed of `movej`, in radians per second.
+const JOINT_SPEED: f32 = 1.05;
+const JOINT_ACCELERATION: f32 = 1.4;
+const TOOL_ACCELERATION: f32 = 1.2;
+
+impl UrScript {
+    /// A pose as `p[x, y, z, rx, ry, rz]`, in meters with a rotation vector.
+    fn pose(&self, pose: &Isometry3<f32>, cell: &CellData) -> String {
+        let t = pose.translation.vector * cell.unit_length;
+        let r = ur_rotvec(&pose.rotation);
+
+        format!(
+            "p[{}, {}, {}, {}, {}, {}]",
+            num(t.x, 6), num(t.y, 6), num(t.z, 6), num(r.x, 6), num(r.y, 6), num(r.z, 6),
+        )
+    }
+
+    fn joints(&self, joints: &DVector<f32>) -> String {
+        let values: Vec<_> = joints.iter().map(|q| num(*q, 6)).collect();
+        format!("[{}]", values.join(", "))
+    }
+
+    fn target(&self, target: &Target, cell: &CellData) -> String {
+        match target {
+            Target::Named(name) => name.to_ascii_lowercase(),
+            Target::Joints(joints) => self.joints(joints),
+            Target::Pose(pose) => self.pose(pose, cell),
+        }
+    }
+
+    /// A motion target, moved from the active base to the robot base frame.
+    fn cartesian_target(&self, target: &Target, cell: &CellData, base: usize, is_joints: bool) -> String {
+        let target = self.target(target, cell);
+
+        if base == 0 || is_joints {
+            target
+        } else {
+            format!("pose_trans(base{}, {})", base, target)
+        }
+    }
+
+    fn condition(&self, condition: &Condition) -> String {
+        match condition {
+            Condition::Input { port, value } => {
+                format!("get_standard_digital_in({}) == {}", port, if *value { "True" } else { "False" })
+            }
+        }
+    }
+}
+
+impl PostProcessor for UrScript {
+    fn name(&self) -> &'static str {
+        "URScript"
+    }
+
+    fn export(&self, program: &Program, cell: &CellData) -> Result<String, ExportError> {
+        let variables = variable_kinds(program)?;
+        check_frames(program, cell)?;
+
+        let is_joints = |target: &Target| match target {
+            Target::Named(name) => variables.get(&name.to_ascii_lowercase()) == Some(&VariableKind::Joints),
+            Target::Joints(_) => true,
+            Target::Pose(_) => false,
+        };
+
+        let mut out = Lines::new("  ");
+        let mut base = 0;
+        let mut depth = 0;
+
+        out.line(format!("def {}():", program.name));
+        out.indent();
+
+        for (index, frame) in &cell.tools {
+            out.line(format!("tool{} = {}", index, self.pose(frame, cell)));
+        }
+
+        for (index, frame) in &cell.bases {
+            out.line(format!("base{} = {}", index, self.pose(frame, cell)));
+        }
+
+        for instruction in &program.instructions {
+            let line = instruction.line;
+
+            match &instruction.command {
+                Command::Assign { name, target } => {
+                    out.line(format!("{} = {}", name.to_ascii_lowercase(), self.target(target, cell)));
+                }
+                Command::Tool(0) => out.line("set_tcp(p[0, 0, 0, 0, 0, 0])"),
+                Command::Tool(tool) => out.line(format!("set_tcp(tool{})", tool)),
+                Command::Base(index) => base = *index,
+                Command::Move(motion) => {
+                    let target = self.cartesian_target(&motion.target, cell, base, is_joints(&motion.target));
+
+                    match motion.kind {
+                        MotionKind::Ptp => {
+                            let percent = ptp_percent(motion);
+                            out.line(format!(
+                                "movej({}, a={}, v={})",
+                                target, num(JOINT_ACCELERATION, 2), num(JOINT_SPEED * percent / 100.0, 4),
+                            ));
+                        }
+                        MotionKind::Lin => {
+                            let velocity = tool_speed(motion, cell);
+                            out.line(format!(
+                                "movel({}, a={}, v={})",
+                                target, num(TOOL_ACCELERATION, 2), num(velocity, 4),
+                            ));
+                        }
+                        MotionKind::Circ => {
+                            let via = auxiliary_point(motion, line)?;
+                            let via = self.cartesian_target(via, cell, base, is_joints(via));
+                            let velocity = tool_speed(motion, cell);
+                            out.line(format!(
+                                "movec({}, {}, a={}, v={})",
+                                via, target, num(TOOL_ACCELERATION, 2), num(velocity, 4),
+                            ));
+                        }
+                    }
+                }
+                Command::SetOutput { port, value } => {
+                    out.line(format!("set_standard_digital_out({}, {})", port, if *value { "True" } else { "False" }));
+                }
+                Command::Gripper(closed) => {
+                    let port = gripper_output(cell, instruction.line, self.name())?;
+                    out.line(format!("set_standard_digital_out({}, {})", port, if *closed { "True" } else { "False" }));
+                }
+                Command::Wait(condition) => {
+                    out.line(format!("while not ({}):", self.condition(condition)));
+                    out.indent();
+                    out.line("sync()");
+                    out.dedent();
+                    out.line("end");
+                }
+                Command::WaitTime(seconds) => out.line(format!("sleep({})", num(*seconds, 3))),
+                Command::Label(label) => out.line(format!("# {}:", label)),
+                Command::Goto { .. } => {
+                    return Err(ExportError::Unsupported { line, language: self.name(), feature: "GOTO" });
+                }
+                Command::Loop { count: Some(count), .. } => {
+                    depth += 1;
+                    out.line(format!("i{} = 0", depth));
+                    out.line(format!("while i{} < {}:", depth, count));
+                    out.indent();
+                    out.line(format!("i{0} = i{0} + 1", depth));
+                }
+                Command::Loop { count: None, .. } => {
+                    out.line("while True:");
+                    out.indent();
+                }
+                Command::EndLoop { start } => {
+                    out.dedent();
+                    out.line("end");
+
+                    if let Command::Loop { count: Some(_), .. } = program.instructions[*start].command {
+                        depth -= 1;
+                    }
+                }
+            }
+        }
+
+        out.dedent();
+        out.line("end");
+
+        Ok(out.finish())
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashSet};
+use nalgebra::Isometry3;
+use crate::errors::ParseError;
+use crate::program::ast::{Command, Instruction, Program, Target};
+use crate::program::export::CellData;
+
+mod krl;
+mod rapid;
+mod urscript;
+
+
+/// A vendor program translated to bluster.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Imported {
+    pub program: Program,
+    /// Tool and base frames defined by the imported program.
+    pub cell: CellData,
+    /// Statements that were skipped because they have no bluster equivalent.
+    pub warnings: Vec<ParseError>,
+}
+
+/// Translates programs written in a vendor robot language to bluster.
+pub trait Importer {
+    /// Parses `source`, converting its lengths to scene units of `unit_length` meters.
+    fn import(&self, name: &str, source: &str, unit_length: f32) -> Result<Imported, ParseError>;
+}
+
+/// Collects imported commands and resolves the structure of the program.
+pub(crate) struct ProgramBuilder {
+    name: String,
+    /// Variable definitions, hoisted before the executable statements.
+    prelude: Vec<Instruction>,
+    body: Vec<Instruction>,
+    open_loops: Vec<usize>,
+    pub cell: CellData,
+    pub warnings: Vec<ParseError>,
+}
+
+impl ProgramBuilder {
+    pub fn new(name: &str, unit_length: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            prelude: Vec::new(),
+            body: Vec::new(),
+            open_loops: Vec::new(),
+            cell: CellData {
+                unit_length,
+                ..CellData::default()
+            },
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn define(&mut self, command: Command, line: usize) {
+        self.prelude.push(Instruction { command, line });
+    }
+
+    pub fn push(&mut self, command: Command, line: usize) {
+        self.body.push(Instruction { command, line });
+    }
+
+    pub fn open_loop(&mut self, count: Option<u32>, line: usize) {
+        self.open_loops.push(self.body.len());
+        self.push(Command::Loop { count, end: 0 }, line);
+    }
+
+    pub fn close_loop(&mut self, line: usize, column: usize) -> Result<(), ParseError> {
+        let start = self.open_loops.pop()
+            .ok_or_else(|| ParseError::new(line, column, "end of loop without matching start"))?;
+        self.push(Command::EndLoop { start }, line);
+
+        Ok(())
+    }
+
+    pub fn warn(&mut self, line: usize, column: usize, message: impl Into<String>) {
+        self.warnings.push(ParseError::new(line, column, message));
+    }
+
+    /// Builds the program. Definitions of variables that no motion refers to, directly or through other variables,
+    /// such as frames, are dropped.
+    pub fn finish(self) -> Result<Imported, ParseError> {
+        if let Some(start) = self.open_loops.last() {
+            return Err(ParseError::new(self.body[*start].line, 1, "loop without matching end"));
+        }
+
+        let mut used = HashSet::new();
+        let mut pending = Vec::new();
+
+        for instruction in &self.body {
+            if let Command::Move(motion) = &instruction.command {
+                for target in std::iter::once(&motion.target).chain(motion.via.as_ref()) {
+                    if let Target::Named(name) = target {
+                        pending.push(name.to_ascii_lowercase());
+                    }
+                }
+            }
+        }
+
+        // Variables assigned from other variables keep those in use too
+        while let Some(name) = pending.pop() {
+            if !used.insert(name.clone()) {
+                continue;
+            }
+
+            for instruction in self.prelude.iter().chain(&self.body) {
+                if let Command::Assign { name: assigned, target: Target::Named(source) } = &instruction.command {
+                    if assigned.eq_ignore_ascii_case(&name) {
+                        pending.push(source.to_ascii_lowercase());
+                    }
+                }
+            }
+        }
+
+        let is_used = |instruction: &Instruction| match &instruction.command {
+            Command::Assign { name, .. } => used.contains(&name.to_ascii_lowercase()),
+            _ => true,
+        };
+
+        let mut program = Program::new(self.name);
+        program.instructions = self.prelude.into_iter().filter(is_used).collect();
+        // Body indices of kept instructions, for loop starts
+        let mut indices = Vec::with_capacity(self.body.len());
+
+        for mut instruction in self.body {
+            indices.push(program.instructions.len());
+
+            if !is_used(&instruction) {
+                continue;
+            }
+
+            if let Command::EndLoop { start } = &mut instruction.command {
+                *start = indices[*start];
+                let end = program.instructions.len();
+
+                if let Command::Loop { end: loop_end, .. } = &mut program.instructions[*start].command {
+                    *loop_end = end;
+                }
+            }
+
+            program.instructions.push(instruction);
+        }
+
+        for instruction in &program.instructions {
+            if let Command::Goto { label, .. } = &instruction.command {
+                if program.label(label).is_none() {
+                    return Err(ParseError::new(instruction.line, 1, format!("unknown label `{}`", label)));
+                }
+            }
+        }
+
+        Ok(Imported {
+            program,
+            cell: self.cell,
+            warnings: self.warnings,
+        })
+    }
+}
+
+/// The number of a new frame called `name`: its trailing digits if that number is free, or else the next after the last.
+pub(crate) fn frame_number(frames: &BTreeMap<usize, Isometry3<f32>>, name: &str) -> usize {
+    let digits = name.trim_start_matches(|c: char| !c.is_ascii_digit());
+
+    match digits.parse() {
+        Ok(number) if number > 0 && !frames.contains_key(&number) => number,
+        _ => frames.keys().last().map(|i| i + 1).unwrap_or(1),
+    }
+}
+
+/// Splits `text` at every `separator` that isn't nested in brackets, braces, parentheses or quotes.
+pub(crate) fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quoted = false;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '{' | '(' if !quoted => depth += 1,
+            ']' | '}' | ')' if !quoted => depth -= 1,
+            c if c == separator && depth == 0 && !quoted => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&text[start..]);
+    parts
+}
+
+/// The 1-based column of `part` within `line`, `part` being a slice of `line`.
+pub(crate) fn column(line: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize).saturating_sub(line.as_ptr() as usize);
+    line[..offset.min(line.len())].chars().count() + 1
+}
+
+pub(crate) fn number(line: usize, text: &str, part: &str) -> Result<f32, ParseError> {
+    part.trim().parse::<f32>().map_err(|_| {
+        ParseError::new(line, column(text, part.trim_start()), format!("expected a number, found `{}`", part.trim()))
+    })
+}
+
+/// Parses a bracketed list of numbers such as `[1, 2, 3]`.
+pub(crate) fn numbers(line: usize, text: &str, part: &str) -> Result<Vec<f32>, ParseError> {
+    let inner = strip_brackets(part, '[', ']')
+        .ok_or_else(|| ParseError::new(line, column(text, part.trim_start()), "expected `[...]`"))?;
+
+    split_top_level(inner, ',')
+        .into_iter()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| number(line, text, p))
+        .collect()
+}
+
+/// The content between `open` and `close` if `part` is enclosed in them.
+pub(crate) fn strip_brackets(part: &str, open: char, close: char) -> Option<&str> {
+    part.trim().strip_prefix(open)?.strip_suffix(close)
+}
+
+/// Whether `text` starts with the keyword `keyword`, ignoring case.
+pub(crate) fn starts_with_keyword(text: &str, keyword: &str) -> bool {
+    match text.get(..keyword.len()) {
+        Some(head) if head.eq_ignore_ascii_case(keyword) => {
+            !text[keyword.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// The remainder of `text` after the keyword `keyword`, if `text` starts with it.
+pub(crate) fn after_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    starts_with_keyword(text, keyword).then(|| text[keyword.len()..].trim())
+}
+
+pub(crate) fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
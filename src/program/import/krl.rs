@@ -0,0 +1,363 @@
+use nalgebra::{DVector, Isometry3};
+use crate::errors::ParseError;
+use crate::pose::kuka_frame;
+use crate::program::ast::{Command, Condition, Motion, MotionKind, Target};
+use crate::program::export::Krl;
+use crate::program::import::{after_keyword, column, is_identifier, number, split_top_level, starts_with_keyword, strip_brackets, Imported, Importer, ProgramBuilder};
+
+
+const POSE_TYPES: [&str; 3] = ["E6POS", "POS", "FRAME"];
+const AXIS_TYPES: [&str; 2] = ["E6AXIS", "AXIS"];
+const DATA_TYPES: [&str; 12] = [
+    "INT", "REAL", "BOOL", "CHAR", "SIGNAL", "PDAT", "FDAT", "LDAT", "LOAD", "E6POS", "POS", "FRAME",
+];
+/// Control structures that can't be translated, with their closing keyword.
+const BLOCKS: [(&str, &str); 4] = [("IF", "ENDIF"), ("WHILE", "ENDWHILE"), ("SWITCH", "ENDSWITCH"), ("REPEAT", "UNTIL")];
+
+/// The non-empty lines of `source` without comments, as `(line number, raw line, code)`.
+fn lines(source: &str) -> Vec<(usize, &str, &str)> {
+    source.lines()
+        .enumerate()
+        .filter_map(|(i, raw)| {
+            let code = raw.split(';').next().unwrap_or("").trim();
+            (!code.is_empty() && !code.starts_with('&')).then(|| (i + 1, raw, code))
+        })
+        .collect()
+}
+
+struct State {
+    scale: f32,
+    ptp_velocity: Option<f32>,
+    cp_velocity: Option<f32>,
+}
+
+impl Krl {
+    /// Parses an aggregate such as `{X 10, Y 20, Z 0}` into its named numeric components.
+    fn parse_aggregate(&self, line: usize, text: &str, part: &str) -> Result<Vec<(String, f32)>, ParseError> {
+        let inner = strip_brackets(part, '{', '}')
+            .ok_or_else(|| ParseError::new(line, column(text, part.trim_start()), "expected an aggregate `{...}`"))?;
+        let inner = match inner.split_once(':') {
+            Some((kind, rest)) if is_identifier(kind.trim()) => rest,
+            _ => inner,
+        };
+
+        let mut values = Vec::new();
+
+        for component in split_top_level(inner, ',') {
+            let component = component.trim();
+
+            if let Some((key, value)) = component.split_once(char::is_whitespace) {
+                if let Ok(value) = value.trim().parse::<f32>() {
+                    values.push((key.to_ascii_uppercase(), value));
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn frame_from(&self, values: &[(String, f32)], scale: f32) -> Isometry3<f32> {
+        let get = |key: &str| values.iter().find(|(k, _)| k == key).map(|(_, v)| *v).unwrap_or(0.0);
+
+        kuka_frame(get("X") * scale, get("Y") * scale, get("Z") * scale, get("A"), get("B"), get("C"))
+    }
+
+    fn parse_target(&self, line: usize, text: &str, part: &str, scale: f32) -> Result<Target, ParseError> {
+        let part = part.trim();
+
+        if is_identifier(part) {
+            return Ok(Target::Named(part.to_string()));
+        }
+
+        let values = self.parse_aggregate(line, text, part)?;
+
+        if values.iter().any(|(k, _)| k == "A1") {
+            let joints = (1..=6).map(|i| {
+                let key = format!("A{}", i);
+                values.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_radians()).unwrap_or(0.0)
+            });
+
+            Ok(Target::Joints(DVector::from_iterator(6, joints)))
+        } else {
+            Ok(Target::Pose(self.frame_from(&values, scale)))
+        }
+    }
+
+    fn parse_condition(&self, line: usize, text: &str, part: &str) -> Result<Condition, ParseError> {
+        let part = part.trim();
+        let (negated, part) = match after_keyword(part, "NOT") {
+            Some(rest) => (true, rest),
+            None => (false, part),
+        };
+        let (signal, value) = match part.split_once("==") {
+            Some((signal, value)) => {
+                let value = value.trim();
+                let value = if value.eq_ignore_ascii_case("TRUE") {
+                    true
+                } else if value.eq_ignore_ascii_case("FALSE") {
+                    false
+                } else {
+                    return Err(ParseError::new(line, column(text, value), "expected `TRUE` or `FALSE`"));
+                };
+                (signal.trim(), value)
+            }
+            None => (part, true),
+        };
+        let port = signal.strip_prefix("$IN[")
+            .or_else(|| signal.strip_prefix("$in["))
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or_else(|| ParseError::new(line, column(text, signal), "expected an input `$IN[...]`"))?;
+        let port = number(line, text, port)? as usize;
+
+        Ok(Condition::Input { port, value: value != negated })
+    }
+
+    fn parse_motion(&self, kind: MotionKind, line: usize, text: &str, args: &str, state: &State) -> Result<Command, ParseError> {
+        let approximation = |part: &str| {
+            let part = part.trim().to_ascii_uppercase();
+            part.starts_with("C_") || part.starts_with("CA ")
+        };
+        let (via, target) = if kind == MotionKind::Circ {
+            let parts: Vec<_> = split_top_level(args, ',').into_iter()
+                .filter(|p| !approximation(p))
+                .collect();
+
+            if parts.len() < 2 {
+                return Err(ParseError::new(line, column(text, args), "`CIRC` expects an auxiliary and an end point"));
+            }
+
+            let end = split_top_level(parts[1].trim(), ' ')[0];
+            (Some(self.parse_target(line, text, parts[0], state.scale)?), self.parse_target(line, text, end, state.scale)?)
+        } else {
+            let target = split_top_level(args, ' ')[0];
+            (None, self.parse_target(line, text, target, state.scale)?)
+        };
+        let velocity = match kind {
+            MotionKind::Ptp => state.ptp_velocity,
+            _ => state.cp_velocity,
+        };
+
+        Ok(Command::Move(Motion { kind, target, via, velocity }))
+    }
+
+    fn parse_declaration(&self, builder: &mut ProgramBuilder, line: usize, text: &str, decl: &str, scale: f32) -> Result<(), ParseError> {
+        let decl = after_keyword(decl, "GLOBAL").unwrap_or(decl);
+        let (kind, rest) = match decl.split_once(char::is_whitespace) {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        let kind = kind.to_ascii_uppercase();
+
+        if !POSE_TYPES.contains(&kind.as_str()) && !AXIS_TYPES.contains(&kind.as_str()) {
+            return Ok(());
+        }
+
+        if let Some((name, value)) = rest.split_once('=') {
+            let name = name.trim();
+            let target = self.parse_target(line, text, value, scale)?;
+            builder.define(Command::Assign { name: name.to_string(), target }, line);
+        }
+
+        Ok(())
+    }
+
+    fn parse_assignment(&self, builder: &mut ProgramBuilder, line: usize, text: &str, lhs: &str, rhs: &str, state: &mut State) -> Result<(), ParseError> {
+        let target = lhs.trim().to_ascii_uppercase();
+        let rhs = rhs.trim();
+        let indexed = |prefix: &str| -> Option<&str> {
+            rhs.get(..prefix.len())
+                .filter(|head| head.eq_ignore_ascii_case(prefix))
+                .and_then(|_| rhs[prefix.len()..].strip_suffix(']'))
+        };
+
+        match target.as_str() {
+            "$VEL.CP" => state.cp_velocity = Some(number(line, text, rhs)? * state.scale * 1000.0),
+            "$TOOL" | "$BASE" => {
+                let data = if target == "$TOOL" { "TOOL_DATA[" } else { "BASE_DATA[" };
+                let index = if rhs.eq_ignore_ascii_case("$NULLFRAME") {
+                    0
+                } else {
+                    match indexed(data) {
+                        Some(index) => number(line, text, index)? as usize,
+                        None => {
+                            builder.warn(line, column(text, rhs), format!("ignored `{}` assignment", lhs.trim()));
+                            return Ok(());
+                        }
+                    }
+                };
+
+                builder.push(if target == "$TOOL" { Command::Tool(index) } else { Command::Base(index) }, line);
+            }
+            _ if target.starts_with("TOOL_DATA[") || target.starts_with("BASE_DATA[") => {
+                let index = lhs.trim()
+                    .get(10..)
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .ok_or_else(|| ParseError::new(line, column(text, lhs.trim_start()), format!("expected `{}<index>]`", &target[..10])))?;
+                let index = number(line, text, index)? as usize;
+                let frame = self.frame_from(&self.parse_aggregate(line, text, rhs)?, state.scale);
+
+                if target.starts_with("TOOL") {
+                    builder.cell.tools.insert(index, frame);
+                } else {
+                    builder.cell.bases.insert(index, frame);
+                }
+            }
+            _ if target.starts_with("$OUT[") => {
+                let port = lhs.trim()
+                    .get(5..)
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .ok_or_else(|| ParseError::new(line, column(text, lhs.trim_start()), "expected `$OUT[<port>]`"))?;
+                let port = number(line, text, port)? as usize;
+                let value = if rhs.eq_ignore_ascii_case("TRUE") {
+                    true
+                } else if rhs.eq_ignore_ascii_case("FALSE") {
+                    false
+                } else {
+                    return Err(ParseError::new(line, column(text, rhs), "expected `TRUE` or `FALSE`"));
+                };
+
+                builder.push(Command::SetOutput { port, value }, line);
+            }
+            // Copies of other variables only matter when a motion uses them, the builder drops the rest
+            _ if is_identifier(lhs.trim()) && (rhs.starts_with('{') || is_identifier(rhs)) => {
+                let target = self.parse_target(line, text, rhs, state.scale)?;
+                builder.push(Command::Assign { name: lhs.trim().to_string(), target }, line);
+            }
+            _ => builder.warn(line, column(text, lhs.trim_start()), format!("ignored `{}` assignment", lhs.trim())),
+        }
+
+        Ok(())
+    }
+
+    /// Skips a control structure starting at `lines[i]`, returning the index of its last line.
+    fn skip_block(&self, lines: &[(usize, &str, &str)], i: usize, open: &str, close: &str) -> usize {
+        let mut depth = 0;
+
+        for (j, (_, _, code)) in lines.iter().enumerate().skip(i) {
+            if starts_with_keyword(code, open) {
+                depth += 1;
+            } else if starts_with_keyword(code, close) {
+                depth -= 1;
+
+                if depth == 0 {
+                    return j;
+                }
+            }
+        }
+
+        lines.len() - 1
+    }
+}
+
+impl Importer for Krl {
+    fn import(&self, name: &str, source: &str, unit_length: f32) -> Result<Imported, ParseError> {
+        let lines = &lines(source);
+        let mut builder = ProgramBuilder::new(name, unit_length);
+        let mut state = State {
+            scale: 1.0 / (1000.0 * unit_length),
+            ptp_velocity: None,
+            cp_velocity: None,
+        };
+        let mut i = 0;
+
+        while i < lines.len() {
+            let (line, text, code) = lines[i];
+            i += 1;
+
+            if ["DEF", "DEFDAT", "DEFFCT", "END", "ENDDAT", "ENDFCT", "EXT", "EXTFCT", "INI"].iter()
+                .any(|k| starts_with_keyword(code, k))
+                || (starts_with_keyword(code, "GLOBAL") && !starts_with_keyword(code, "GLOBAL DECL"))
+            {
+                continue;
+            }
+
+            if let Some(decl) = after_keyword(code, "DECL") {
+                self.parse_declaration(&mut builder, line, text, decl, state.scale)?;
+            } else if DATA_TYPES.iter().chain(AXIS_TYPES.iter()).any(|k| starts_with_keyword(code, k)) {
+                self.parse_declaration(&mut builder, line, text, code, state.scale)?;
+            } else if let Some(args) = after_keyword(code, "BAS") {
+                let args = strip_brackets(args, '(', ')').unwrap_or(args);
+                let parts = split_top_level(args, ',');
+
+                if parts.len() == 2 {
+                    let value = number(line, text, parts[1])?;
+
+                    match parts[0].trim().to_ascii_uppercase().as_str() {
+                        "#VEL_PTP" | "#PTP_PARAMS" => state.ptp_velocity = Some(value),
+                        "#VEL_CP" | "#CP_PARAMS" => state.cp_velocity = Some(value * state.scale * 1000.0),
+                        _ => {}
+                    }
+                }
+            } else if let Some(args) = after_keyword(code, "PTP") {
+                builder.push(self.parse_motion(MotionKind::Ptp, line, text, args, &state)?, line);
+            } else if let Some(args) = after_keyword(code, "LIN") {
+                builder.push(self.parse_motion(MotionKind::Lin, line, text, args, &state)?, line);
+            } else if let Some(args) = after_keyword(code, "CIRC") {
+                builder.push(self.parse_motion(MotionKind::Circ, line, text, args, &state)?, line);
+            } else if let Some(args) = after_keyword(code, "WAIT") {
+                if let Some(seconds) = after_keyword(args, "SEC") {
+                    builder.push(Command::WaitTime(number(line, text, seconds)?), line);
+                } else if let Some(condition) = after_keyword(args, "FOR") {
+                    builder.push(Command::Wait(self.parse_condition(line, text, condition)?), line);
+                } else {
+                    return Err(ParseError::new(line, column(text, args), "expected `SEC` or `FOR`"));
+                }
+            } else if starts_with_keyword(code, "LOOP") {
+                builder.open_loop(None, line);
+            } else if starts_with_keyword(code, "ENDLOOP") || starts_with_keyword(code, "ENDFOR") {
+                builder.close_loop(line, column(text, code))?;
+            } else if let Some(header) = after_keyword(code, "FOR") {
+                let range = header.split_once('=').map(|(_, r)| r).unwrap_or(header);
+                let bounds: Vec<_> = range.split_whitespace().collect();
+
+                let has_step = bounds.len() >= 5 && bounds[3].eq_ignore_ascii_case("STEP");
+
+                if bounds.len() < 3 || !bounds[1].eq_ignore_ascii_case("TO") || (bounds.len() > 3 && !has_step) {
+                    return Err(ParseError::new(line, column(text, header), "expected `FOR i = <start> TO <end> [STEP <step>]`"));
+                }
+
+                let start = number(line, text, bounds[0])?;
+                let end = number(line, text, bounds[2])?;
+                let step = if has_step { number(line, text, bounds[4])? } else { 1.0 };
+
+                if step == 0.0 {
+                    return Err(ParseError::new(line, column(text, bounds[4]), "`STEP` must not be zero"));
+                }
+
+                // The counter isn't visible to the program, only the number of iterations matters
+                let count = ((end - start) / step).floor() + 1.0;
+                builder.open_loop(Some(count.max(0.0) as u32), line);
+            } else if let Some(label) = after_keyword(code, "GOTO") {
+                builder.push(Command::Goto { label: label.to_string(), condition: None }, line);
+            } else if let Some(condition) = after_keyword(code, "IF") {
+                let goto = condition.strip_suffix("THEN")
+                    .or_else(|| condition.strip_suffix("then"))
+                    .filter(|_| i + 1 < lines.len() && starts_with_keyword(lines[i + 1].2, "ENDIF"))
+                    .and_then(|condition| Some((condition, after_keyword(lines[i].2, "GOTO")?)));
+
+                match goto {
+                    Some((condition, label)) => {
+                        let condition = self.parse_condition(line, text, condition)?;
+                        builder.push(Command::Goto { label: label.to_string(), condition: Some(condition) }, line);
+                        i += 2;
+                    }
+                    None => {
+                        builder.warn(line, column(text, code), "ignored `IF` block");
+                        i = self.skip_block(lines, i - 1, "IF", "ENDIF") + 1;
+                    }
+                }
+            } else if let Some((open, close)) = BLOCKS.iter().find(|(open, _)| starts_with_keyword(code, open)) {
+                builder.warn(line, column(text, code), format!("ignored `{}` block", open));
+                i = self.skip_block(lines, i - 1, open, close) + 1;
+            } else if let Some(label) = code.strip_suffix(':').filter(|l| is_identifier(l.trim())) {
+                builder.push(Command::Label(label.trim().to_string()), line);
+            } else if let Some((lhs, rhs)) = code.split_once('=').filter(|(_, rhs)| !rhs.starts_with('=')) {
+                self.parse_assignment(&mut builder, line, text, lhs, rhs, &mut state)?;
+            } else {
+                builder.warn(line, column(text, code), format!("ignored statement `{}`", code));
+            }
+        }
+
+        builder.finish()
+    }
+}
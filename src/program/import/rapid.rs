@@ -0,0 +1,431 @@
+use std::collections::{HashMap, HashSet};
+use nalgebra::{DVector, Isometry3, Translation3, Vector3};
+use crate::errors::ParseError;
+use crate::pose::from_abb_quaternion;
+use crate::program::ast::{Command, Condition, Motion, MotionKind, Target};
+use crate::program::export::Rapid;
+use crate::program::import::{column, frame_number, is_identifier, number, numbers, split_top_level, starts_with_keyword, strip_brackets, Imported, Importer, ProgramBuilder};
+
+
+/// A statement of the source, with its line and the text it was read from.
+struct Statement<'a> {
+    line: usize,
+    text: &'a str,
+    code: &'a str,
+}
+
+/// Splits `source` into statements at `;` and line breaks, without `!` comments.
+fn statements(source: &str) -> Vec<Statement<'_>> {
+    let mut statements = Vec::new();
+
+    for (i, text) in source.lines().enumerate() {
+        let code = match text.find('!') {
+            Some(comment) if !text[..comment].contains('"') => &text[..comment],
+            _ => text,
+        };
+
+        for code in split_top_level(code, ';') {
+            let code = code.trim();
+
+            if !code.is_empty() {
+                statements.push(Statement { line: i + 1, text, code });
+            }
+        }
+    }
+
+    statements
+}
+
+struct State {
+    scale: f32,
+    tools: HashMap<String, usize>,
+    wobjs: HashMap<String, usize>,
+    signals: HashMap<String, usize>,
+    targets: HashMap<String, Target>,
+    /// Variables declared as `jointtarget`.
+    joint_targets: HashSet<String>,
+    tool: usize,
+    wobj: usize,
+}
+
+impl State {
+    /// The port of a signal, taken from its trailing digits or else numbered in order of appearance.
+    fn port(&mut self, signal: &str) -> usize {
+        let signal = signal.trim().to_ascii_lowercase();
+        let next = self.signals.len() + 1;
+        let digits = signal.trim_start_matches(|c: char| !c.is_ascii_digit());
+
+        *self.signals.entry(signal.clone())
+            .or_insert_with(|| digits.parse().unwrap_or(next))
+    }
+}
+
+impl Rapid {
+    /// Parses a `[[x, y, z], [q1, q2, q3, q4]]` pose, positions in millimeters.
+    fn parse_pose(&self, line: usize, text: &str, part: &str, scale: f32) -> Result<Isometry3<f32>, ParseError> {
+        let inner = strip_brackets(part, '[', ']')
+            .ok_or_else(|| ParseError::new(line, column(text, part.trim_start()), "expected `[...]`"))?;
+        let parts = split_top_level(inner, ',');
+
+        if parts.len() < 2 {
+            return Err(ParseError::new(line, column(text, part.trim_start()), "expected a position and an orientation"));
+        }
+
+        let position = numbers(line, text, parts[0])?;
+        let orientation = numbers(line, text, parts[1])?;
+
+        if position.len() != 3 || orientation.len() != 4 {
+            return Err(ParseError::new(line, column(text, part.trim_start()), "malformed pose"));
+        }
+
+        let rotation = from_abb_quaternion([orientation[0], orientation[1], orientation[2], orientation[3]]);
+        let translation = Vector3::new(position[0], position[1], position[2]) * scale;
+
+        Ok(Isometry3::from_parts(translation.into(), rotation))
+    }
+
+    fn parse_robtarget(&self, line: usize, text: &str, part: &str, scale: f32) -> Result<Target, ParseError> {
+        let inner = strip_brackets(part, '[', ']')
+            .ok_or_else(|| ParseError::new(line, column(text, part.trim_start()), "expected `[...]`"))?;
+        let parts = split_top_level(inner, ',');
+
+        if parts.len() < 2 {
+            return Err(ParseError::new(line, column(text, part.trim_start()), "malformed robtarget"));
+        }
+
+        let pose = format!("[{}, {}]", parts[0].trim(), parts[1].trim());
+        // The rebuilt text has no position in the source line, report errors at the start of the target.
+        self.parse_pose(line, text, &pose, scale)
+            .map(Target::Pose)
+            .map_err(|_| ParseError::new(line, column(text, part.trim_start()), "malformed robtarget"))
+    }
+
+    fn parse_jointtarget(&self, line: usize, text: &str, part: &str) -> Result<Target, ParseError> {
+        let inner = strip_brackets(part, '[', ']')
+            .ok_or_else(|| ParseError::new(line, column(text, part.trim_start()), "expected `[...]`"))?;
+        let parts = split_top_level(inner, ',');
+        let mut joints = numbers(line, text, parts[0])?;
+
+        if let Some(external) = parts.get(1) {
+            joints.extend(numbers(line, text, external)?.into_iter().take_while(|v| *v < 9e8));
+        }
+
+        Ok(Target::Joints(DVector::from_iterator(joints.len(), joints.into_iter().map(f32::to_radians))))
+    }
+
+    /// A target: a variable, a literal `jointtarget` or `robtarget`, or an `Offs`/`RelTool` of a known robtarget.
+    fn parse_target(&self, line: usize, text: &str, part: &str, joints: bool, state: &State) -> Result<Target, ParseError> {
+        let part = part.trim();
+
+        if is_identifier(part) {
+            return Ok(Target::Named(part.to_string()));
+        }
+
+        if part.starts_with('[') {
+            return if joints {
+                self.parse_jointtarget(line, text, part)
+            } else {
+                self.parse_robtarget(line, text, part, state.scale)
+            };
+        }
+
+        let (function, args) = part.split_once('(')
+            .and_then(|(function, args)| Some((function.trim(), args.strip_suffix(')')?)))
+            .ok_or_else(|| ParseError::new(line, column(text, part), format!("unsupported target `{}`", part)))?;
+        let args = split_top_level(args, ',');
+        let base = match state.targets.get(&args[0].trim().to_ascii_lowercase()) {
+            Some(Target::Pose(pose)) => *pose,
+            _ => return Err(ParseError::new(line, column(text, args[0].trim_start()), "expected a known robtarget")),
+        };
+        let offset = |i: usize| args.get(i).map(|a| number(line, text, a)).unwrap_or(Ok(0.0));
+        let translation = Vector3::new(offset(1)?, offset(2)?, offset(3)?) * state.scale;
+
+        if function.eq_ignore_ascii_case("Offs") {
+            Ok(Target::Pose(Translation3::from(translation) * base))
+        } else if function.eq_ignore_ascii_case("RelTool") {
+            Ok(Target::Pose(base * Translation3::from(translation)))
+        } else {
+            Err(ParseError::new(line, column(text, function), format!("unsupported function `{}`", function)))
+        }
+    }
+
+    /// The velocity of a `speeddata`: `vN` or an inline `[v_tcp, v_ori, v_leax, v_reax]`.
+    fn parse_speed(&self, line: usize, text: &str, part: &str, kind: MotionKind, state: &State) -> Result<Option<f32>, ParseError> {
+        let part = part.trim();
+        let tcp = if let Some(values) = strip_brackets(part, '[', ']') {
+            number(line, text, split_top_level(values, ',')[0])?
+        } else if let Some(value) = part.strip_prefix('v').filter(|v| v.parse::<f32>().is_ok()) {
+            number(line, text, value)?
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(match kind {
+            MotionKind::Ptp => (tcp / 10.0).min(100.0),
+            _ => tcp * state.scale,
+        }))
+    }
+
+    fn parse_motion(&self, builder: &mut ProgramBuilder, statement: &Statement, instruction: &str, args: &str, state: &mut State) -> Result<(), ParseError> {
+        let Statement { line, text, .. } = *statement;
+        let (kind, arity) = match instruction.to_ascii_lowercase().as_str() {
+            "moveabsj" | "movej" => (MotionKind::Ptp, 1),
+            "movel" => (MotionKind::Lin, 1),
+            _ => (MotionKind::Circ, 2),
+        };
+        // Optional arguments such as `\NoEOffs` follow the one they apply to
+        let parts: Vec<_> = split_top_level(args, ',').into_iter()
+            .map(|part| split_top_level(part, '\\')[0])
+            .collect();
+
+        if parts.len() < arity + 3 {
+            return Err(ParseError::new(line, column(text, args), format!("`{}` expects more arguments", instruction)));
+        }
+
+        let joints = instruction.eq_ignore_ascii_case("MoveAbsJ");
+        let via = if arity == 2 { Some(self.parse_target(line, text, parts[0], joints, state)?) } else { None };
+        let target = self.parse_target(line, text, parts[arity - 1], joints, state)?;
+        let velocity = self.parse_speed(line, text, parts[arity], kind, state)?;
+        let tool = parts[arity + 2].trim().to_ascii_lowercase();
+        let wobj = split_top_level(args, ',').into_iter()
+            .skip(arity + 2)
+            .flat_map(|part| split_top_level(part, '\\'))
+            .find_map(|option| option.trim().strip_prefix("WObj:=").or_else(|| option.trim().strip_prefix("wobj:=")))
+            .map(|wobj| wobj.trim().to_ascii_lowercase())
+            .unwrap_or_else(|| "wobj0".to_string());
+
+        let tool = match state.tools.get(&tool) {
+            Some(index) => *index,
+            None => return Err(ParseError::new(line, column(text, parts[arity + 2].trim_start()), format!("unknown tool `{}`", tool))),
+        };
+        let wobj = match state.wobjs.get(&wobj) {
+            Some(index) => *index,
+            None => return Err(ParseError::new(line, column(text, args), format!("unknown work object `{}`", wobj))),
+        };
+
+        if tool != state.tool {
+            builder.push(Command::Tool(tool), line);
+            state.tool = tool;
+        }
+
+        if wobj != state.wobj {
+            builder.push(Command::Base(wobj), line);
+            state.wobj = wobj;
+        }
+
+        builder.push(Command::Move(Motion { kind, target, via, velocity }), line);
+
+        Ok(())
+    }
+
+    fn parse_declaration(&self, builder: &mut ProgramBuilder, statement: &Statement, decl: &str, state: &mut State) -> Result<(), ParseError> {
+        let Statement { line, text, .. } = *statement;
+        let (kind, rest) = match decl.split_once(char::is_whitespace) {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        let (name, value) = match rest.split_once(":=") {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (rest.trim(), None),
+        };
+        let key = name.to_ascii_lowercase();
+
+        match kind.to_ascii_lowercase().as_str() {
+            "robtarget" | "jointtarget" => {
+                let joints = kind.eq_ignore_ascii_case("jointtarget");
+
+                if joints {
+                    state.joint_targets.insert(key.clone());
+                }
+
+                let value = match value {
+                    Some(value) => value,
+                    None => return Ok(()),
+                };
+                let target = self.parse_target(line, text, value, joints, state)?;
+
+                state.targets.insert(key, target.clone());
+                builder.define(Command::Assign { name: name.to_string(), target }, line);
+            }
+            "tooldata" | "wobjdata" => {
+                let tool = kind.eq_ignore_ascii_case("tooldata");
+                let cell = if tool { &builder.cell.tools } else { &builder.cell.bases };
+                let index = frame_number(cell, &key);
+
+                let parts = value.and_then(|value| strip_brackets(value, '[', ']'))
+                    .map(|inner| split_top_level(inner, ','))
+                    .unwrap_or_default();
+                // tooldata is [robhold, tframe, tload], wobjdata is [robhold, ufprog, ufmec, uframe, oframe]
+                let frame = match (tool, parts.len()) {
+                    (true, 3..) => self.parse_pose(line, text, parts[1], state.scale)?,
+                    (false, 5..) => {
+                        self.parse_pose(line, text, parts[3], state.scale)? * self.parse_pose(line, text, parts[4], state.scale)?
+                    }
+                    _ => return Err(ParseError::new(line, column(text, decl), format!("malformed `{}`", kind))),
+                };
+
+                if tool {
+                    state.tools.insert(key, index);
+                    builder.cell.tools.insert(index, frame);
+                } else {
+                    state.wobjs.insert(key, index);
+                    builder.cell.bases.insert(index, frame);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_condition(&self, line: usize, text: &str, signal: &str, value: &str, state: &mut State) -> Result<Condition, ParseError> {
+        let value = number(line, text, value)? != 0.0;
+        Ok(Condition::Input { port: state.port(signal), value })
+    }
+
+    /// Skips a `WHILE` or `IF` block starting at `statements[i]`, returning the index of its last statement.
+    fn skip_block(&self, statements: &[Statement], i: usize, open: &str, close: &str) -> usize {
+        let mut depth = 0;
+
+        for (j, statement) in statements.iter().enumerate().skip(i) {
+            let opens = starts_with_keyword(statement.code, open)
+                && (open != "IF" || statement.code.to_ascii_uppercase().ends_with("THEN"));
+
+            if opens {
+                depth += 1;
+            } else if starts_with_keyword(statement.code, close) {
+                depth -= 1;
+
+                if depth == 0 {
+                    return j;
+                }
+            }
+        }
+
+        statements.len() - 1
+    }
+}
+
+impl Importer for Rapid {
+    fn import(&self, name: &str, source: &str, unit_length: f32) -> Result<Imported, ParseError> {
+        let statements = statements(source);
+        let mut builder = ProgramBuilder::new(name, unit_length);
+        let mut state = State {
+            scale: 1.0 / (1000.0 * unit_length),
+            tools: HashMap::from([("tool0".to_string(), 0)]),
+            wobjs: HashMap::from([("wobj0".to_string(), 0)]),
+            signals: HashMap::new(),
+            targets: HashMap::new(),
+            joint_targets: HashSet::new(),
+            tool: 0,
+            wobj: 0,
+        };
+        let mut i = 0;
+
+        while i < statements.len() {
+            let statement = &statements[i];
+            let Statement { line, text, code } = *statement;
+            i += 1;
+
+            // `LOCAL` only restricts the scope of the declaration or routine that follows
+            let code = match code.split_once(char::is_whitespace) {
+                Some((first, rest)) if first.eq_ignore_ascii_case("local") => rest.trim_start(),
+                _ => code,
+            };
+
+            let (instruction, args) = code.split_once(char::is_whitespace)
+                .map(|(instruction, args)| (instruction, args.trim()))
+                .unwrap_or((code, ""));
+
+            match instruction.to_ascii_lowercase().as_str() {
+                "module" | "endmodule" | "proc" | "endproc" | "task" | "confj" | "confl" => {}
+                "var" | "pers" | "const" => self.parse_declaration(&mut builder, statement, args, &mut state)?,
+                "moveabsj" | "movej" | "movel" | "movec" => {
+                    self.parse_motion(&mut builder, statement, instruction, args, &mut state)?;
+                }
+                "setdo" => {
+                    let parts = split_top_level(args, ',');
+
+                    if parts.len() != 2 {
+                        return Err(ParseError::new(line, column(text, args), "`SetDO` expects a signal and a value"));
+                    }
+
+                    let value = number(line, text, parts[1])? != 0.0;
+                    builder.push(Command::SetOutput { port: state.port(parts[0]), value }, line);
+                }
+                "set" | "reset" => {
+                    let value = instruction.eq_ignore_ascii_case("set");
+                    builder.push(Command::SetOutput { port: state.port(args), value }, line);
+                }
+                "waitdi" => {
+                    let parts = split_top_level(args, ',');
+
+                    if parts.len() != 2 {
+                        return Err(ParseError::new(line, column(text, args), "`WaitDI` expects a signal and a value"));
+                    }
+
+                    let condition = self.parse_condition(line, text, parts[0], parts[1], &mut state)?;
+                    builder.push(Command::Wait(condition), line);
+                }
+                "waittime" => {
+                    let seconds = split_top_level(args, '\\')[0];
+                    builder.push(Command::WaitTime(number(line, text, seconds)?), line);
+                }
+                "goto" => builder.push(Command::Goto { label: args.to_string(), condition: None }, line),
+                "for" => {
+                    let bounds: Vec<_> = args.split_whitespace().collect();
+
+                    if bounds.len() < 6 || !bounds[1].eq_ignore_ascii_case("FROM") || !bounds[3].eq_ignore_ascii_case("TO") {
+                        return Err(ParseError::new(line, column(text, args), "expected `FOR i FROM <start> TO <end> DO`"));
+                    }
+
+                    let start = number(line, text, bounds[2])?;
+                    let end = number(line, text, bounds[4])?;
+                    builder.open_loop(Some((end - start + 1.0).max(0.0) as u32), line);
+                }
+                "while" if args.eq_ignore_ascii_case("TRUE DO") => builder.open_loop(None, line),
+                "while" => {
+                    builder.warn(line, column(text, code), "ignored `WHILE` loop");
+                    i = self.skip_block(&statements, i - 1, "WHILE", "ENDWHILE") + 1;
+                }
+                "endfor" | "endwhile" => builder.close_loop(line, column(text, code))?,
+                "if" => {
+                    let goto = args.split_once(" GOTO ")
+                        .or_else(|| args.split_once(" goto "))
+                        .and_then(|(condition, label)| Some((condition.split_once('=')?, label.trim())));
+
+                    match goto {
+                        Some(((signal, value), label)) => {
+                            let condition = self.parse_condition(line, text, signal, value, &mut state)?;
+                            builder.push(Command::Goto { label: label.to_string(), condition: Some(condition) }, line);
+                        }
+                        None if code.to_ascii_uppercase().ends_with("THEN") => {
+                            builder.warn(line, column(text, code), "ignored `IF` block");
+                            i = self.skip_block(&statements, i - 1, "IF", "ENDIF") + 1;
+                        }
+                        None => builder.warn(line, column(text, code), "ignored `IF` statement"),
+                    }
+                }
+                _ if code.ends_with(':') && is_identifier(code[..code.len() - 1].trim()) => {
+                    builder.push(Command::Label(code[..code.len() - 1].trim().to_string()), line);
+                }
+                _ => {
+                    if let Some((name, value)) = code.split_once(":=") {
+                        let key = name.trim().to_ascii_lowercase();
+                        let target = self.parse_target(line, text, value, state.joint_targets.contains(&key), &state);
+
+                        if let (true, Ok(target)) = (is_identifier(name.trim()), target) {
+                            state.targets.insert(key, target.clone());
+                            builder.push(Command::Assign { name: name.trim().to_string(), target }, line);
+                            continue;
+                        }
+                    }
+
+                    builder.warn(line, column(text, code), format!("ignored statement `{}`", code));
+                }
+            }
+        }
+
+        builder.finish()
+    }
+}
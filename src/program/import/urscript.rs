@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashMap};
+use nalgebra::{DVector, Isometry3};
+use crate::errors::ParseError;
+use crate::pose::from_ur_pose;
+use crate::program::ast::{Command, Condition, Motion, MotionKind, Target};
+use crate::program::export::UrScript;
+use crate::program::import::{after_keyword, column, frame_number, is_identifier, number, numbers, split_top_level, starts_with_keyword, Imported, Importer, ProgramBuilder};
+
+
+/// Default joint speed of `movej`, in radians per second.
+const JOINT_SPEED: f32 = 1.05;
+/// Keywords that open a block closed by `end`.
+const BLOCKS: [&str; 5] = ["def", "thread", "while", "if", "for"];
+
+/// The non-empty lines of `source` without comments, as `(line number, raw line, code)`.
+fn lines(source: &str) -> Vec<(usize, &str, &str)> {
+    source.lines()
+        .enumerate()
+        .filter_map(|(i, raw)| {
+            let code = match raw.find('#') {
+                Some(comment) if !raw[..comment].contains('"') => &raw[..comment],
+                _ => raw,
+            };
+            let code = code.trim();
+            (!code.is_empty() && !code.starts_with('$')).then(|| (i + 1, raw, code))
+        })
+        .collect()
+}
+
+enum Block {
+    Function,
+    Loop { counter: Option<String> },
+}
+
+struct State {
+    unit_length: f32,
+    poses: HashMap<String, Isometry3<f32>>,
+    counters: HashMap<String, f32>,
+    blocks: Vec<Block>,
+    functions: usize,
+    base: usize,
+}
+
+impl UrScript {
+    /// Parses `p[x, y, z, rx, ry, rz]`, in meters with a rotation vector.
+    fn parse_pose(&self, line: usize, text: &str, part: &str, unit_length: f32) -> Result<Isometry3<f32>, ParseError> {
+        let part = part.trim();
+        let values = numbers(line, text, &part[1..])?;
+
+        if values.len() != 6 {
+            return Err(ParseError::new(line, column(text, part), "a pose has 6 components"));
+        }
+
+        let mut pose = from_ur_pose([values[0], values[1], values[2], values[3], values[4], values[5]]);
+        pose.translation.vector /= unit_length;
+
+        Ok(pose)
+    }
+
+    fn parse_target(&self, line: usize, text: &str, part: &str, state: &State) -> Result<Target, ParseError> {
+        let part = part.trim();
+
+        if is_identifier(part) {
+            Ok(Target::Named(part.to_string()))
+        } else if part.starts_with("p[") {
+            self.parse_pose(line, text, part, state.unit_length).map(Target::Pose)
+        } else if part.starts_with('[') {
+            let joints = numbers(line, text, part)?;
+            Ok(Target::Joints(DVector::from_vec(joints)))
+        } else {
+            Err(ParseError::new(line, column(text, part), format!("unsupported target `{}`", part)))
+        }
+    }
+
+    /// A pose, given literally or by a variable.
+    fn parse_frame(&self, line: usize, text: &str, part: &str, state: &State) -> Result<Isometry3<f32>, ParseError> {
+        let part = part.trim();
+
+        match state.poses.get(part) {
+            Some(pose) => Ok(*pose),
+            None if part.starts_with("p[") => self.parse_pose(line, text, part, state.unit_length),
+            None => Err(ParseError::new(line, column(text, part), format!("expected a known pose, found `{}`", part))),
+        }
+    }
+
+    /// Parses `get_standard_digital_in(n) [== True|False]`, possibly negated with `not`.
+    fn parse_condition(&self, line: usize, text: &str, part: &str) -> Result<Condition, ParseError> {
+        let mut part = part.trim();
+        let mut negated = false;
+
+        loop {
+            if let Some(inner) = after_keyword(part, "not") {
+                negated = !negated;
+                part = inner;
+            } else if let Some(inner) = part.strip_prefix('(').and_then(|p| p.strip_suffix(')')) {
+                part = inner.trim();
+            } else {
+                break;
+            }
+        }
+
+        let (signal, value) = match part.split_once("==") {
+            Some((signal, value)) => match value.trim() {
+                "True" => (signal.trim(), true),
+                "False" => (signal.trim(), false),
+                _ => return Err(ParseError::new(line, column(text, value.trim_start()), "expected `True` or `False`")),
+            },
+            None => (part, true),
+        };
+        let port = signal.strip_prefix("get_standard_digital_in(")
+            .or_else(|| signal.strip_prefix("get_digital_in("))
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or_else(|| ParseError::new(line, column(text, signal), "expected a digital input"))?;
+
+        Ok(Condition::Input { port: number(line, text, port)? as usize, value: value != negated })
+    }
+
+    /// The velocity argument of a move, named `v=` or in position `index`.
+    fn parse_velocity(&self, line: usize, text: &str, args: &[&str], index: usize) -> Result<Option<f32>, ParseError> {
+        let named = args.iter()
+            .find_map(|arg| arg.trim().strip_prefix("v").and_then(|v| v.trim_start().strip_prefix('=')));
+
+        match named {
+            Some(velocity) => number(line, text, velocity).map(Some),
+            None => match args.get(index) {
+                Some(arg) if !arg.contains('=') => number(line, text, arg).map(Some),
+                _ => Ok(None),
+            },
+        }
+    }
+
+    fn parse_motion(&self, builder: &mut ProgramBuilder, line: usize, text: &str, function: &str, args: &str, state: &mut State) -> Result<(), ParseError> {
+        let args = split_top_level(args, ',');
+        let (kind, arity) = match function {
+            "movej" => (MotionKind::Ptp, 1),
+            "movel" => (MotionKind::Lin, 1),
+            _ => (MotionKind::Circ, 2),
+        };
+
+        if args.len() < arity || args[0].trim().is_empty() {
+            return Err(ParseError::new(line, column(text, text.trim_start()), format!("`{}` expects a target", function)));
+        }
+
+        let mut base = 0;
+        let mut targets = Vec::new();
+
+        for arg in &args[..arity] {
+            let arg = arg.trim();
+            let target = match arg.strip_prefix("pose_trans(").and_then(|a| a.strip_suffix(')')) {
+                Some(inner) => match split_top_level(inner, ',')[..] {
+                    [frame, target] => {
+                        let pose = self.parse_frame(line, text, frame, state)?;
+                        base = frame_index(&mut builder.cell.bases, frame.trim(), pose);
+                        self.parse_target(line, text, target, state)?
+                    }
+                    _ => return Err(ParseError::new(line, column(text, arg), "`pose_trans` expects two poses")),
+                },
+                None => self.parse_target(line, text, arg, state)?,
+            };
+
+            targets.push(target);
+        }
+
+        let velocity = self.parse_velocity(line, text, &args, arity + 1)?.map(|v| match kind {
+            MotionKind::Ptp => (v / JOINT_SPEED * 100.0).min(100.0),
+            _ => v / state.unit_length,
+        });
+
+        if base != state.base {
+            builder.push(Command::Base(base), line);
+            state.base = base;
+        }
+
+        let target = targets.pop().unwrap();
+        let via = targets.pop();
+        builder.push(Command::Move(Motion { kind, target, via, velocity }), line);
+
+        Ok(())
+    }
+
+    /// Skips the block opened at `lines[i]`, returning the index of its `end`.
+    fn skip_block(&self, lines: &[(usize, &str, &str)], i: usize) -> usize {
+        let mut depth = 0;
+
+        for (j, (_, _, code)) in lines.iter().enumerate().skip(i) {
+            if BLOCKS.iter().any(|k| starts_with_keyword(code, k)) && code.ends_with(':') {
+                depth += 1;
+            } else if *code == "end" {
+                depth -= 1;
+
+                if depth == 0 {
+                    return j;
+                }
+            }
+        }
+
+        lines.len() - 1
+    }
+
+    /// Whether the block opened at `lines[i]` only waits, returning the index of its `end`.
+    fn wait_block(&self, lines: &[(usize, &str, &str)], i: usize) -> Option<usize> {
+        let end = i + 1 + lines[i + 1..].iter()
+            .position(|(_, _, code)| !(code.starts_with("sync(") || code.starts_with("sleep(")))?;
+
+        (lines[end].2 == "end").then_some(end)
+    }
+}
+
+/// The index of `frame` in `frames`, adding it if it isn't there. Index 0 is the identity.
+fn frame_index(frames: &mut BTreeMap<usize, Isometry3<f32>>, name: &str, frame: Isometry3<f32>) -> usize {
+    if frame == Isometry3::identity() {
+        return 0;
+    }
+
+    if let Some((index, _)) = frames.iter().find(|(_, f)| **f == frame) {
+        return *index;
+    }
+
+    let index = frame_number(frames, name);
+    frames.insert(index, frame);
+    index
+}
+
+impl Importer for UrScript {
+    fn import(&self, name: &str, source: &str, unit_length: f32) -> Result<Imported, ParseError> {
+        let lines = &lines(source);
+        let mut builder = ProgramBuilder::new(name, unit_length);
+        let mut state = State {
+            unit_length,
+            poses: HashMap::new(),
+            counters: HashMap::new(),
+            blocks: Vec::new(),
+            functions: 0,
+            base: 0,
+        };
+        let mut i = 0;
+
+        while i < lines.len() {
+            let (line, text, code) = lines[i];
+            i += 1;
+
+            let call = code.split_once('(')
+                .filter(|(function, _)| is_identifier(function.trim()))
+                .and_then(|(function, args)| Some((function.trim(), args.trim_end().strip_suffix(')')?)));
+
+            if code == "end" {
+                match state.blocks.pop() {
+                    Some(Block::Loop { .. }) => builder.close_loop(line, column(text, code))?,
+                    Some(Block::Function) => {}
+                    None => return Err(ParseError::new(line, column(text, code), "`end` without matching block")),
+                }
+            } else if starts_with_keyword(code, "def") {
+                state.functions += 1;
+
+                if state.functions > 1 {
+                    builder.warn(line, column(text, code), "ignored function definition");
+                    i = self.skip_block(lines, i - 1) + 1;
+                } else {
+                    state.blocks.push(Block::Function);
+                }
+            } else if let Some(condition) = after_keyword(code, "while").and_then(|c| c.strip_suffix(':')) {
+                let condition = condition.trim();
+
+                if condition == "True" {
+                    builder.open_loop(None, line);
+                    state.blocks.push(Block::Loop { counter: None });
+                } else if let Some(end) = self.wait_block(lines, i - 1) {
+                    let condition = match self.parse_condition(line, text, condition)? {
+                        Condition::Input { port, value } => Condition::Input { port, value: !value },
+                    };
+                    builder.push(Command::Wait(condition), line);
+                    i = end + 1;
+                } else if let Some((counter, limit)) = condition.split_once('<') {
+                    let counter = counter.trim();
+                    let start = state.counters.get(counter).copied()
+                        .ok_or_else(|| ParseError::new(line, column(text, counter), format!("unknown counter `{}`", counter)))?;
+                    let limit = number(line, text, limit)?;
+
+                    builder.open_loop(Some((limit - start).max(0.0).ceil() as u32), line);
+                    state.blocks.push(Block::Loop { counter: Some(counter.to_string()) });
+                } else {
+                    builder.warn(line, column(text, code), "ignored `while` loop");
+                    i = self.skip_block(lines, i - 1) + 1;
+                }
+            } else if BLOCKS.iter().any(|k| starts_with_keyword(code, k)) && code.ends_with(':') {
+                builder.warn(line, column(text, code), format!("ignored `{}` block", code.split_whitespace().next().unwrap()));
+                i = self.skip_block(lines, i - 1) + 1;
+            } else if let Some((function, args)) = call {
+                match function {
+                    "movej" | "movel" | "movec" => self.parse_motion(&mut builder, line, text, function, args, &mut state)?,
+                    "set_tcp" => {
+                        let frame = self.parse_frame(line, text, args, &state)?;
+                        let index = frame_index(&mut builder.cell.tools, args.trim(), frame);
+                        builder.push(Command::Tool(index), line);
+                    }
+                    "set_standard_digital_out" | "set_digital_out" => match split_top_level(args, ',')[..] {
+                        [port, value] => {
+                            let port = number(line, text, port)? as usize;
+                            let value = match value.trim() {
+                                "True" => true,
+                                "False" => false,
+                                _ => return Err(ParseError::new(line, column(text, value.trim_start()), "expected `True` or `False`")),
+                            };
+                            builder.push(Command::SetOutput { port, value }, line);
+                        }
+                        _ => return Err(ParseError::new(line, column(text, args), format!("`{}` expects a port and a value", function))),
+                    },
+                    "sleep" => builder.push(Command::WaitTime(number(line, text, args)?), line),
+                    "sync" => {}
+                    _ => builder.warn(line, column(text, code), format!("ignored call to `{}`", function)),
+                }
+            } else if let Some((lhs, rhs)) = code.split_once('=').filter(|(_, rhs)| !rhs.starts_with('=')) {
+                let lhs = after_keyword(lhs.trim(), "global")
+                    .or_else(|| after_keyword(lhs.trim(), "local"))
+                    .unwrap_or(lhs)
+                    .trim();
+                let rhs = rhs.trim();
+                let increment = state.blocks.iter().any(|block| match block {
+                    Block::Loop { counter: Some(counter) } => counter == lhs && rhs.replace(' ', "") == format!("{}+1", lhs),
+                    _ => false,
+                });
+
+                if increment {
+                    continue;
+                } else if let Ok(value) = rhs.parse::<f32>() {
+                    state.counters.insert(lhs.to_string(), value);
+                } else if rhs.starts_with('[') || rhs.starts_with("p[") {
+                    let target = self.parse_target(line, text, rhs, &state)?;
+
+                    if let Target::Pose(pose) = &target {
+                        state.poses.insert(lhs.to_string(), *pose);
+                    }
+
+                    builder.push(Command::Assign { name: lhs.to_string(), target }, line);
+                } else if is_identifier(rhs) {
+                    if let Some(pose) = state.poses.get(rhs).copied() {
+                        state.poses.insert(lhs.to_string(), pose);
+                    }
+
+                    builder.push(Command::Assign { name: lhs.to_string(), target: Target::Named(rhs.to_string()) }, line);
+                } else {
+                    builder.warn(line, column(text, code), format!("ignored assignment to `{}`", lhs));
+                }
+            } else {
+                builder.warn(line, column(text, code), format!("ignored statement `{}`", code));
+            }
+        }
+
+        builder.finish()
+    }
+}
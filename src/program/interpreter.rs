@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use nalgebra::{DVector, Isometry3};
+use crate::errors::ProgramError;
+use crate::kinematics::{InverseKinematics, KinematicChain};
+use crate::program::ast::{Command, Condition, Motion, MotionKind, Program, Target};
+use crate::program::motion::{MotionSample, MotionSegment};
+
+
+/// Everything a running program needs from the robot executing it.
+pub trait ProgramContext {
+    fn chain(&self) -> &KinematicChain;
+    fn joints(&self) -> &DVector<f32>;
+    fn set_joints(&mut self, joints: DVector<f32>);
+    /// The tool center point of the tool `tool`, expressed wrt. the flange.
+    fn tool_frame(&self, tool: usize) -> Option<Isometry3<f32>>;
+    /// The world-space frame of the base `base`.
+    fn base_frame(&self, base: usize) -> Option<Isometry3<f32>>;
+    fn digital_input(&self, port: usize) -> bool;
+    fn set_digital_output(&mut self, port: usize, value: bool);
+    /// Closes the gripper of the active tool, grasping what it holds, or opens it.
+    fn set_gripper(&mut self, closed: bool);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MotionSettings {
+    /// Joint velocity of a `PTP` motion at 100%, in radians per second.
+    pub max_joint_velocity: f32,
+    /// Default tool speed of `LIN` and `CIRC` motions.
+    pub linear_velocity: f32,
+    /// Tool reorientation speed of `LIN` and `CIRC` motions, in radians per second.
+    pub angular_velocity: f32,
+    pub ik: InverseKinematics,
+}
+
+impl Default for MotionSettings {
+    fn default() -> Self {
+        Self {
+            max_joint_velocity: 1.0,
+            linear_velocity: 0.25,
+            angular_velocity: 1.0,
+            ik: InverseKinematics::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProgramState {
+    Ready,
+    Moving,
+    Waiting,
+    Finished,
+    Failed,
+}
+
+/// Executes a [`Program`] incrementally, one simulation step at a time.
+#[derive(Clone, Debug)]
+pub struct Interpreter {
+    pub settings: MotionSettings,
+    program: Program,
+    pc: usize,
+    state: ProgramState,
+    variables: HashMap<String, Target>,
+    loop_counters: HashMap<usize, u32>,
+    tool: usize,
+    base: usize,
+    motion: Option<MotionSegment>,
+    wait_time: f32,
+}
+
+/// The number of non-blocking instructions executed at most by a single step.
+const MAX_INSTRUCTIONS_PER_STEP: usize = 1000;
+
+impl Interpreter {
+    pub fn new(program: Program) -> Self {
+        Self {
+            settings: MotionSettings::default(),
+            program,
+            pc: 0,
+            state: ProgramState::Ready,
+            variables: HashMap::new(),
+            loop_counters: HashMap::new(),
+            tool: 0,
+            base: 0,
+            motion: None,
+            wait_time: 0.0,
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn state(&self) -> ProgramState {
+        self.state
+    }
+
+    pub fn active_tool(&self) -> usize {
+        self.tool
+    }
+
+    pub fn active_base(&self) -> usize {
+        self.base
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Target> {
+        self.variables.get(&name.to_ascii_lowercase())
+    }
+
+    /// The source line of the instruction being executed.
+    pub fn current_line(&self) -> Option<usize> {
+        self.program.instructions.get(self.pc).map(|i| i.line)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self {
+            settings: self.settings,
+            ..Self::new(std::mem::take(&mut self.program))
+        };
+    }
+
+    /// Runs the program for `dt` seconds.
+    pub fn step(&mut self, ctx: &mut dyn ProgramContext, dt: f32) -> Result<ProgramState, ProgramError> {
+        if self.state == ProgramState::Failed || self.state == ProgramState::Finished {
+            return Ok(self.state);
+        }
+
+        let result = self.run(ctx, dt);
+
+        if result.is_err() {
+            self.state = ProgramState::Failed;
+            self.motion = None;
+        }
+
+        result
+    }
+
+    fn run(&mut self, ctx: &mut dyn ProgramContext, dt: f32) -> Result<ProgramState, ProgramError> {
+        if let Some(motion) = &mut self.motion {
+            let sample = motion.advance(dt);
+            let finished = motion.is_finished();
+            self.apply_sample(ctx, sample)?;
+
+            if !finished {
+                self.state = ProgramState::Moving;
+                return Ok(self.state);
+            }
+
+            self.motion = None;
+            self.pc += 1;
+        }
+
+        if self.wait_time > 0.0 {
+            self.wait_time -= dt;
+
+            if self.wait_time > 0.0 {
+                self.state = ProgramState::Waiting;
+                return Ok(self.state);
+            }
+
+            self.wait_time = 0.0;
+            self.pc += 1;
+        }
+
+        for _ in 0..MAX_INSTRUCTIONS_PER_STEP {
+            let instruction = match self.program.instructions.get(self.pc) {
+                Some(instruction) => instruction.clone(),
+                None => {
+                    self.state = ProgramState::Finished;
+                    return Ok(self.state);
+                }
+            };
+            let line = instruction.line;
+
+            match instruction.command {
+                Command::Assign { name, target } => {
+                    let target = match target {
+                        Target::Named(other) => self.lookup(&other, line)?.clone(),
+                        target => target,
+                    };
+                    self.variables.insert(name.to_ascii_lowercase(), target);
+                }
+                Command::Tool(tool) => {
+                    if ctx.tool_frame(tool).is_none() {
+                        return Err(ProgramError::UnknownTool { line, tool });
+                    }
+
+                    self.tool = tool;
+                }
+                Command::Base(base) => {
+                    if ctx.base_frame(base).is_none() {
+                        return Err(ProgramError::UnknownBase { line, base });
+                    }
+
+                    self.base = base;
+                }
+                Command::Move(motion) => {
+                    let segment = self.plan(ctx, &motion, line)?;
+
+                    if segment.is_finished() {
+                        let sample = segment.sample(1.0);
+                        self.apply_sample(ctx, sample)?;
+                    } else {
+                        self.motion = Some(segment);
+                        self.state = ProgramState::Moving;
+                        return Ok(self.state);
+                    }
+                }
+                Command::SetOutput { port, value } => ctx.set_digital_output(port, value),
+                Command::Gripper(closed) => ctx.set_gripper(closed),
+                Command::Wait(condition) => {
+                    if !self.check(ctx, &condition) {
+                        self.state = ProgramState::Waiting;
+                        return Ok(self.state);
+                    }
+                }
+                Command::WaitTime(seconds) => {
+                    if seconds > 0.0 {
+                        self.wait_time = seconds;
+                        self.state = ProgramState::Waiting;
+                        return Ok(self.state);
+                    }
+                }
+                Command::Label(_) => {}
+                Command::Goto { label, condition } => {
+                    if condition.map(|c| self.check(ctx, &c)).unwrap_or(true) {
+                        self.pc = self.program.label(&label)
+                            .ok_or(ProgramError::UnknownLabel { line, label })?;
+                        continue;
+                    }
+                }
+                Command::Loop { count, end } => {
+                    if let Some(count) = count {
+                        if count == 0 {
+                            self.pc = end + 1;
+                            continue;
+                        }
+
+                        self.loop_counters.insert(self.pc, count);
+                    }
+                }
+                Command::EndLoop { start } => {
+                    let repeat = match self.loop_counters.get_mut(&start) {
+                        // A GOTO may jump back into a loop that already finished
+                        Some(counter) => {
+                            *counter = counter.saturating_sub(1);
+                            *counter > 0
+                        }
+                        None => true,
+                    };
+
+                    if repeat {
+                        self.pc = start + 1;
+                        continue;
+                    }
+                }
+            }
+
+            self.pc += 1;
+        }
+
+        self.state = ProgramState::Ready;
+        Ok(self.state)
+    }
+
+    fn check(&self, ctx: &dyn ProgramContext, condition: &Condition) -> bool {
+        match condition {
+            Condition::Input { port, value } => ctx.digital_input(*port) == *value,
+        }
+    }
+
+    fn lookup(&self, name: &str, line: usize) -> Result<&Target, ProgramError> {
+        self.variable(name).ok_or_else(|| ProgramError::UnknownVariable {
+            line,
+            name: name.to_string(),
+        })
+    }
+
+    fn tcp(&self, ctx: &dyn ProgramContext, line: usize) -> Result<Isometry3<f32>, ProgramError> {
+        ctx.tool_frame(self.tool).ok_or(ProgramError::UnknownTool { line, tool: self.tool })
+    }
+
+    fn resolve<'a>(&'a self, target: &'a Target, line: usize) -> Result<&'a Target, ProgramError> {
+        match target {
+            Target::Named(name) => self.lookup(name, line),
+            target => Ok(target),
+        }
+    }
+
+    /// The world-space tool pose of a target.
+    fn target_pose(&self, ctx: &dyn ProgramContext, target: &Target, line: usize) -> Result<Isometry3<f32>, ProgramError> {
+        match self.resolve(target, line)? {
+            Target::Pose(pose) => {
+                let base = ctx.base_frame(self.base).ok_or(ProgramError::UnknownBase { line, base: self.base })?;
+                Ok(base * pose)
+            }
+            Target::Joints(joints) => {
+                self.check_joint_count(ctx, joints, line)?;
+                Ok(ctx.chain().forward(joints, &self.tcp(ctx, line)?))
+            }
+            Target::Named(_) => unreachable!(),
+        }
+    }
+
+    fn check_joint_count(&self, ctx: &dyn ProgramContext, joints: &DVector<f32>, line: usize) -> Result<(), ProgramError> {
+        if joints.len() != ctx.chain().ndofs() {
+            return Err(ProgramError::JointCount {
+                line,
+                expected: ctx.chain().ndofs(),
+                found: joints.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn plan(&self, ctx: &dyn ProgramContext, motion: &Motion, line: usize) -> Result<MotionSegment, ProgramError> {
+        let settings = &self.settings;
+        let tcp = self.tcp(ctx, line)?;
+        let current = ctx.joints().clone();
+
+        match motion.kind {
+            MotionKind::Ptp => {
+                let end = match self.resolve(&motion.target, line)? {
+                    Target::Joints(joints) => {
+                        self.check_joint_count(ctx, joints, line)?;
+                        joints.clone()
+                    }
+                    _ => {
+                        let pose = self.target_pose(ctx, &motion.target, line)?;
+                        settings.ik.solve(ctx.chain(), &pose, &tcp, &current)
+                            .map_err(|source| ProgramError::Kinematics { line, source })?
+                    }
+                };
+                let scale = motion.velocity.map(|v| v.min(100.0) / 100.0).unwrap_or(1.0);
+
+                Ok(MotionSegment::joint(current, end, settings.max_joint_velocity * scale))
+            }
+            MotionKind::Lin => {
+                let start = ctx.chain().forward(&current, &tcp);
+                let end = self.target_pose(ctx, &motion.target, line)?;
+                let velocity = motion.velocity.unwrap_or(settings.linear_velocity);
+
+                Ok(MotionSegment::linear(start, end, velocity, settings.angular_velocity))
+            }
+            MotionKind::Circ => {
+                let start = ctx.chain().forward(&current, &tcp);
+                let via = motion.via.as_ref().ok_or(ProgramError::DegenerateCircle { line })?;
+                let via = self.target_pose(ctx, via, line)?;
+                let end = self.target_pose(ctx, &motion.target, line)?;
+                let velocity = motion.velocity.unwrap_or(settings.linear_velocity);
+
+                MotionSegment::circular(start, &via, end, velocity, settings.angular_velocity)
+                    .ok_or(ProgramError::DegenerateCircle { line })
+            }
+        }
+    }
+
+    fn apply_sample(&self, ctx: &mut dyn ProgramContext, sample: MotionSample) -> Result<(), ProgramError> {
+        let line = self.current_line().unwrap_or(0);
+
+        let joints = match sample {
+            MotionSample::Joints(joints) => joints,
+            MotionSample::Pose(pose) => {
+                let tcp = self.tcp(ctx, line)?;
+                self.settings.ik.solve(ctx.chain(), &pose, &tcp, ctx.joints())
+                    .map_err(|source| ProgramError::Kinematics { line, source })?
+            }
+        };
+
+        ctx.set_joints(joints);
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use crate::errors::ParseError;
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    Number(f32),
+    Comma,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Assign,
+    Equal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The 1-based column of the first character of this token.
+    pub column: usize,
+}
+
+/// Splits a single source line into tokens, stopping at the first `;` comment.
+pub fn tokenize(line: usize, text: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        let kind = match c {
+            ';' => break,
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ',' => TokenKind::Comma,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            '=' if chars.get(i + 1) == Some(&'=') => {
+                i += 1;
+                TokenKind::Equal
+            }
+            '=' => TokenKind::Assign,
+            c if c.is_ascii_digit() || c == '.' || c == '-' || c == '+' => {
+                let start = i;
+                i += 1;
+
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.'
+                    || ((chars[i] == 'e' || chars[i] == 'E') && i + 1 < chars.len())
+                    || ((chars[i] == '-' || chars[i] == '+') && matches!(chars[i - 1], 'e' | 'E')))
+                {
+                    i += 1;
+                }
+
+                let literal: String = chars[start..i].iter().collect();
+                let value = literal.parse::<f32>().map_err(|_| {
+                    ParseError::new(line, column, format!("invalid number `{}`", literal))
+                })?;
+
+                tokens.push(Token { kind: TokenKind::Number(value), column });
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                    i += 1;
+                }
+
+                let ident = chars[start..i].iter().collect();
+                tokens.push(Token { kind: TokenKind::Ident(ident), column });
+                continue;
+            }
+            c => return Err(ParseError::new(line, column, format!("unexpected character `{}`", c))),
+        };
+
+        tokens.push(Token { kind, column });
+        i += 1;
+    }
+
+    Ok(tokens)
+}
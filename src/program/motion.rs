@@ -0,0 +1,148 @@
+use nalgebra::{DVector, Isometry3, Point3, Translation3, Unit, UnitQuaternion, Vector3};
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MotionPath {
+    Joint {
+        start: DVector<f32>,
+        end: DVector<f32>,
+    },
+    Linear {
+        start: Isometry3<f32>,
+        end: Isometry3<f32>,
+    },
+    Circular {
+        start: Isometry3<f32>,
+        end: Isometry3<f32>,
+        center: Point3<f32>,
+        axis: Unit<Vector3<f32>>,
+        angle: f32,
+    },
+}
+
+/// A point sampled along a motion path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MotionSample {
+    Joints(DVector<f32>),
+    Pose(Isometry3<f32>),
+}
+
+/// A single motion, traversed with a smooth velocity profile starting and ending at rest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotionSegment {
+    pub path: MotionPath,
+    pub duration: f32,
+    elapsed: f32,
+}
+
+/// Ratio between the peak and the mean velocity of the smoothstep profile.
+const PROFILE_PEAK: f32 = 1.5;
+
+impl MotionSegment {
+    fn with_duration(path: MotionPath, duration: f32) -> Self {
+        Self {
+            path,
+            duration: duration * PROFILE_PEAK,
+            elapsed: 0.0,
+        }
+    }
+
+    /// A joint interpolated motion where no joint exceeds `max_velocity` (radians per second).
+    pub fn joint(start: DVector<f32>, end: DVector<f32>, max_velocity: f32) -> Self {
+        let duration = (&end - &start).amax() / max_velocity;
+        Self::with_duration(MotionPath::Joint { start, end }, duration)
+    }
+
+    /// A straight line motion with the given linear and angular velocities.
+    pub fn linear(start: Isometry3<f32>, end: Isometry3<f32>, velocity: f32, angular_velocity: f32) -> Self {
+        let distance = (end.translation.vector - start.translation.vector).norm();
+        let angle = start.rotation.angle_to(&end.rotation);
+        let duration = (distance / velocity).max(angle / angular_velocity);
+
+        Self::with_duration(MotionPath::Linear { start, end }, duration)
+    }
+
+    /// A circular motion from `start` to `end` passing through the position of `via`.
+    ///
+    /// Returns `None` if the three points are aligned.
+    pub fn circular(
+        start: Isometry3<f32>,
+        via: &Isometry3<f32>,
+        end: Isometry3<f32>,
+        velocity: f32,
+        angular_velocity: f32,
+    ) -> Option<Self> {
+        let p0 = start.translation.vector;
+        let a = via.translation.vector - p0;
+        let b = end.translation.vector - p0;
+        let n = a.cross(&b);
+        let n_sq = n.norm_squared();
+
+        if n_sq < 1.0e-12 {
+            return None;
+        }
+
+        let center = Point3::from(p0 + (b * a.norm_squared() - a * b.norm_squared()).cross(&n) / (2.0 * n_sq));
+        let axis = Unit::new_normalize(n);
+        let v0 = p0 - center.coords;
+        let v2 = end.translation.vector - center.coords;
+        let mut angle = v0.cross(&v2).dot(&axis).atan2(v0.dot(&v2));
+
+        if angle <= 0.0 {
+            angle += std::f32::consts::TAU;
+        }
+
+        let length = angle * v0.norm();
+        let rotation = start.rotation.angle_to(&end.rotation);
+        let duration = (length / velocity).max(rotation / angular_velocity);
+        let path = MotionPath::Circular { start, end, center, axis, angle };
+
+        Some(Self::with_duration(path, duration))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// The normalized path parameter at the current time.
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+
+        let t = (self.elapsed / self.duration).min(1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Moves forward along the path by `dt` seconds and returns the new sample.
+    pub fn advance(&mut self, dt: f32) -> MotionSample {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.sample(self.progress())
+    }
+
+    /// The point of the path at the normalized parameter `s`.
+    pub fn sample(&self, s: f32) -> MotionSample {
+        match &self.path {
+            MotionPath::Joint { start, end } => MotionSample::Joints(start.lerp(end, s)),
+            MotionPath::Linear { start, end } => {
+                let position = start.translation.vector.lerp(&end.translation.vector, s);
+                let rotation = slerp(&start.rotation, &end.rotation, s);
+
+                MotionSample::Pose(Isometry3::from_parts(Translation3::from(position), rotation))
+            }
+            MotionPath::Circular { start, end, center, axis, angle } => {
+                let turn = UnitQuaternion::from_axis_angle(axis, angle * s);
+                let position = center + turn * (start.translation.vector - center.coords);
+                let rotation = slerp(&start.rotation, &end.rotation, s);
+
+                MotionSample::Pose(Isometry3::from_parts(Translation3::from(position.coords), rotation))
+            }
+        }
+    }
+}
+
+/// Spherical interpolation that doesn't panic on half-turns.
+fn slerp(start: &UnitQuaternion<f32>, end: &UnitQuaternion<f32>, s: f32) -> UnitQuaternion<f32> {
+    start.try_slerp(end, s, 1.0e-6)
+        .unwrap_or(if s < 0.5 { *start } else { *end })
+}
@@ -0,0 +1,376 @@
+use nalgebra::DVector;
+use crate::errors::ParseError;
+use crate::pose::kuka_frame;
+use crate::program::ast::{Command, Condition, Instruction, Motion, MotionKind, Program, Target};
+use crate::program::lexer::{tokenize, Token, TokenKind};
+
+
+/// Parses the text of a robot program.
+///
+/// Each line holds a single statement, keywords are case-insensitive and `;` starts a comment:
+///
+/// ```text
+/// VAR home = JOINTS(0, -90, 90, 0, 45, 0)
+/// VAR pick = POSE(0.5, 0.0, 0.2, 0, 180, 0)
+/// TOOL 1
+/// LOOP 3
+///     PTP home VEL 50
+///     LIN pick VEL 0.1
+///     GRIP
+///     SET OUT[1] = TRUE
+///     WAIT IN[2]
+/// ENDLOOP
+/// ```
+///
+/// Positions are in scene units, `POSE` angles are KUKA-style A, B, C degrees (rotations about Z, Y, X)
+/// and `JOINTS` values are degrees.
+pub fn parse(name: &str, source: &str) -> Result<Program, ParseError> {
+    let mut program = Program::new(name);
+    let mut open_loops = Vec::new();
+    let mut gotos = Vec::new();
+
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let tokens = tokenize(line, text)?;
+
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let mut parser = LineParser {
+            tokens,
+            pos: 0,
+            line,
+            eol: text.chars().count() + 1,
+        };
+        let command = parser.statement()?;
+        parser.end()?;
+
+        let index = program.instructions.len();
+
+        match &command {
+            Command::Loop { .. } => open_loops.push(index),
+            Command::EndLoop { .. } => {
+                let start = open_loops.pop().ok_or_else(|| {
+                    ParseError::new(line, parser.tokens[0].column, "`ENDLOOP` without matching `LOOP`")
+                })?;
+
+                if let Command::Loop { end, .. } = &mut program.instructions[start].command {
+                    *end = index;
+                }
+
+                program.instructions.push(Instruction { command: Command::EndLoop { start }, line });
+                continue;
+            }
+            Command::Label(label) if program.label(label).is_some() => {
+                return Err(ParseError::new(
+                    line,
+                    parser.tokens[1].column,
+                    format!("label `{}` is already defined", label),
+                ));
+            }
+            Command::Goto { .. } => gotos.push((index, parser.tokens.last().unwrap().column)),
+            _ => {}
+        }
+
+        program.instructions.push(Instruction { command, line });
+    }
+
+    if let Some(start) = open_loops.pop() {
+        let line = program.instructions[start].line;
+        return Err(ParseError::new(line, 1, "`LOOP` without matching `ENDLOOP`"));
+    }
+
+    for (index, column) in gotos {
+        let instruction = &program.instructions[index];
+
+        if let Command::Goto { label, .. } = &instruction.command {
+            if program.label(label).is_none() {
+                return Err(ParseError::new(instruction.line, column, format!("unknown label `{}`", label)));
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+struct LineParser {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+    eol: usize,
+}
+
+impl LineParser {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        let column = self.tokens.get(self.pos).map(|t| t.column).unwrap_or(self.eol);
+        ParseError::new(self.line, column, message)
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(TokenKind::Ident(ident)) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next(&mut self) -> Option<TokenKind> {
+        let token = self.tokens.get(self.pos).map(|t| t.kind.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("unexpected token, expected end of line")),
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(k) if *k == kind => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(format!("expected {}", what))),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", keyword)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(TokenKind::Ident(ident)) if !is_reserved(ident) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, ParseError> {
+        match self.peek() {
+            Some(TokenKind::Number(value)) => {
+                let value = *value;
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn index(&mut self) -> Result<usize, ParseError> {
+        let value = self.number()?;
+
+        if value < 0.0 || value.fract() != 0.0 {
+            self.pos -= 1;
+            return Err(self.error("expected a non-negative integer"));
+        }
+
+        Ok(value as usize)
+    }
+
+    fn boolean(&mut self) -> Result<bool, ParseError> {
+        let value = match self.peek() {
+            Some(TokenKind::Ident(ident)) if ident.eq_ignore_ascii_case("TRUE") || ident.eq_ignore_ascii_case("ON") => true,
+            Some(TokenKind::Ident(ident)) if ident.eq_ignore_ascii_case("FALSE") || ident.eq_ignore_ascii_case("OFF") => false,
+            Some(TokenKind::Number(value)) if *value == 1.0 => true,
+            Some(TokenKind::Number(value)) if *value == 0.0 => false,
+            _ => return Err(self.error("expected `TRUE` or `FALSE`")),
+        };
+        self.pos += 1;
+
+        Ok(value)
+    }
+
+    fn arguments(&mut self) -> Result<Vec<f32>, ParseError> {
+        self.expect(TokenKind::LParen, "`(`")?;
+        let mut values = vec![self.number()?];
+
+        while self.peek() == Some(&TokenKind::Comma) {
+            self.pos += 1;
+            values.push(self.number()?);
+        }
+
+        self.expect(TokenKind::RParen, "`)`")?;
+        Ok(values)
+    }
+
+    fn port(&mut self, keyword: &str) -> Result<usize, ParseError> {
+        self.keyword(keyword)?;
+        self.expect(TokenKind::LBracket, "`[`")?;
+        let port = self.index()?;
+        self.expect(TokenKind::RBracket, "`]`")?;
+
+        Ok(port)
+    }
+
+    fn target(&mut self) -> Result<Target, ParseError> {
+        if self.peek_keyword("POSE") {
+            let column = self.tokens[self.pos].column;
+            self.pos += 1;
+            let values = self.arguments()?;
+
+            if values.len() != 6 {
+                return Err(ParseError::new(self.line, column, format!(
+                    "`POSE` expects 6 values (X, Y, Z, A, B, C), found {}", values.len()
+                )));
+            }
+
+            let pose = kuka_frame(values[0], values[1], values[2], values[3], values[4], values[5]);
+            Ok(Target::Pose(pose))
+        } else if self.peek_keyword("JOINTS") {
+            self.pos += 1;
+            let values = self.arguments()?;
+
+            Ok(Target::Joints(DVector::from_iterator(
+                values.len(),
+                values.into_iter().map(f32::to_radians),
+            )))
+        } else {
+            self.ident()
+                .map(Target::Named)
+                .map_err(|_| self.error("expected a target: a variable, `POSE(...)` or `JOINTS(...)`"))
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, ParseError> {
+        let port = self.port("IN")?;
+        let value = if self.peek() == Some(&TokenKind::Equal) {
+            self.pos += 1;
+            self.boolean()?
+        } else {
+            true
+        };
+
+        Ok(Condition::Input { port, value })
+    }
+
+    fn velocity(&mut self) -> Result<Option<f32>, ParseError> {
+        if !self.peek_keyword("VEL") {
+            return Ok(None);
+        }
+
+        self.pos += 1;
+        let value = self.number()?;
+
+        if value <= 0.0 {
+            self.pos -= 1;
+            return Err(self.error("velocity must be positive"));
+        }
+
+        Ok(Some(value))
+    }
+
+    fn motion(&mut self, kind: MotionKind) -> Result<Command, ParseError> {
+        let first = self.target()?;
+        let (via, target) = if kind == MotionKind::Circ {
+            self.expect(TokenKind::Comma, "`,` between the auxiliary and the end point")?;
+            (Some(first), self.target()?)
+        } else {
+            (None, first)
+        };
+        let velocity = self.velocity()?;
+
+        Ok(Command::Move(Motion { kind, target, via, velocity }))
+    }
+
+    fn statement(&mut self) -> Result<Command, ParseError> {
+        let keyword = match self.next() {
+            Some(TokenKind::Ident(ident)) => ident.to_ascii_uppercase(),
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected a statement"));
+            }
+        };
+
+        match keyword.as_str() {
+            "VAR" => {
+                let name = self.ident()?;
+                self.expect(TokenKind::Assign, "`=`")?;
+                let target = self.target()?;
+
+                Ok(Command::Assign { name, target })
+            }
+            "TOOL" => Ok(Command::Tool(self.index()?)),
+            "BASE" => Ok(Command::Base(self.index()?)),
+            "PTP" => self.motion(MotionKind::Ptp),
+            "LIN" => self.motion(MotionKind::Lin),
+            "CIRC" => self.motion(MotionKind::Circ),
+            "SET" => {
+                let port = self.port("OUT")?;
+                self.expect(TokenKind::Assign, "`=`")?;
+                let value = self.boolean()?;
+
+                Ok(Command::SetOutput { port, value })
+            }
+            "WAIT" => {
+                if self.peek_keyword("TIME") {
+                    self.pos += 1;
+                    let seconds = self.number()?;
+
+                    if seconds < 0.0 {
+                        self.pos -= 1;
+                        return Err(self.error("wait time must not be negative"));
+                    }
+
+                    Ok(Command::WaitTime(seconds))
+                } else {
+                    Ok(Command::Wait(self.condition()?))
+                }
+            }
+            "GRIP" => Ok(Command::Gripper(true)),
+            "RELEASE" => Ok(Command::Gripper(false)),
+            "LABEL" => Ok(Command::Label(self.ident()?)),
+            "GOTO" => Ok(Command::Goto { label: self.ident()?, condition: None }),
+            "IF" => {
+                let condition = self.condition()?;
+                self.keyword("GOTO")?;
+
+                Ok(Command::Goto { label: self.ident()?, condition: Some(condition) })
+            }
+            "LOOP" => {
+                let count = match self.peek() {
+                    Some(TokenKind::Number(_)) => {
+                        let count = self.index()?;
+
+                        match u32::try_from(count) {
+                            Ok(count) => Some(count),
+                            Err(_) => {
+                                self.pos -= 1;
+                                return Err(self.error(format!("loop count must not exceed {}", u32::MAX)));
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+
+                Ok(Command::Loop { count, end: 0 })
+            }
+            "ENDLOOP" => Ok(Command::EndLoop { start: 0 }),
+            _ => {
+                self.pos -= 1;
+                Err(self.error(format!("unknown statement `{}`", keyword)))
+            }
+        }
+    }
+}
+
+fn is_reserved(ident: &str) -> bool {
+    const RESERVED: [&str; 23] = [
+        "VAR", "TOOL", "BASE", "PTP", "LIN", "CIRC", "SET", "WAIT", "TIME", "LABEL", "GOTO",
+        "IF", "LOOP", "ENDLOOP", "POSE", "JOINTS", "VEL", "IN", "OUT", "TRUE", "FALSE", "GRIP", "RELEASE",
+    ];
+
+    RESERVED.iter().any(|r| r.eq_ignore_ascii_case(ident))
+}
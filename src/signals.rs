@@ -0,0 +1,6 @@
+mod table;
+mod sensor;
+
+
+pub use self::table::{Edge, Signal, SignalEvent, SignalKind, SignalTable, SignalValue};
+pub use self::sensor::{LightBarrier, RangeSensor, Sensor};
@@ -0,0 +1,118 @@
+use nalgebra::{Point3, UnitVector3};
+use parry3d::bounding_volume::AABB;
+use parry3d::query::Ray;
+use crate::errors::SignalError;
+use crate::mesh::{ObjectHandle, ObjectSet};
+use crate::pipeline::query_pipeline::QueryPipeline;
+use crate::signals::SignalTable;
+
+
+/// A simulated device setting signals from the state of the scene.
+pub trait Sensor {
+    /// Updates the signals of the sensor. `query_pipeline` has to be up to date with the scene.
+    fn sense(&mut self, objects: &ObjectSet, query_pipeline: &QueryPipeline, signals: &mut SignalTable);
+
+    /// Checks that the signals the sensor sets are declared in `signals` with the right kind, before it is used.
+    fn check(&self, _signals: &SignalTable) -> Result<(), SignalError> {
+        Ok(())
+    }
+}
+
+/// The nearest object hit by a world-space ray within `length`, with the distance to it.
+fn cast(
+    objects: &ObjectSet,
+    query_pipeline: &QueryPipeline,
+    origin: &Point3<f32>,
+    direction: &UnitVector3<f32>,
+    length: f32,
+    ignored: &[ObjectHandle],
+) -> Option<(ObjectHandle, f32)> {
+    let ray = Ray::new(*origin, direction.into_inner());
+    let end = ray.point_at(length);
+    let aabb = AABB::new(origin.inf(&end), origin.sup(&end));
+
+    query_pipeline.intersect_aabb(&aabb).into_iter()
+        .filter(|handle| !ignored.contains(handle))
+        .filter_map(|handle| {
+            let obj = objects.get(handle).filter(|obj| obj.is_enabled())?;
+            Some((handle, obj.shape().cast_ray(obj.position(), &ray, length, true)?))
+        })
+        .min_by(|(_, toi1), (_, toi2)| toi1.total_cmp(toi2))
+}
+
+/// A light beam between an emitter and a receiver, turning its digital input on while an object crosses it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightBarrier {
+    /// The digital input set by the barrier.
+    pub signal: String,
+    /// The emitter, in world space.
+    pub origin: Point3<f32>,
+    pub direction: UnitVector3<f32>,
+    /// The distance to the receiver.
+    pub length: f32,
+    /// The objects the beam goes through, like the frame of the barrier.
+    pub ignored: Vec<ObjectHandle>,
+    /// The object blocking the beam at the last update.
+    pub blocked_by: Option<ObjectHandle>,
+}
+
+impl LightBarrier {
+    pub fn new(signal: impl Into<String>, origin: Point3<f32>, direction: UnitVector3<f32>, length: f32) -> Self {
+        Self { signal: signal.into(), origin, direction, length, ignored: Vec::new(), blocked_by: None }
+    }
+
+    pub fn ignore(mut self, handle: ObjectHandle) -> Self {
+        self.ignored.push(handle);
+        self
+    }
+}
+
+impl Sensor for LightBarrier {
+    fn sense(&mut self, objects: &ObjectSet, query_pipeline: &QueryPipeline, signals: &mut SignalTable) {
+        self.blocked_by = cast(objects, query_pipeline, &self.origin, &self.direction, self.length, &self.ignored)
+            .map(|(handle, _)| handle);
+
+        // The signal is checked when the sensor is added, it can only have been removed since
+        let _ = signals.set(&self.signal, self.blocked_by.is_some());
+    }
+
+    fn check(&self, signals: &SignalTable) -> Result<(), SignalError> {
+        signals.check(&self.signal, true)
+    }
+}
+
+/// A distance sensor measuring along a ray, setting its analog input to the distance of the nearest object, or to
+/// its range when there is none.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeSensor {
+    /// The analog input set by the sensor.
+    pub signal: String,
+    pub origin: Point3<f32>,
+    pub direction: UnitVector3<f32>,
+    pub range: f32,
+    pub ignored: Vec<ObjectHandle>,
+}
+
+impl RangeSensor {
+    pub fn new(signal: impl Into<String>, origin: Point3<f32>, direction: UnitVector3<f32>, range: f32) -> Self {
+        Self { signal: signal.into(), origin, direction, range, ignored: Vec::new() }
+    }
+
+    pub fn ignore(mut self, handle: ObjectHandle) -> Self {
+        self.ignored.push(handle);
+        self
+    }
+}
+
+impl Sensor for RangeSensor {
+    fn sense(&mut self, objects: &ObjectSet, query_pipeline: &QueryPipeline, signals: &mut SignalTable) {
+        let distance = cast(objects, query_pipeline, &self.origin, &self.direction, self.range, &self.ignored)
+            .map_or(self.range, |(_, distance)| distance);
+
+        let _ = signals.set(&self.signal, distance);
+    }
+
+    fn check(&self, signals: &SignalTable) -> Result<(), SignalError> {
+        signals.check(&self.signal, false)
+    }
+}
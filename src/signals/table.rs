@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use crate::errors::SignalError;
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SignalKind {
+    DigitalInput,
+    DigitalOutput,
+    AnalogInput,
+    AnalogOutput,
+}
+
+impl SignalKind {
+    pub fn is_digital(self) -> bool {
+        matches!(self, SignalKind::DigitalInput | SignalKind::DigitalOutput)
+    }
+
+    pub fn is_input(self) -> bool {
+        matches!(self, SignalKind::DigitalInput | SignalKind::AnalogInput)
+    }
+
+    /// The name given to a port used without being declared, like the `IN[n]` and `OUT[n]` program signals.
+    fn port_name(self, port: usize) -> String {
+        match self {
+            SignalKind::DigitalInput => format!("IN[{}]", port),
+            SignalKind::DigitalOutput => format!("OUT[{}]", port),
+            SignalKind::AnalogInput => format!("AIN[{}]", port),
+            SignalKind::AnalogOutput => format!("AOUT[{}]", port),
+        }
+    }
+
+    fn default_value(self) -> SignalValue {
+        if self.is_digital() {
+            SignalValue::Digital(false)
+        } else {
+            SignalValue::Analog(0.0)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SignalValue {
+    Digital(bool),
+    Analog(f32),
+}
+
+impl From<bool> for SignalValue {
+    fn from(value: bool) -> Self {
+        SignalValue::Digital(value)
+    }
+}
+
+impl From<f32> for SignalValue {
+    fn from(value: f32) -> Self {
+        SignalValue::Analog(value)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signal {
+    pub kind: SignalKind,
+    /// The port number programs address the signal with.
+    pub port: usize,
+    pub value: SignalValue,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+/// A change of the value of a signal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignalEvent {
+    pub name: String,
+    pub previous: SignalValue,
+    pub value: SignalValue,
+}
+
+impl SignalEvent {
+    /// The edge of a digital signal.
+    pub fn edge(&self) -> Option<Edge> {
+        match (self.previous, self.value) {
+            (SignalValue::Digital(false), SignalValue::Digital(true)) => Some(Edge::Rising),
+            (SignalValue::Digital(true), SignalValue::Digital(false)) => Some(Edge::Falling),
+            _ => None,
+        }
+    }
+}
+
+/// The named digital and analog signals of a cell, with the changes not yet handled.
+///
+/// Every signal has a port per kind, signals used by port without being declared are added on the fly.
+#[derive(Clone, Debug, Default)]
+pub struct SignalTable {
+    signals: BTreeMap<String, Signal>,
+    events: Vec<SignalEvent>,
+}
+
+impl SignalTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the signal `name`, off or zero.
+    pub fn insert(&mut self, name: impl Into<String>, kind: SignalKind, port: usize) -> Result<(), SignalError> {
+        let name = name.into();
+
+        if self.signals.contains_key(&name) {
+            return Err(SignalError::DuplicateSignal(name));
+        }
+
+        if let Some(other) = self.port_name(kind, port) {
+            return Err(SignalError::DuplicatePort { name: other.to_string(), port });
+        }
+
+        self.signals.insert(name, Signal { kind, port, value: kind.default_value() });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Signal> {
+        self.signals.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Signal> {
+        self.signals.get(name)
+    }
+
+    /// The signals sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Signal)> {
+        self.signals.iter().map(|(name, signal)| (name.as_str(), signal))
+    }
+
+    pub fn len(&self) -> usize {
+        self.signals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signals.is_empty()
+    }
+
+    /// The name of the signal of `kind` on `port`.
+    pub fn port_name(&self, kind: SignalKind, port: usize) -> Option<&str> {
+        self.signals.iter()
+            .find(|(_, signal)| signal.kind == kind && signal.port == port)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn value(&self, name: &str) -> Option<SignalValue> {
+        self.signals.get(name).map(|signal| signal.value)
+    }
+
+    pub fn digital(&self, name: &str) -> Option<bool> {
+        match self.value(name)? {
+            SignalValue::Digital(value) => Some(value),
+            SignalValue::Analog(_) => None,
+        }
+    }
+
+    pub fn analog(&self, name: &str) -> Option<f32> {
+        match self.value(name)? {
+            SignalValue::Analog(value) => Some(value),
+            SignalValue::Digital(_) => None,
+        }
+    }
+
+    /// Checks that the signal `name` is declared, with digital values if `digital` and analog ones otherwise.
+    pub fn check(&self, name: &str, digital: bool) -> Result<(), SignalError> {
+        match self.signals.get(name) {
+            Some(signal) if signal.kind.is_digital() == digital => Ok(()),
+            Some(_) => Err(SignalError::TypeMismatch(name.to_string())),
+            None => Err(SignalError::UnknownSignal(name.to_string())),
+        }
+    }
+
+    /// Sets the signal `name`, recording an event if its value changes.
+    pub fn set(&mut self, name: &str, value: impl Into<SignalValue>) -> Result<(), SignalError> {
+        let value = value.into();
+        let signal = self.signals.get_mut(name).ok_or_else(|| SignalError::UnknownSignal(name.to_string()))?;
+
+        if signal.kind.is_digital() != matches!(value, SignalValue::Digital(_)) {
+            return Err(SignalError::TypeMismatch(name.to_string()));
+        }
+
+        if signal.value != value {
+            self.events.push(SignalEvent { name: name.to_string(), previous: signal.value, value });
+            signal.value = value;
+        }
+
+        Ok(())
+    }
+
+    /// The value of the signal of `kind` on `port`, off or zero if there is none.
+    pub fn port_value(&self, kind: SignalKind, port: usize) -> SignalValue {
+        self.port_name(kind, port)
+            .and_then(|name| self.value(name))
+            .unwrap_or_else(|| kind.default_value())
+    }
+
+    /// Sets the signal of `kind` on `port`, declaring it if there is none.
+    pub fn set_port(&mut self, kind: SignalKind, port: usize, value: impl Into<SignalValue>) -> Result<(), SignalError> {
+        let name = match self.port_name(kind, port) {
+            Some(name) => name.to_string(),
+            None => {
+                let name = kind.port_name(port);
+                self.insert(name.clone(), kind, port)?;
+                name
+            }
+        };
+
+        self.set(&name, value)
+    }
+
+    /// The values of the digital signals of `kind`, by port.
+    pub fn digital_ports(&self, kind: SignalKind) -> Vec<bool> {
+        let mut values = Vec::new();
+
+        for signal in self.signals.values().filter(|signal| signal.kind == kind) {
+            if let SignalValue::Digital(value) = signal.value {
+                if signal.port >= values.len() {
+                    values.resize(signal.port + 1, false);
+                }
+
+                values[signal.port] = value;
+            }
+        }
+
+        values
+    }
+
+    /// The changes since the events were last cleared, in order.
+    pub fn events(&self) -> &[SignalEvent] {
+        &self.events
+    }
+
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+}
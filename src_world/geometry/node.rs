@@ -21,6 +21,7 @@ pub struct EntityWithMaterial {
     pub object: Option<ObjectHandle>,
     pub delta: Isometry3<f32>,
     pub opacity: f32,
+    scale: Vec3,
     material: Handle<BevyMaterial>,
 }
 
@@ -87,6 +88,7 @@ impl EntityWithMaterial {
             base_color: color,
             object,
             delta,
+            scale,
             material: weak_material_handle,
             opacity,
         }
@@ -113,6 +115,9 @@ impl EntityWithMaterial {
                     ob_pos.rotation.k as f32,
                     ob_pos.rotation.w as f32,
                 );
+
+                // Disabled objects are hidden by collapsing them
+                pos.scale = if ob.is_enabled() { self.scale } else { Vec3::ZERO };
             }
         }
     }
@@ -0,0 +1,245 @@
+use na::{Isometry3, Point3, Translation3, UnitQuaternion, Vector3};
+use parry3d::query;
+use parry3d::shape::SharedShape;
+use bluster::data::random::Random;
+use bluster::errors::SignalError;
+use bluster::mesh::{ObjectBuilder, ObjectHandle, ObjectSet};
+use bluster::signals::SignalTable;
+use crate::harness::plugin::HarnessPlugin;
+use crate::harness::RunState;
+
+
+/// Whether a component wired to the digital signal `signal` runs, always without one.
+fn is_enabled(signals: &SignalTable, signal: Option<&str>) -> bool {
+    signal.is_none_or(|name| signals.digital(name) == Some(true))
+}
+
+fn check_signal(signals: &SignalTable, signal: Option<&str>, digital: bool) -> Result<(), SignalError> {
+    signal.map_or(Ok(()), |name| signals.check(name, digital))
+}
+
+/// A belt carrying the free objects lying on it.
+///
+/// An object lies on the belt when its origin is above the top of the belt, within its outline, and the object is
+/// closer to the belt than `tolerance`. The belt is up along its local y axis.
+pub struct Conveyor {
+    pub belt: ObjectHandle,
+    /// The direction of motion, in the belt frame.
+    pub direction: Vector3<f32>,
+    pub speed: f32,
+    pub tolerance: f32,
+    /// The digital signal running the belt, it always runs without one.
+    pub signal: Option<String>,
+}
+
+impl Conveyor {
+    pub fn new(belt: ObjectHandle, direction: Vector3<f32>, speed: f32) -> Self {
+        Self { belt, direction, speed, tolerance: 0.01, signal: None }
+    }
+
+    pub fn with_signal(mut self, signal: impl Into<String>) -> Self {
+        self.signal = Some(signal.into());
+        self
+    }
+
+    /// The objects lying on the belt.
+    pub fn carried(&self, objects: &ObjectSet) -> Vec<ObjectHandle> {
+        let belt = match objects.get(self.belt) {
+            Some(belt) => belt,
+            None => return Vec::new(),
+        };
+        let outline = belt.shape().compute_local_aabb();
+
+        objects.iter()
+            .filter(|(handle, obj)| *handle != self.belt && obj.parent().is_none() && obj.is_enabled())
+            .filter(|(_, obj)| {
+                let origin = belt.position().inverse_transform_point(&Point3::from(obj.position().translation.vector));
+
+                origin.y > outline.maxs.y
+                    && (outline.mins.x..=outline.maxs.x).contains(&origin.x)
+                    && (outline.mins.z..=outline.maxs.z).contains(&origin.z)
+                    && query::distance(belt.position(), belt.shape(), obj.position(), obj.shape())
+                        .is_ok_and(|distance| distance <= self.tolerance)
+            })
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+}
+
+impl HarnessPlugin for Conveyor {
+    fn run_callbacks(&mut self, objects: &mut ObjectSet, signals: &mut SignalTable, state: &RunState) {
+        if !is_enabled(signals, self.signal.as_deref()) {
+            return;
+        }
+
+        let direction = match (objects.get(self.belt), self.direction.try_normalize(f32::EPSILON)) {
+            (Some(belt), Some(direction)) => belt.position().rotation * direction,
+            _ => return,
+        };
+        let offset = Translation3::from(direction * self.speed * state.dt);
+
+        for handle in self.carried(objects) {
+            let position = offset * objects[handle].position();
+            objects[handle].set_position(position);
+        }
+
+        objects.propagate_positions();
+    }
+
+    fn check(&self, signals: &SignalTable) -> Result<(), SignalError> {
+        check_signal(signals, self.signal.as_deref(), true)
+    }
+}
+
+/// Creates parts at regular intervals, at random poses around a nominal one.
+pub struct Spawner {
+    pub part: ObjectBuilder,
+    /// The nominal pose of the parts, the builder position is ignored.
+    pub position: Isometry3<f32>,
+    /// The largest offset from the nominal position along each axis of its frame.
+    pub position_jitter: Vector3<f32>,
+    /// The largest rotation around the y axis of the nominal pose, in radians.
+    pub rotation_jitter: f32,
+    /// The time between two parts, in seconds.
+    pub interval: f32,
+    /// The number of parts after which the spawner stops, unlimited without it.
+    pub max_count: Option<usize>,
+    /// The digital signal enabling the spawner, it always runs without one.
+    pub signal: Option<String>,
+    random: Random,
+    elapsed: f32,
+    count: usize,
+}
+
+impl Spawner {
+    /// A spawner creating a first part at the first step, then one every `interval`.
+    pub fn new(part: ObjectBuilder, position: Isometry3<f32>, interval: f32, seed: u64) -> Self {
+        Self {
+            part,
+            position,
+            position_jitter: Vector3::zeros(),
+            rotation_jitter: 0.0,
+            interval,
+            max_count: None,
+            signal: None,
+            random: Random::new(seed),
+            elapsed: interval,
+            count: 0,
+        }
+    }
+
+    pub fn with_jitter(mut self, position: Vector3<f32>, rotation: f32) -> Self {
+        self.position_jitter = position;
+        self.rotation_jitter = rotation;
+        self
+    }
+
+    pub fn with_signal(mut self, signal: impl Into<String>) -> Self {
+        self.signal = Some(signal.into());
+        self
+    }
+
+    /// The number of parts created so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn spawn(&mut self, objects: &mut ObjectSet) -> ObjectHandle {
+        let jitter = &self.position_jitter;
+        let random = &mut self.random;
+        let offset = Vector3::new(
+            random.range(-jitter.x, jitter.x),
+            random.range(-jitter.y, jitter.y),
+            random.range(-jitter.z, jitter.z),
+        );
+        let angle = random.range(-self.rotation_jitter, self.rotation_jitter);
+        let position = self.position
+            * Translation3::from(offset)
+            * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle);
+
+        self.count += 1;
+        objects.insert(self.part.clone().position(position))
+    }
+}
+
+impl HarnessPlugin for Spawner {
+    fn run_callbacks(&mut self, objects: &mut ObjectSet, signals: &mut SignalTable, state: &RunState) {
+        if !is_enabled(signals, self.signal.as_deref()) || self.max_count.is_some_and(|max| self.count >= max) {
+            return;
+        }
+
+        self.elapsed += state.dt;
+
+        if self.elapsed >= self.interval {
+            self.elapsed = (self.elapsed - self.interval).min(self.interval);
+            self.spawn(objects);
+        }
+    }
+
+    fn check(&self, signals: &SignalTable) -> Result<(), SignalError> {
+        check_signal(signals, self.signal.as_deref(), true)
+    }
+}
+
+/// Removes the free dynamic objects whose origin enters a region, like parts leaving the cell.
+///
+/// Robot links, tools and fixtures are never removed, they aren't dynamic.
+pub struct Sink {
+    /// The region, placed in world space.
+    pub region: SharedShape,
+    pub position: Isometry3<f32>,
+    /// Dynamic objects never removed.
+    pub ignored: Vec<ObjectHandle>,
+    /// The analog signal set to the number of removed objects.
+    pub signal: Option<String>,
+    count: usize,
+}
+
+impl Sink {
+    pub fn new(region: SharedShape, position: Isometry3<f32>) -> Self {
+        Self { region, position, ignored: Vec::new(), signal: None, count: 0 }
+    }
+
+    pub fn ignore(mut self, handle: ObjectHandle) -> Self {
+        self.ignored.push(handle);
+        self
+    }
+
+    pub fn with_signal(mut self, signal: impl Into<String>) -> Self {
+        self.signal = Some(signal.into());
+        self
+    }
+
+    /// The number of objects removed so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl HarnessPlugin for Sink {
+    fn run_callbacks(&mut self, objects: &mut ObjectSet, signals: &mut SignalTable, _state: &RunState) {
+        let removed: Vec<_> = objects.iter()
+            .filter(|(handle, obj)| obj.is_dynamic() && obj.parent().is_none() && !self.ignored.contains(handle))
+            .filter(|(_, obj)| {
+                let origin = Point3::from(obj.position().translation.vector);
+                self.region.contains_point(&self.position, &origin)
+            })
+            .map(|(handle, _)| handle)
+            .collect();
+
+        for handle in removed {
+            if objects.remove(handle).is_some() {
+                self.count += 1;
+            }
+        }
+
+        // The signal is checked when the sink is added, it can only have been removed since
+        if let Some(signal) = &self.signal {
+            let _ = signals.set(signal, self.count as f32);
+        }
+    }
+
+    fn check(&self, signals: &SignalTable) -> Result<(), SignalError> {
+        check_signal(signals, self.signal.as_deref(), false)
+    }
+}
@@ -1,10 +1,29 @@
+use na::{DVector, Isometry3};
+use bluster::errors::{PlanningError, ProgramError, SignalError};
+use bluster::frames::FrameTree;
+use bluster::pipeline::collision_pipeline::CollisionPipeline;
+use bluster::pipeline::continuous_collision::{first_impact, Impact, SweptObject};
+use bluster::pipeline::distance_monitor::{DistanceMonitor, SafetyStatus};
+use bluster::pipeline::physics_pipeline::PhysicsPipeline;
+use bluster::pipeline::query_pipeline::QueryPipeline;
+use bluster::planning::{ConstrainedChecker, PathSmoother, RrtConnect, SceneChecker, StateChecker, TaskConstraint};
 use bluster::prelude::ObjectSet;
+use bluster::program::ProgramState;
+use bluster::signals::{Sensor, SignalKind, SignalTable};
 use plugin::HarnessPlugin;
 pub mod plugin;
+mod components;
+mod robot;
+mod robot_set;
+
+pub use self::components::{Conveyor, Sink, Spawner};
+pub use self::robot::Robot;
+pub use self::robot_set::{RobotHandle, RobotSet};
 
 pub struct RunState {
     pub timestep_id: usize,
     pub time: f32,
+    pub dt: f32,
 }
 
 impl RunState {
@@ -12,6 +31,7 @@ impl RunState {
         RunState {
             timestep_id: 0,
             time: 0.0,
+            dt: 1.0 / 60.0,
         }
     }
 
@@ -20,26 +40,28 @@ impl RunState {
     }
 }
 
-pub struct Robot {
-    pub joint: f32,
-    pub position: i32,
-    pub tool: usize
-}
-
-impl Robot {
-    pub fn new() -> Self {
-        Self {
-            joint: 0.0,
-            position: 0,
-            tool: 0,
-        }
-    }
+/// The first contact found while checking a program.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ProgramImpact {
+    /// The program time of the contact, in seconds.
+    pub time: f32,
+    /// The line of the motion causing it.
+    pub line: Option<usize>,
+    pub impact: Impact,
 }
 
 pub struct Harness {
     pub objects: ObjectSet,
     pub state: RunState,
-    pub robot: Robot,
+    pub robots: RobotSet,
+    pub frames: FrameTree,
+    pub query_pipeline: QueryPipeline,
+    pub collisions: CollisionPipeline,
+    pub distances: DistanceMonitor,
+    pub physics: PhysicsPipeline,
+    /// The I/O of the cell, the digital ports are the ones of the robot programs.
+    pub signals: SignalTable,
+    sensors: Vec<Box<dyn Sensor>>,
     plugins: Vec<Box<dyn HarnessPlugin>>
 }
 
@@ -49,7 +71,14 @@ impl Harness {
 
         Harness {
             state,
-            robot: Robot::new(),
+            robots: RobotSet::new(),
+            frames: FrameTree::new(),
+            query_pipeline: QueryPipeline::new(),
+            collisions: CollisionPipeline::new(),
+            distances: DistanceMonitor::new(),
+            physics: PhysicsPipeline::new(),
+            signals: SignalTable::new(),
+            sensors: Vec::new(),
             objects: ObjectSet::new(),
             plugins: Vec::new(),
         }
@@ -64,8 +93,183 @@ impl Harness {
 
     pub fn init_world(&mut self, objects: ObjectSet) {
         self.objects = objects;
+        self.robots = RobotSet::new();
+        self.frames = FrameTree::new();
+        self.collisions = CollisionPipeline::new();
+        self.distances = DistanceMonitor::new();
+        self.physics.reset();
+        self.signals = SignalTable::new();
+        self.sensors.clear();
+        self.plugins.clear();
 
         self.state.timestep_id = 0;
         self.state.time = 0.0;
     }
+
+    /// Records the object positions and the robot frames at the current timestep.
+    pub fn sync_frames(&mut self) {
+        self.frames.update(&self.objects, self.state.timestep_id);
+
+        for (_, robot) in self.robots.iter_mut() {
+            robot.publish_frames(&mut self.frames);
+        }
+    }
+
+    /// Finds the objects in contact and the clearance to the obstacles at their current positions.
+    pub fn detect_collisions(&mut self) {
+        self.collisions.step(&self.objects, &mut self.query_pipeline);
+        self.distances.step(&self.objects, self.state.timestep_id);
+    }
+
+    /// Runs the program of `robot` from its start on a copy of the scene, for at most `duration` seconds,
+    /// and returns the first contact of the moving objects, checked continuously between steps.
+    pub fn check_program(&self, mut robot: Robot, duration: f32) -> Result<Option<ProgramImpact>, ProgramError> {
+        let mut objects = self.objects.clone();
+        let mut query_pipeline = QueryPipeline::new();
+        let robot_objects = self.robots.objects();
+        let dt = self.state.dt;
+        let mut time = 0.0;
+
+        if let Some(program) = &mut robot.program {
+            program.reset();
+        }
+
+        robot.program_error = None;
+
+        while time < duration {
+            if !matches!(robot.program_state(), Some(ProgramState::Ready | ProgramState::Moving | ProgramState::Waiting)) {
+                break;
+            }
+
+            let line = robot.program.as_ref().and_then(|program| program.current_line());
+            let start: Vec<_> = objects.iter().map(|(handle, obj)| (handle, *obj.position())).collect();
+            query_pipeline.update(&objects);
+
+            robot.step(dt, &objects);
+            robot.apply(&mut objects);
+            objects.propagate_positions();
+            robot.actuate_gripper(&mut objects, &mut query_pipeline, &robot_objects);
+
+            if let Some(err) = robot.program_error.take() {
+                return Err(err);
+            }
+
+            let swept: Vec<_> = start.into_iter()
+                .filter(|(handle, start)| objects[*handle].position() != start)
+                .map(|(handle, start)| SweptObject::new(handle, start, *objects[handle].position()))
+                .collect();
+
+            if let Some(impact) = first_impact(&objects, &query_pipeline, &swept) {
+                return Ok(Some(ProgramImpact { time: time + impact.time * dt, line, impact }));
+            }
+
+            time += dt;
+        }
+
+        Ok(None)
+    }
+
+    /// Plans a collision-free joint path of `robot` from its current configuration to `goal`, carrying the active tool.
+    ///
+    /// The active tool center point follows `constraints` along the path, the start and the goal have to satisfy them.
+    /// The other robots are obstacles at their current configurations.
+    pub fn plan_to(
+        &mut self,
+        robot: RobotHandle,
+        goal: &DVector<f32>,
+        constraints: &[TaskConstraint],
+    ) -> Result<Vec<DVector<f32>>, PlanningError> {
+        self.query_pipeline.update(&self.objects);
+
+        let robot = &self.robots[robot];
+        let mut checker = SceneChecker::new(&robot.chain, &self.objects, &self.query_pipeline);
+
+        if let Some(tool) = robot.tool_object() {
+            checker = checker.attach(tool, Isometry3::identity());
+        }
+
+        let constrained;
+        let checker: &dyn StateChecker = if constraints.is_empty() {
+            &checker
+        } else {
+            constrained = ConstrainedChecker::new(&checker, &robot.chain, constraints.to_vec());
+            &constrained
+        };
+
+        let path = RrtConnect::default().plan(checker, &robot.joints, goal)?;
+        Ok(PathSmoother::default().smooth(checker, &path))
+    }
+
+    /// Adds a sensor updated at the end of every step, its signals have to be declared in `signals`.
+    pub fn add_sensor(&mut self, sensor: impl Sensor + 'static) -> Result<(), SignalError> {
+        sensor.check(&self.signals)?;
+        self.sensors.push(Box::new(sensor));
+
+        Ok(())
+    }
+
+    /// Adds a plugin run at the end of every step, its signals have to be declared in `signals`.
+    pub fn add_plugin(&mut self, plugin: impl HarnessPlugin + 'static) -> Result<(), SignalError> {
+        plugin.check(&self.signals)?;
+        self.plugins.push(Box::new(plugin));
+
+        Ok(())
+    }
+
+    /// Advances the simulation by one timestep.
+    pub fn step(&mut self) {
+        let dt = self.state.dt;
+
+        // The robots hold their positions while an obstacle is within a stop margin
+        let stopped = self.distances.status() == SafetyStatus::Stop;
+
+        for (_, robot) in self.robots.iter_mut() {
+            // Every robot reads and writes the digital ports of the signal table, after the robots before it
+            robot.inputs = self.signals.digital_ports(SignalKind::DigitalInput);
+            robot.outputs = self.signals.digital_ports(SignalKind::DigitalOutput);
+            let outputs = robot.outputs.clone();
+
+            if !stopped {
+                robot.step(dt, &self.objects);
+            }
+
+            for (port, value) in robot.outputs.iter().enumerate() {
+                if outputs.get(port) != Some(value) {
+                    let _ = self.signals.set_port(SignalKind::DigitalOutput, port, *value);
+                }
+            }
+
+            robot.apply(&mut self.objects);
+        }
+
+        self.objects.propagate_positions();
+
+        // No robot grasps the links or tools of another one
+        let robot_objects = self.robots.objects();
+
+        for (_, robot) in self.robots.iter_mut() {
+            robot.actuate_gripper(&mut self.objects, &mut self.query_pipeline, &robot_objects);
+        }
+
+        // The dynamic objects react to the new positions of the robot, then carry their children
+        self.physics.step(&mut self.objects, &mut self.query_pipeline, dt);
+        self.objects.propagate_positions();
+
+        self.query_pipeline.update(&self.objects);
+
+        for sensor in &mut self.sensors {
+            sensor.sense(&self.objects, &self.query_pipeline, &mut self.signals);
+        }
+
+        for plugin in &mut self.plugins {
+            plugin.run_callbacks(&mut self.objects, &mut self.signals, &self.state);
+        }
+
+        self.signals.clear_events();
+
+        self.state.timestep_id += 1;
+        self.state.time += dt;
+        self.sync_frames();
+        self.detect_collisions();
+    }
 }
\ No newline at end of file
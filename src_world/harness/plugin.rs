@@ -1,10 +1,20 @@
+use bluster::errors::SignalError;
 use bluster::mesh::ObjectSet;
+use bluster::signals::SignalTable;
 use crate::harness::RunState;
 
 pub trait HarnessPlugin {
+    /// Runs at the end of every step. The signal events are the changes since the previous step.
     fn run_callbacks(
         &mut self,
         objects: &mut ObjectSet,
+        signals: &mut SignalTable,
         state: &RunState,
     );
+
+    /// Checks that the signals the plugin reads or sets are declared in `signals` with the right kind, before it
+    /// is used.
+    fn check(&self, _signals: &SignalTable) -> Result<(), SignalError> {
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,397 @@
+use std::collections::{BTreeMap, BTreeSet};
+use na::{DVector, Isometry3, Vector3};
+use bluster::dynamics::{ForwardDynamics, InverseDynamics, JointDrives};
+use bluster::errors::ProgramError;
+use bluster::frames::{FrameParent, FrameTree};
+use bluster::kinematics::{AllowedCollisionMatrix, Gripper, KinematicChain, Tool, ToolTable, WorkObject};
+use bluster::mesh::{MassProperties, ObjectBuilder, ObjectHandle, ObjectSet};
+use bluster::pipeline::query_pipeline::QueryPipeline;
+use bluster::program::{Interpreter, ProgramContext, ProgramState};
+
+#[derive(Clone)]
+pub struct Robot {
+    /// Prefixes the frames the robot publishes, unique among the robots of a cell.
+    pub name: String,
+    pub chain: KinematicChain,
+    pub joints: DVector<f32>,
+    pub velocities: DVector<f32>,
+    /// The joint torques applied when the robot is simulated with `dynamics`.
+    pub torques: DVector<f32>,
+    /// Moves the joints from the torques, when set.
+    pub dynamics: Option<ForwardDynamics>,
+    /// Computes the torques following the program when the robot has dynamics, the torques are applied as set otherwise.
+    pub drives: Option<JointDrives>,
+    /// The joint positions commanded by the program to the drives.
+    pub command: DVector<f32>,
+    pub tools: ToolTable,
+    /// The objects held by the active tool.
+    pub gripper: Gripper,
+    /// The last gripper command of the program, applied by `actuate_gripper`.
+    gripper_command: Option<bool>,
+    /// The link pairs left out of self-collision checks, only adjacent links when there is none.
+    pub collision_matrix: Option<AllowedCollisionMatrix>,
+    /// The user frames selected by the `BASE n` program command, base 0 being the robot base.
+    pub work_objects: BTreeMap<usize, WorkObject>,
+    tool: usize,
+    tool_objects: BTreeMap<usize, ObjectHandle>,
+    /// World-space work object frames, resolved at the start of each step.
+    base_frames: BTreeMap<usize, Isometry3<f32>>,
+    /// The frames set by `publish_frames`, the robot never takes over the other ones.
+    published_frames: BTreeSet<String>,
+    pub program: Option<Interpreter>,
+    pub program_error: Option<ProgramError>,
+    pub inputs: Vec<bool>,
+    pub outputs: Vec<bool>,
+}
+
+impl Default for Robot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Robot {
+    pub fn new() -> Self {
+        Self::with_chain(KinematicChain::default())
+    }
+
+    pub fn with_chain(chain: KinematicChain) -> Self {
+        Self {
+            name: "robot".to_string(),
+            joints: chain.zeros(),
+            velocities: chain.zeros(),
+            torques: chain.zeros(),
+            dynamics: None,
+            drives: None,
+            command: chain.zeros(),
+            chain,
+            tools: ToolTable::new(),
+            gripper: Gripper::new(),
+            gripper_command: None,
+            collision_matrix: None,
+            work_objects: BTreeMap::new(),
+            tool: 0,
+            tool_objects: BTreeMap::new(),
+            base_frames: BTreeMap::new(),
+            published_frames: BTreeSet::new(),
+            program: None,
+            program_error: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn insert_tool(&mut self, index: usize, tool: Tool) {
+        self.tools.insert(index, tool);
+
+        if index == self.tool {
+            self.chain.set_tool(self.tools.tcp(index).unwrap_or_else(Isometry3::identity));
+        }
+    }
+
+    pub fn tool(&self) -> usize {
+        self.tool
+    }
+
+    /// Makes `index` the active tool, returning `false` if the tool table doesn't have it.
+    pub fn set_tool(&mut self, index: usize) -> bool {
+        match self.tools.tcp(index) {
+            Some(tcp) => {
+                self.tool = index;
+                self.chain.set_tool(tcp);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The object of the active tool, if it has a shape.
+    pub fn tool_object(&self) -> Option<ObjectHandle> {
+        self.tool_objects.get(&self.tool).copied()
+    }
+
+    /// The mass properties of the active tool, from its declared mass, and of the grasped objects in the flange frame,
+    /// carried as the payload by the dynamics.
+    pub fn payload(&self, objects: &ObjectSet) -> Option<MassProperties> {
+        let flange = self.chain.flange_pose(&self.joints);
+        let grasped = self.gripper.grasped().iter()
+            .filter_map(|handle| objects.get(*handle))
+            .map(|obj| obj.mass_properties().transform_by(&flange.inv_mul(obj.position())));
+
+        // The declared load of the tool, a point mass at its center of gravity
+        self.tools.get(self.tool)
+            .filter(|tool| tool.mass > 0.0)
+            .map(|tool| MassProperties::new(tool.center_of_mass, tool.mass, Vector3::zeros()))
+            .into_iter()
+            .chain(grasped)
+            .reduce(|total, properties| total + properties)
+    }
+
+    /// Closes the gripper of the active tool, returning the objects it grasps.
+    ///
+    /// Tools with a gripper model grasp what the model holds, the other ones the dynamic objects touching their grip
+    /// region, or their shape without one. The objects are attached to the tool object, or to the last link for tools without a shape. The objects of
+    /// the robot and the ones of `ignored`, like the other robots of the cell, are never grasped. `query_pipeline` has to be up to
+    /// date with the scene.
+    pub fn grip(&mut self, objects: &mut ObjectSet, query_pipeline: &QueryPipeline, ignored: &[ObjectHandle]) -> Vec<ObjectHandle> {
+        let tool = match self.tools.get(self.tool) {
+            Some(tool) => tool,
+            None => return Vec::new(),
+        };
+        let parent = match self.tool_object().or_else(|| self.chain.links.iter().rev().find_map(|link| link.object)) {
+            Some(parent) => parent,
+            None => return Vec::new(),
+        };
+        let flange = self.chain.flange_pose(&self.joints);
+        let ignored: Vec<_> = self.objects().into_iter().chain(ignored.iter().copied()).collect();
+
+        match (&tool.gripper, tool.grip_region.as_ref().or(tool.shape.as_ref())) {
+            (Some(model), _) => self.gripper.close(model, objects, query_pipeline, parent, &flange, &ignored),
+            (None, Some(region)) => self.gripper.grasp(objects, query_pipeline, parent, (&**region, &flange), &ignored),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Opens the gripper, leaving the grasped objects where they are.
+    pub fn release(&mut self, objects: &mut ObjectSet) -> Vec<ObjectHandle> {
+        self.gripper.release(objects)
+    }
+
+    /// Applies the last gripper command of the program, if any, after the robot is moved by `apply`.
+    ///
+    /// The objects of `ignored` are never grasped, see `grip`.
+    pub fn actuate_gripper(&mut self, objects: &mut ObjectSet, query_pipeline: &mut QueryPipeline, ignored: &[ObjectHandle]) {
+        match self.gripper_command.take() {
+            Some(true) => {
+                query_pipeline.update(objects);
+                self.grip(objects, query_pipeline, ignored);
+            }
+            Some(false) => {
+                self.release(objects);
+            }
+            None => {}
+        }
+    }
+
+    /// Excludes the link pairs that don't need to be checked from collision detection.
+    pub fn filter_self_collisions(&self, objects: &mut ObjectSet) {
+        match &self.collision_matrix {
+            Some(matrix) => matrix.apply(&self.chain, objects),
+            None => self.chain.exclude_adjacent_links(objects),
+        }
+    }
+
+    /// Adds an object for the shape of every tool that doesn't have one yet.
+    ///
+    /// Tools never collide with the last link they are mounted on.
+    pub fn spawn_tools(&mut self, objects: &mut ObjectSet) {
+        let last_link = self.chain.links.iter().rev().find_map(|link| link.object);
+
+        for (index, tool) in self.tools.iter() {
+            if let (Some(shape), false) = (&tool.shape, self.tool_objects.contains_key(&index)) {
+                let handle = objects.insert(ObjectBuilder::new(shape.clone()));
+                self.tool_objects.insert(index, handle);
+
+                if let Some(link) = last_link {
+                    objects.exclude_pair(link, handle);
+                }
+            }
+        }
+    }
+
+    /// The objects of the links and of the tools.
+    pub fn objects(&self) -> Vec<ObjectHandle> {
+        self.chain.links.iter()
+            .filter_map(|link| link.object)
+            .chain(self.tool_objects.values().copied())
+            .collect()
+    }
+
+    pub fn load_program(&mut self, program: Interpreter) {
+        self.program = Some(program);
+        self.program_error = None;
+    }
+
+    pub fn program_state(&self) -> Option<ProgramState> {
+        self.program.as_ref().map(|p| p.state())
+    }
+
+    /// Whether the program commands the drives instead of setting the joints.
+    pub fn is_driven(&self) -> bool {
+        self.dynamics.is_some() && self.drives.is_some()
+    }
+
+    /// The difference between the commanded and the actual joint positions, zero without drives.
+    pub fn tracking_error(&self) -> DVector<f32> {
+        if self.is_driven() && self.command.len() == self.joints.len() {
+            &self.command - &self.joints
+        } else {
+            DVector::zeros(self.joints.len())
+        }
+    }
+
+    /// Advances the robot by `dt` seconds.
+    ///
+    /// Without dynamics, the program sets the joints. With dynamics, the joints move under the torques, computed by
+    /// the drives from the program command and the inverse dynamics feedforward if there are drives.
+    pub fn step(&mut self, dt: f32, objects: &ObjectSet) {
+        let dynamics = match self.dynamics {
+            Some(dynamics) => dynamics,
+            None => return self.run_program(dt, objects),
+        };
+        let payload = self.payload(objects);
+        let substeps = dynamics.substeps.max(1);
+        let h = dt / substeps as f32;
+
+        let mut setpoints = Vec::new();
+
+        if self.drives.is_some() {
+            self.run_program(dt, objects);
+
+            if let Some(drives) = &mut self.drives {
+                setpoints = drives.setpoints(&self.command, dt);
+            }
+
+            let velocity = DVector::from_iterator(setpoints.len(), setpoints.iter().map(|s| s.velocity));
+            let acceleration = DVector::from_iterator(setpoints.len(), setpoints.iter().map(|s| s.acceleration));
+            let feedforward = InverseDynamics { gravity: dynamics.gravity }
+                .torques(&self.chain, objects, &self.command, &velocity, &acceleration, payload.as_ref());
+
+            if let Ok(feedforward) = feedforward {
+                for (setpoint, torque) in setpoints.iter_mut().zip(feedforward.iter()) {
+                    setpoint.torque = *torque;
+                }
+            }
+        }
+
+        // The servo loops run at the rate of the integration
+        for _ in 0..substeps {
+            if let Some(drives) = &mut self.drives {
+                self.torques = drives.torques(&setpoints, &self.joints, &self.velocities, h);
+            }
+
+            // Torques of the wrong length leave the robot in place
+            let (mut joints, mut velocities) = (self.joints.clone(), self.velocities.clone());
+
+            if dynamics.integrate(&self.chain, objects, &mut joints, &mut velocities, &self.torques, payload.as_ref(), h).is_err() {
+                break;
+            }
+
+            self.joints = joints;
+            self.velocities = velocities;
+        }
+    }
+
+    /// Runs the loaded program for `dt` seconds.
+    fn run_program(&mut self, dt: f32, objects: &ObjectSet) {
+        self.base_frames = self.work_objects.iter()
+            .filter_map(|(index, wobj)| Some((*index, wobj.world_frame(objects)?)))
+            .collect();
+
+        if let Some(mut program) = self.program.take() {
+            let tool = program.active_tool();
+
+            if let Err(err) = program.step(self, dt) {
+                self.program_error = Some(err);
+            }
+
+            // A tool chosen outside the program stays active until the program selects one
+            if program.active_tool() != tool {
+                self.set_tool(program.active_tool());
+            }
+
+            self.program = Some(program);
+        }
+    }
+
+    /// Publishes the `<name>_base`, `<name>_flange` and `<name>_tcp` frames and the work object frames as
+    /// `<name>_<work object>`.
+    ///
+    /// Frames of the same names that the robot didn't publish first, like ones defined by the user, are left
+    /// untouched.
+    pub fn publish_frames(&mut self, frames: &mut FrameTree) {
+        let flange_name = format!("{}_flange", self.name);
+        let robot_frames = [
+            (format!("{}_base", self.name), FrameParent::World, self.chain.base),
+            (flange_name.clone(), FrameParent::World, self.chain.flange_pose(&self.joints)),
+            (format!("{}_tcp", self.name), FrameParent::Frame(flange_name), self.chain.tool),
+        ];
+        let wobj_frames = self.work_objects.values().map(|wobj| {
+            let parent = wobj.object.map(FrameParent::Object).unwrap_or(FrameParent::World);
+            (format!("{}_{}", self.name, wobj.name), parent, wobj.frame)
+        });
+
+        for (name, parent, transform) in robot_frames.into_iter().chain(wobj_frames).collect::<Vec<_>>() {
+            if frames.contains(&name) && !self.published_frames.contains(&name) {
+                continue;
+            }
+
+            if frames.set_frame(&name, parent, transform).is_ok() {
+                self.published_frames.insert(name);
+            }
+        }
+    }
+
+    /// Moves the link objects to the current joint positions and shows the active tool only.
+    pub fn apply(&self, objects: &mut ObjectSet) {
+        self.chain.apply(&self.joints, objects);
+
+        let flange = self.chain.flange_pose(&self.joints);
+
+        for (index, handle) in &self.tool_objects {
+            if let Some(object) = objects.get_mut(*handle) {
+                object.set_enabled(*index == self.tool);
+                object.set_position(flange);
+            }
+        }
+    }
+}
+
+impl ProgramContext for Robot {
+    fn chain(&self) -> &KinematicChain {
+        &self.chain
+    }
+
+    fn joints(&self) -> &DVector<f32> {
+        if self.is_driven() {
+            &self.command
+        } else {
+            &self.joints
+        }
+    }
+
+    fn set_joints(&mut self, joints: DVector<f32>) {
+        if self.is_driven() {
+            self.command = joints;
+        } else {
+            self.joints = joints;
+        }
+    }
+
+    fn tool_frame(&self, tool: usize) -> Option<Isometry3<f32>> {
+        self.tools.tcp(tool)
+    }
+
+    fn base_frame(&self, base: usize) -> Option<Isometry3<f32>> {
+        match base {
+            0 => Some(self.chain.base),
+            _ => self.base_frames.get(&base).copied(),
+        }
+    }
+
+    fn digital_input(&self, port: usize) -> bool {
+        self.inputs.get(port).copied().unwrap_or(false)
+    }
+
+    fn set_digital_output(&mut self, port: usize, value: bool) {
+        if port >= self.outputs.len() {
+            self.outputs.resize(port + 1, false);
+        }
+
+        self.outputs[port] = value;
+    }
+
+    fn set_gripper(&mut self, closed: bool) {
+        self.gripper_command = Some(closed);
+    }
+}
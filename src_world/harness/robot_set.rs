@@ -0,0 +1,92 @@
+use std::ops::{Index, IndexMut};
+use bluster::data::space::{self, Space};
+use bluster::mesh::ObjectHandle;
+use crate::harness::Robot;
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct RobotHandle(pub space::Index);
+
+/// The robots of a cell, each with its own chain, tools and program.
+#[derive(Clone)]
+pub struct RobotSet {
+    robots: Space<Robot>,
+}
+
+impl Default for RobotSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RobotSet {
+    pub fn new() -> Self {
+        Self { robots: Space::new() }
+    }
+
+    /// Adds a robot, renamed with a numbered suffix if another robot already has its name.
+    ///
+    /// The robot frames are named after it, so names have to be unique in the cell.
+    pub fn insert(&mut self, mut robot: Robot) -> RobotHandle {
+        let taken = |name: &str| self.robots.iter().any(|(_, other)| other.name == name);
+
+        if taken(&robot.name) {
+            let name = (2..).map(|i| format!("{}_{}", robot.name, i)).find(|name| !taken(name)).unwrap();
+            robot.name = name;
+        }
+
+        RobotHandle(self.robots.insert(robot))
+    }
+
+    /// Removes a robot, its link and tool objects stay in the scene.
+    pub fn remove(&mut self, handle: RobotHandle) -> Option<Robot> {
+        self.robots.remove(handle.0)
+    }
+
+    pub fn get(&self, handle: RobotHandle) -> Option<&Robot> {
+        self.robots.get(handle.0)
+    }
+
+    pub fn get_mut(&mut self, handle: RobotHandle) -> Option<&mut Robot> {
+        self.robots.get_mut(handle.0)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (RobotHandle, &Robot)> {
+        self.robots.iter().map(|(index, robot)| (RobotHandle(index), robot))
+    }
+
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = (RobotHandle, &mut Robot)> {
+        self.robots.iter_mut().map(|(index, robot)| (RobotHandle(index), robot))
+    }
+
+    pub fn handles(&self) -> impl Iterator<Item = RobotHandle> + '_ {
+        self.iter().map(|(handle, _)| handle)
+    }
+
+    /// The link and tool objects of every robot.
+    pub fn objects(&self) -> Vec<ObjectHandle> {
+        self.iter().flat_map(|(_, robot)| robot.objects()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.robots.iter().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Index<RobotHandle> for RobotSet {
+    type Output = Robot;
+
+    fn index(&self, index: RobotHandle) -> &Self::Output {
+        &self.robots[index.0]
+    }
+}
+
+impl IndexMut<RobotHandle> for RobotSet {
+    fn index_mut(&mut self, index: RobotHandle) -> &mut Self::Output {
+        &mut self.robots[index.0]
+    }
+}
@@ -118,6 +118,25 @@ impl RenderManager {
         }
     }
 
+    /// Whether a node already renders `handle`.
+    pub fn contains(&self, handle: ObjectHandle) -> bool {
+        self.o2sn.values().flatten().any(|node| node.object == Some(handle))
+    }
+
+    /// Despawns the nodes of an object removed from the scene.
+    pub fn remove_object(&mut self, commands: &mut Commands, handle: ObjectHandle) {
+        for nodes in self.o2sn.values_mut() {
+            nodes.retain(|node| {
+                if node.object == Some(handle) {
+                    commands.entity(node.entity).despawn();
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
+
     pub fn prefab_meshes(&self) -> &HashMap<ShapeType, Handle<Mesh>> {
         &self.prefab_meshes
     }
@@ -1,10 +1,53 @@
 use bevy_egui::{egui, EguiContext, egui::Slider};
-use crate::harness::Harness;
+use bluster::dynamics::{ForwardDynamics, InverseDynamics};
+use bluster::kinematics::WorkObject;
+use bluster::pipeline::distance_monitor::SafetyStatus;
+use bluster::planning::ptp_commands;
+use bluster::program::{self, Interpreter, Program};
+use bluster::program::export::{CellData, Krl, Rapid, UrScript};
+use bluster::program::import::{Imported, Importer};
+use bluster::signals::SignalValue;
+use crate::harness::{Harness, Robot, RobotHandle};
 use crate::world::ActionFlags;
 
 
 use crate::WorldState;
 
+const LANGUAGES: [&str; 4] = ["bluster", "KRL", "RAPID", "URScript"];
+
+/// How much of a program the Check button simulates, in seconds.
+const CHECK_DURATION: f32 = 60.0;
+
+/// Parses the program text in its selected language.
+fn load_program(state: &WorldState) -> Result<Imported, String> {
+    let importer: &dyn Importer = match state.program_language {
+        1 => &Krl,
+        2 => &Rapid,
+        3 => &UrScript,
+        _ => {
+            return program::parse("main", &state.program_text)
+                .map(|program| Imported { program, cell: CellData::default(), warnings: Vec::new() })
+                .map_err(|err| err.to_string());
+        }
+    };
+
+    importer.import("main", &state.program_text, 1.0).map_err(|err| err.to_string())
+}
+
+/// Loads an imported program and the frames of its cell into `robot`.
+fn install_program(robot: &mut Robot, imported: Imported) {
+    robot.tools.set_tcp_frames(&imported.cell.tools);
+
+    // Frames of the cell take precedence over the ones written in the program
+    for (index, frame) in imported.cell.bases {
+        let frame = robot.chain.base * frame;
+        robot.work_objects.entry(index)
+            .or_insert_with(|| WorkObject::new(format!("Base {}", index), frame));
+    }
+
+    robot.load_program(Interpreter::new(imported.program));
+}
+
 pub fn update_ui(ui_ctx: &mut EguiContext, state: &mut WorldState, harness: &mut Harness) {
     egui::Window::new("Parameters").show(ui_ctx.ctx_mut(), |ui| {
         let mut chaged = false;
@@ -27,13 +70,257 @@ pub fn update_ui(ui_ctx: &mut EguiContext, state: &mut WorldState, harness: &mut
 
         ui.separator();
 
-        let robot = &mut harness.robot;
+        // Every robot of the cell with the state of its program, the selected one is controlled below
+        if state.selected_robot.is_none_or(|handle| harness.robots.get(handle).is_none()) {
+            state.selected_robot = harness.robots.handles().next();
+        }
 
+        for (handle, robot) in harness.robots.iter() {
+            let status = robot.program.as_ref()
+                .map(|program| format!("{:?}", program.state()))
+                .unwrap_or_else(|| "No program".to_string());
 
-        ui.add(Slider::new(&mut robot.joint, -90.0..=90.0)
-            .fixed_decimals(3)
-            .text("Joint 1"));
+            if ui.selectable_label(state.selected_robot == Some(handle), format!("{}: {}", robot.name, status)).clicked() {
+                state.selected_robot = Some(handle);
+            }
+        }
+
+        if let Some(handle) = state.selected_robot {
+            ui.separator();
+            robot_ui(ui, state, harness, handle);
+        }
+
+        // Digital signals can be forced from here, to test interlocks
+        if !harness.signals.is_empty() {
+            ui.separator();
+            let mut forced = Vec::new();
+
+            for (name, signal) in harness.signals.iter() {
+                match signal.value {
+                    SignalValue::Digital(mut value) => {
+                        if ui.checkbox(&mut value, name).changed() {
+                            forced.push((name.to_string(), value));
+                        }
+                    }
+                    SignalValue::Analog(value) => {
+                        ui.label(format!("{}: {:.3}", name, value));
+                    }
+                }
+            }
+
+            for (name, value) in forced {
+                let _ = harness.signals.set(&name, value);
+            }
+        }
 
         ui.separator();
+
+        let mut collisions: Vec<_> = harness.collisions.intersecting_pairs()
+            .map(|pair| (pair.object1, pair.object2, pair.penetration_depth().unwrap_or(0.0)))
+            .collect();
+        collisions.sort_by_key(|(object1, object2, _)| (*object1, *object2));
+
+        if collisions.is_empty() {
+            ui.label("No collisions");
+        }
+
+        for (object1, object2, depth) in collisions {
+            ui.colored_label(egui::Color32::RED, format!(
+                "Object {} hits object {}, depth {:.3}",
+                object1.0.into_raw_parts().0, object2.0.into_raw_parts().0, depth,
+            ));
+        }
+
+        for clearance in harness.distances.clearances() {
+            let minimum = harness.distances.minimum(clearance.obstacle)
+                .map(|(minimum, _)| minimum.distance)
+                .unwrap_or(clearance.distance);
+            let text = format!(
+                "Object {}: clearance {:.3}, minimum {:.3}",
+                clearance.obstacle.0.into_raw_parts().0, clearance.distance, minimum,
+            );
+
+            match clearance.status {
+                SafetyStatus::Clear => ui.label(text),
+                SafetyStatus::Warning => ui.colored_label(egui::Color32::YELLOW, text),
+                SafetyStatus::Stop => ui.colored_label(egui::Color32::RED, text),
+            };
+        }
     });
-}
\ No newline at end of file
+}
+
+/// The joints, tools and program of one robot.
+fn robot_ui(ui: &mut egui::Ui, state: &mut WorldState, harness: &mut Harness, handle: RobotHandle) {
+    let robot = &mut harness.robots[handle];
+
+    for (i, joint) in robot.joints.iter_mut().enumerate() {
+        let mut degrees = joint.to_degrees();
+
+        if ui.add(Slider::new(&mut degrees, -180.0..=180.0)
+            .fixed_decimals(3)
+            .text(format!("Joint {}", i + 1)))
+            .changed() {
+            *joint = degrees.to_radians();
+        }
+    }
+
+    let mut tool = robot.tool();
+    let tool_name = |index: usize| robot.tools.get(index)
+        .map(|tool| tool.name.clone())
+        .unwrap_or_else(|| "Flange".to_string());
+
+    egui::ComboBox::from_label("Tool")
+        .selected_text(tool_name(tool))
+        .show_ui(ui, |ui| {
+            if robot.tools.get(0).is_none() {
+                ui.selectable_value(&mut tool, 0, tool_name(0));
+            }
+
+            for (index, _) in robot.tools.iter() {
+                ui.selectable_value(&mut tool, index, tool_name(index));
+            }
+        });
+
+    if tool != robot.tool() {
+        robot.set_tool(tool);
+    }
+
+    let mut dynamics = robot.dynamics.is_some();
+
+    if ui.checkbox(&mut dynamics, "Dynamics").changed() {
+        robot.dynamics = dynamics.then(ForwardDynamics::default);
+        robot.velocities = robot.chain.zeros();
+        robot.torques = robot.chain.zeros();
+        robot.command = robot.joints.clone();
+
+        if let Some(drives) = &mut robot.drives {
+            drives.reset();
+        }
+    }
+
+    if robot.is_driven() {
+        ui.label(format!("Tracking error: {:.4}", robot.tracking_error().amax()));
+    }
+
+    if !robot.gripper.grasped().is_empty() {
+        ui.label(format!("Grasped objects: {}", robot.gripper.grasped().len()));
+    }
+
+    if let Some(width) = robot.gripper.jaw_width() {
+        ui.label(format!("Jaw width: {:.3}", width));
+    }
+
+    let tcp = robot.chain.end_effector(&robot.joints).translation.vector;
+    ui.label(format!("TCP: {:.3}, {:.3}, {:.3}", tcp.x, tcp.y, tcp.z));
+
+    let payload = robot.payload(&harness.objects);
+
+    if let Ok(torques) = InverseDynamics::default().gravity_torques(&robot.chain, &harness.objects, &robot.joints, payload.as_ref()) {
+        let torques: Vec<_> = torques.iter().map(|t| format!("{:.1}", t)).collect();
+        ui.label(format!("Gravity torques: {}", torques.join(", ")));
+    }
+
+    ui.separator();
+
+    egui::ComboBox::from_label("Language")
+        .selected_text(LANGUAGES[state.program_language])
+        .show_ui(ui, |ui| {
+            for (id, name) in LANGUAGES.iter().enumerate() {
+                ui.selectable_value(&mut state.program_language, id, *name);
+            }
+        });
+
+    ui.add(egui::TextEdit::multiline(&mut state.program_text)
+        .code_editor()
+        .desired_rows(12));
+
+    let mut check = None;
+    let mut plan_home = false;
+
+    ui.horizontal(|ui| {
+        if ui.button("Run").clicked() {
+            match load_program(state) {
+                Ok(imported) => {
+                    state.program_error = None;
+                    state.program_warnings = imported.warnings.iter().map(|w| w.to_string()).collect();
+                    install_program(robot, imported);
+                }
+                Err(err) => state.program_error = Some(err),
+            }
+        }
+
+        if ui.button("Check").clicked() {
+            match load_program(state) {
+                Ok(imported) => {
+                    let mut copy = robot.clone();
+                    install_program(&mut copy, imported);
+                    state.program_error = None;
+                    check = Some(copy);
+                }
+                Err(err) => state.program_error = Some(err),
+            }
+        }
+
+        plan_home = ui.button("Plan home").clicked();
+
+        if ui.button("Stop").clicked() {
+            robot.program = None;
+        }
+    });
+
+    if let Some(program) = &robot.program {
+        let line = program.current_line()
+            .map(|l| l.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        ui.label(format!("{:?}, line {}", program.state(), line));
+    }
+
+    let error = state.program_error.clone()
+        .or_else(|| robot.program_error.as_ref().map(|e| e.to_string()));
+
+    if let Some(error) = error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+
+    for warning in &state.program_warnings {
+        ui.colored_label(egui::Color32::YELLOW, warning);
+    }
+
+    if let Some(robot) = check {
+        state.program_check = Some(match harness.check_program(robot, CHECK_DURATION) {
+            Ok(None) => Ok(format!("No collision in the first {} s", CHECK_DURATION)),
+            Ok(Some(found)) => Err(format!(
+                "Line {}: object {} hits object {} at {:.2} s",
+                found.line.map(|l| l.to_string()).unwrap_or_else(|| "-".to_string()),
+                found.impact.object1.0.into_raw_parts().0,
+                found.impact.object2.0.into_raw_parts().0,
+                found.time,
+            )),
+            Err(err) => Err(err.to_string()),
+        });
+    }
+
+    if plan_home {
+        let home = harness.robots[handle].chain.zeros();
+
+        match harness.plan_to(handle, &home, &[]) {
+            Ok(path) => {
+                let mut program = Program::new("home");
+
+                for command in ptp_commands(&path, None) {
+                    program.push(command);
+                }
+
+                state.program_error = None;
+                harness.robots[handle].load_program(Interpreter::new(program));
+            }
+            Err(err) => state.program_error = Some(err.to_string()),
+        }
+    }
+
+    match &state.program_check {
+        Some(Ok(message)) => { ui.colored_label(egui::Color32::GREEN, message); }
+        Some(Err(message)) => { ui.colored_label(egui::Color32::RED, message); }
+        None => {}
+    }
+}
@@ -10,7 +10,11 @@ use crate::arc_ball::{ArcBall, ArcBallPlugin};
 use crate::render::{BevyMaterial, RenderManager};
 use crate::{ui, WorldPlugin};
 use bluster::mesh::{SceneObject, ObjectSet, ObjectHandle};
-use crate::harness::Harness;
+use bluster::errors::SignalError;
+use bluster::pipeline::distance_monitor::SafetyMargin;
+use bluster::signals::{Sensor, SignalKind, SignalValue};
+use crate::harness::{Harness, Robot, RobotHandle};
+use crate::harness::plugin::HarnessPlugin;
 use crate::synergy::SynergyState;
 
 // Flags for program states
@@ -41,10 +45,19 @@ pub enum RunMode {
 pub struct WorldState {
     pub running: RunMode,
     pub selected_object: Option<ObjectHandle>,
+    /// The robot the UI controls.
+    pub selected_robot: Option<RobotHandle>,
     pub program_names: Vec<&'static str>,
     pub selected_program: usize,
     pub state_flags: StateFlags,
     pub action_flags: ActionFlags,
+    pub program_text: String,
+    /// Language of `program_text`: 0 for bluster, or a vendor language in `LANGUAGES`.
+    pub program_language: usize,
+    pub program_error: Option<String>,
+    pub program_warnings: Vec<String>,
+    /// The result of the last program check, a message for success or failure.
+    pub program_check: Option<Result<String, String>>,
     camera_locked: bool,
 }
 
@@ -85,10 +98,16 @@ impl WorldApp {
         let state = WorldState {
             running: RunMode::Running,
             selected_object: None,
+            selected_robot: None,
             program_names: Vec::new(),
             selected_program: 0,
             state_flags,
             action_flags: ActionFlags::empty(),
+            program_text: String::new(),
+            program_language: 0,
+            program_error: None,
+            program_warnings: Vec::new(),
+            program_check: None,
             camera_locked: false
         };
 
@@ -188,6 +207,53 @@ impl<'a, 'b, 'c, 'd, 'e, 'f> World<'a, 'b, 'c, 'd, 'e, 'f> {
         self.state.action_flags.set(ActionFlags::RESET_WORLD_RENDER, true);
         dbg!(self.state.action_flags);
         self.state.selected_object = None;
+        self.state.selected_robot = None;
+    }
+
+    /// Adds a robot to the cell, the links of the robots already there are obstacles to it.
+    ///
+    /// A robot named like one already in the cell gets a numbered suffix.
+    pub fn add_robot(&mut self, mut robot: Robot) -> RobotHandle {
+        robot.filter_self_collisions(&mut self.harness.objects);
+        robot.spawn_tools(&mut self.harness.objects);
+        robot.apply(&mut self.harness.objects);
+        self.harness.objects.propagate_positions();
+        let handle = self.harness.robots.insert(robot);
+        self.harness.distances.set_monitored(self.harness.robots.objects());
+        self.harness.sync_frames();
+        self.harness.detect_collisions();
+        self.state.selected_robot.get_or_insert(handle);
+
+        handle
+    }
+
+    /// Monitors the clearance between the robot and `obstacle`.
+    pub fn add_obstacle(&mut self, obstacle: ObjectHandle, margin: SafetyMargin) {
+        self.harness.distances.add_obstacle(obstacle, margin);
+        self.harness.detect_collisions();
+    }
+
+    pub fn add_signal(&mut self, name: &str, kind: SignalKind, port: usize) -> Result<(), SignalError> {
+        self.harness.signals.insert(name, kind, port)
+    }
+
+    pub fn set_signal(&mut self, name: &str, value: impl Into<SignalValue>) -> Result<(), SignalError> {
+        self.harness.signals.set(name, value)
+    }
+
+    /// Adds a sensor, its signals have to be added first.
+    pub fn add_sensor(&mut self, sensor: impl Sensor + 'static) -> Result<(), SignalError> {
+        self.harness.add_sensor(sensor)
+    }
+
+    /// Adds a plugin run at the end of every step, like the cell components of `harness`. Its signals have to be
+    /// added first.
+    pub fn add_plugin(&mut self, plugin: impl HarnessPlugin + 'static) -> Result<(), SignalError> {
+        self.harness.add_plugin(plugin)
+    }
+
+    pub fn set_program_text(&mut self, text: &str) {
+        self.state.program_text = text.to_string();
     }
 
     pub fn handle_events(&mut self, keys: &Input<KeyCode>) {
@@ -332,6 +398,9 @@ fn update_world(
                 .set(ActionFlags::RESET_WORLD_RENDER, false);
 
             dbg!("from action flag");
+            harness.objects.changed_objects.clear();
+            harness.objects.removed_objects.clear();
+
             for (handle, _) in harness.objects.iter() {
                 render.add_object(
                     &mut commands,
@@ -355,6 +424,21 @@ fn update_world(
         }
     }
 
+    if state.running == RunMode::Running {
+        harness.step();
+    }
+
+    // Plugins may create and remove objects while the simulation runs
+    for handle in std::mem::take(&mut harness.objects.removed_objects) {
+        render.remove_object(&mut commands, handle);
+    }
+
+    for handle in std::mem::take(&mut harness.objects.changed_objects) {
+        if harness.objects.get(handle).is_some() && !render.contains(handle) {
+            render.add_object(&mut commands, meshes, materials, handle, &harness.objects);
+        }
+    }
+
     render.draw(
         &harness.objects,
         &mut components,